@@ -3,93 +3,308 @@ use bevy::prelude::*;
 use crate::{
     common::Direction,
     components::{Movable, Player, Position},
-    map::{MapType, TilePos, MAP},
+    map::{Map, MapType, TilePos},
+    nav::NavGraph,
 };
 
-use super::Ghost;
+use super::{
+    profile::{flanking_target, AiDice, AiProfile, CasualDecision, CasualReaction, PathDecision},
+    AiDecision, Ghost, GhostMode, GhostTarget,
+};
+use crate::movement::NextDirection;
+use crate::party::{Controls, PlayerIndex};
 
-#[derive(Component)]
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
 pub struct Blinky;
 
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub fn blinky_ai(
     time: Res<Time>,
+    profile: Res<AiProfile>,
+    map: Res<Map>,
+    nav: Res<NavGraph>,
+    mut casual_reaction: ResMut<CasualReaction>,
+    mut dice: ResMut<AiDice>,
     mut blinky_query: Query<(
-        &mut Movable,
-        &mut Position,
-        &Ghost,
+        &Movable,
+        &Position,
+        &GhostMode,
+        &mut GhostTarget,
+        &mut NextDirection,
+        Option<&Controls>,
         &Blinky,
         Without<Player>,
     )>,
-    pacman_query: Query<(&Position, &Player, Without<Ghost>)>,
+    pacman_query: Query<
+        (&Position, &Movable, Option<&PlayerIndex>),
+        (With<Player>, Without<Ghost>),
+    >,
 ) {
-    let (pacman_pos, _, _) = pacman_query
-        .get_single()
-        .expect("There should never be more than 1 player");
-
-    let delta = time.delta().as_secs_f32();
-    for (mut blinky_movable, mut blinky_pos, _, _, _) in &mut blinky_query {
-        handle_ai_decision(
-            BlinkyDecision {
-                pacman_pos: pacman_pos.clone(),
-            },
-            delta,
-            &mut blinky_pos,
-            &mut blinky_movable,
-        );
+    let ghost_tiles = blinky_query
+        .iter()
+        .map(|(_, pos, ..)| pos.0.clone())
+        .collect::<Vec<TilePos>>();
+    // With several Pac-Men around, Blinky goes after the closest one, the
+    // first player's when they are as close.
+    let Some((pacman_pos, pacman_movable, _)) =
+        pacman_query.iter().min_by_key(|(pos, _, index)| {
+            let distance = ghost_tiles
+                .first()
+                .map_or(0, |ghost_tile| pos.0.dist_sq_to(ghost_tile));
+            (distance, index.map_or(0, |index| index.0))
+        })
+    else {
+        return;
+    };
+    // Casual ghosts only notice where Pac-Man went every so often.
+    let seen_pacman = match *profile {
+        AiProfile::Casual => casual_reaction.target(time.delta(), &pacman_pos.0),
+        _ => pacman_pos.0.clone(),
+    };
+
+    for (blinky_movable, blinky_pos, mode, mut ghost_target, mut next, controls, _, _) in
+        &mut blinky_query
+    {
+        // A player drives this one, but for going home once eaten.
+        if controls.is_some() && *mode != GhostMode::Eaten {
+            continue;
+        }
+
+        let target = match mode {
+            GhostMode::Chase => Some(match *profile {
+                AiProfile::Arcade | AiProfile::Casual => seen_pacman.clone(),
+                AiProfile::Relentless => flanking_target(
+                    &nav,
+                    &blinky_pos.0,
+                    &ghost_tiles,
+                    &pacman_pos.0,
+                    &pacman_movable.direction,
+                ),
+            }),
+            GhostMode::Frightened => None,
+            GhostMode::Eaten => Some(map.ghost_start.clone()),
+        };
+        if ghost_target.0 != target {
+            ghost_target.0 = target.clone();
+        }
+
+        // The direction to take at a tile is chosen one tile in advance, as
+        // the arcade does, when entering the tile before it.
+        if next.0.is_some() {
+            continue;
+        }
+        let decision_maker = match (target, *profile) {
+            (None, _) => GhostDecision::Flee(FleeDecision {
+                threat: pacman_pos.0.clone(),
+            }),
+            (Some(target), AiProfile::Arcade) => GhostDecision::Arcade(BlinkyDecision { target }),
+            (Some(target), AiProfile::Relentless) => {
+                GhostDecision::Path(PathDecision { target, nav: &nav })
+            }
+            (Some(target), AiProfile::Casual) => GhostDecision::Casual(CasualDecision {
+                target,
+                throw: dice.throw(),
+            }),
+        };
+        let decision_pos = Position(blinky_movable.target_tile.clone());
+        let (_, dir) = decision_maker.take_decision(&map, &decision_pos, blinky_movable, mode);
+        next.0 = Some(dir);
     }
 }
 
-trait AiDecision {
-    fn take_decision(&self, position: &Position, movable: &Movable) -> (TilePos, Direction);
+/// Heads for [target], Pac-Man himself when chasing.
+struct BlinkyDecision {
+    target: TilePos,
 }
 
-fn handle_ai_decision(
-    decision_maker: impl AiDecision,
-    delta: f32,
-    position: &mut Position,
-    movable: &mut Movable,
-) {
-    let percent = movable.base_speed * delta;
-    movable.progress = movable.progress + percent;
-
-    if movable.progress >= 1.0 {
-        movable.progress = 0.0;
-        position.0 = movable.target_tile.clone();
+/// Runs away from [threat] while frightened.
+struct FleeDecision {
+    threat: TilePos,
+}
 
-        let (new_pos, new_dir) = decision_maker.take_decision(position, movable);
+/// The decision a ghost takes this frame, depending on its mode and the
+/// [AiProfile].
+enum GhostDecision<'a> {
+    Arcade(BlinkyDecision),
+    Flee(FleeDecision),
+    Path(PathDecision<'a>),
+    Casual(CasualDecision),
+}
 
-        if !MAP.is_wall(&new_pos) {
-            movable.target_tile = new_pos;
-            movable.direction = new_dir;
+impl AiDecision for GhostDecision<'_> {
+    fn take_decision(
+        &self,
+        map: &Map,
+        position: &Position,
+        movable: &Movable,
+        mode: &GhostMode,
+    ) -> (TilePos, Direction) {
+        match self {
+            GhostDecision::Arcade(decision) => decision.take_decision(map, position, movable, mode),
+            GhostDecision::Flee(decision) => decision.take_decision(map, position, movable, mode),
+            GhostDecision::Path(decision) => decision.take_decision(map, position, movable, mode),
+            GhostDecision::Casual(decision) => decision.take_decision(map, position, movable, mode),
         }
     }
 }
 
-struct BlinkyDecision {
-    pacman_pos: Position,
+/// The tiles a ghost standing at [position] may move to next.
+pub fn ghost_moves(
+    map: &Map,
+    position: &Position,
+    movable: &Movable,
+    mode: &GhostMode,
+) -> Vec<(TilePos, Direction)> {
+    map.get_empty_neighbours(position)
+        // Avoid doing 180s
+        .filter(|(_, dir)| !movable.direction.opposite().eq(dir))
+        // Avoid entering nest
+        .filter(|(pos, _)| !matches!(map.get_at(pos), MapType::GhostOnlyBarrier))
+        // Chasing ghosts never turn up at a few intersections
+        .filter(|(_, dir)| {
+            !(*mode == GhostMode::Chase
+                && *dir == Direction::Up
+                && map.no_up_tiles.contains(position))
+        })
+        .collect::<Vec<(TilePos, Direction)>>()
 }
 
 impl AiDecision for BlinkyDecision {
-    fn take_decision(&self, position: &Position, movable: &Movable) -> (TilePos, Direction) {
-        let mut neighbours = MAP
-            .get_empty_neighbours(position)
+    fn take_decision(
+        &self,
+        map: &Map,
+        position: &Position,
+        movable: &Movable,
+        mode: &GhostMode,
+    ) -> (TilePos, Direction) {
+        ghost_moves(map, position, movable, mode)
+            .into_iter()
+            .min_by_key(|(pos, dir)| (pos.dist_sq_to(&self.target), dir.arcade_priority()))
+            .expect("Blinky has nowhere to go")
+    }
+}
+
+impl AiDecision for FleeDecision {
+    fn take_decision(
+        &self,
+        map: &Map,
+        position: &Position,
+        movable: &Movable,
+        mode: &GhostMode,
+    ) -> (TilePos, Direction) {
+        ghost_moves(map, position, movable, mode)
             .into_iter()
-            // Avoid doing 180s
-            .filter(|(_, dir)| !movable.direction.opposite().eq(dir))
-            // Avoid entering nest
-            .filter(|(pos, _)| match MAP.get_at(pos) {
-                MapType::GhostOnlyBarrier => false,
-                _ => true,
-            })
-            .collect::<Vec<(TilePos, Direction)>>();
-
-        neighbours.sort_by(|(a, _), (b, _)| {
-            let dist_a = a.dist_to(&self.pacman_pos);
-            let dist_b = b.dist_to(&self.pacman_pos);
-
-            dist_b.partial_cmp(&dist_a).unwrap()
-        });
-
-        neighbours.pop().expect("Blinky has nowhere to go")
+            .min_by_key(|(pos, dir)| (-pos.dist_sq_to(&self.threat), dir.arcade_priority()))
+            .expect("Frightened Blinky has nowhere to flee")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A crossroads in the middle of the maze.
+    const CROSSROADS: [&str; 5] = ["#####", "##.##", "#...#", "##.##", "#####"];
+    const CENTRE: TilePos = TilePos { x: 2, y: 2 };
+
+    /// Blinky at the crossroads, having come in from the right.
+    fn heading_left() -> (Position, Movable) {
+        let movable = Movable::new(TilePos { x: 1, y: 2 }, 0.0, 1.0, Direction::Left);
+        (Position(CENTRE), movable)
+    }
+
+    fn chase(map: &Map, target: TilePos) -> Direction {
+        let (position, movable) = heading_left();
+        let decision = BlinkyDecision { target };
+        decision
+            .take_decision(map, &position, &movable, &GhostMode::Chase)
+            .1
+    }
+
+    #[test]
+    fn ties_go_up_then_left_then_down() {
+        let map = Map::from_layout(&CROSSROADS).unwrap();
+        // As far from the tiles up, left and down.
+        assert_eq!(chase(&map, CENTRE), Direction::Up);
+        // As far from the tiles left and down.
+        assert_eq!(chase(&map, TilePos { x: 1, y: 3 }), Direction::Left);
+    }
+
+    #[test]
+    fn no_up_tiles_only_hold_back_chasing_ghosts() {
+        let mut map = Map::from_layout(&CROSSROADS).unwrap();
+        map.no_up_tiles = vec![CENTRE];
+        let above = TilePos { x: 2, y: 0 };
+        assert_eq!(chase(&map, above.clone()), Direction::Left);
+
+        let (position, movable) = heading_left();
+        let moves = |mode| {
+            ghost_moves(&map, &position, &movable, &mode)
+                .into_iter()
+                .map(|(_, dir)| dir)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(moves(GhostMode::Chase), [Direction::Down, Direction::Left]);
+        assert_eq!(
+            moves(GhostMode::Frightened),
+            [Direction::Up, Direction::Down, Direction::Left]
+        );
+        let flee = FleeDecision {
+            threat: TilePos { x: 2, y: 4 },
+        };
+        let (_, dir) = flee.take_decision(&map, &position, &movable, &GhostMode::Frightened);
+        assert_eq!(dir, Direction::Up);
+    }
+
+    const DICE_SEED: u64 = 3;
+
+    /// Blinky on his way into the corner below Pac-Man, from a corridor he
+    /// couldn't turn in.
+    fn cornering(profile: AiProfile) -> (World, Entity) {
+        let map = Map::from_layout(&["#######", "###.###", "#.....#", "#######"]).unwrap();
+        let mut world = World::new();
+        world.insert_resource(Time::default());
+        world.insert_resource(profile);
+        world.insert_resource(NavGraph::new(&map));
+        world.insert_resource(map);
+        world.init_resource::<CasualReaction>();
+        world.insert_resource(AiDice::new(DICE_SEED));
+        world.spawn((
+            Position(TilePos { x: 3, y: 1 }),
+            Movable::new(TilePos { x: 3, y: 1 }, 0.0, 1.0, Direction::Left),
+            Player,
+        ));
+        let blinky = world
+            .spawn((
+                Position(TilePos { x: 4, y: 2 }),
+                Movable::new(TilePos { x: 3, y: 2 }, 0.5, 1.0, Direction::Left),
+                GhostMode::Chase,
+                GhostTarget::default(),
+                NextDirection::default(),
+                Blinky,
+            ))
+            .id();
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems(blinky_ai);
+        schedule.run(&mut world);
+        (world, blinky)
+    }
+
+    #[test]
+    fn decides_at_the_tile_ahead() {
+        let (world, blinky) = cornering(AiProfile::Arcade);
+        let next = world.get::<NextDirection>(blinky).unwrap();
+        assert_eq!(next.0, Some(Direction::Up));
+    }
+
+    #[test]
+    fn only_casual_ghosts_throw_dice() {
+        let next_throw = |profile| cornering(profile).0.resource_mut::<AiDice>().throw();
+        let first_throw = AiDice::new(DICE_SEED).throw();
+        assert_eq!(next_throw(AiProfile::Arcade), first_throw);
+        assert_eq!(next_throw(AiProfile::Relentless), first_throw);
+        assert_ne!(next_throw(AiProfile::Casual), first_throw);
     }
 }
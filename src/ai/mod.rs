@@ -1,6 +1,135 @@
 use bevy::prelude::*;
+use serde::Serialize;
+
+use crate::common::Direction;
+use crate::components::{Movable, Position};
+use crate::events::PelletEaten;
+use crate::map::{Map, TilePos};
+use crate::movement::NextDirection;
 
 pub mod blinky;
+pub mod profile;
+
+const FRIGHTENED_DURATION_SECS: f32 = 6.0;
 
-#[derive(Component)]
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
 pub struct Ghost;
+
+/// Picks the tile a ghost moves to next, and the direction to get there.
+pub trait AiDecision {
+    fn take_decision(
+        &self,
+        map: &Map,
+        position: &Position,
+        movable: &Movable,
+        mode: &GhostMode,
+    ) -> (TilePos, Direction);
+}
+
+/// The tile the ghost is currently heading for.
+/// Frightened ghosts have no target as they only flee from Pac-Man.
+#[derive(Component, Default, Debug, Reflect)]
+#[reflect(Component)]
+pub struct GhostTarget(pub Option<TilePos>);
+
+/// The colour identifying a ghost, used by debugging views.
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct GhostColor(pub Color);
+
+#[derive(Component, Clone, Debug, Default, PartialEq, Reflect, Serialize)]
+#[reflect(Component)]
+#[serde(rename_all = "lowercase")]
+pub enum GhostMode {
+    #[default]
+    Chase,
+    /// After Pac-Man ate a power pellet, the ghost flees and can be eaten.
+    Frightened,
+    /// Only the eyes are left, returning to the ghost house.
+    Eaten,
+}
+
+impl GhostMode {
+    pub fn speed_multiplier(&self) -> f32 {
+        match self {
+            GhostMode::Chase => 1.0,
+            GhostMode::Frightened => 0.5,
+            GhostMode::Eaten => 2.0,
+        }
+    }
+}
+
+#[derive(Resource, Deref, DerefMut, Reflect)]
+#[reflect(Resource)]
+pub struct FrightenedTimer(Timer);
+
+impl Default for FrightenedTimer {
+    fn default() -> Self {
+        let mut timer = Timer::from_seconds(FRIGHTENED_DURATION_SECS, TimerMode::Once);
+        timer.pause();
+        Self(timer)
+    }
+}
+
+/// Eating a power pellet frightens the ghosts, which immediately turn around.
+pub fn frighten_ghosts(
+    mut pellet_events: EventReader<PelletEaten>,
+    mut timer: ResMut<FrightenedTimer>,
+    mut query: Query<
+        (
+            &mut GhostMode,
+            &mut Movable,
+            &mut Position,
+            &mut NextDirection,
+        ),
+        With<Ghost>,
+    >,
+) {
+    if !pellet_events.iter().any(|event| event.power) {
+        return;
+    }
+
+    timer.reset();
+    timer.unpause();
+
+    for (mut mode, mut movable, mut position, mut next) in &mut query {
+        if *mode == GhostMode::Eaten {
+            continue;
+        }
+        *mode = GhostMode::Frightened;
+        next.0 = None;
+
+        if movable.target_tile != position.0 {
+            let previous_tile = std::mem::replace(&mut position.0, movable.target_tile.clone());
+            movable.target_tile = previous_tile;
+            movable.progress = 1.0 - movable.progress;
+        }
+        movable.direction = movable.direction.opposite();
+    }
+}
+
+pub fn end_frightened(
+    time: Res<Time>,
+    mut timer: ResMut<FrightenedTimer>,
+    mut query: Query<&mut GhostMode, With<Ghost>>,
+) {
+    timer.tick(time.delta());
+    if !timer.just_finished() {
+        return;
+    }
+
+    for mut mode in &mut query {
+        if *mode == GhostMode::Frightened {
+            *mode = GhostMode::Chase;
+        }
+    }
+}
+
+pub fn return_home(map: Res<Map>, mut query: Query<(&mut GhostMode, &Position), With<Ghost>>) {
+    for (mut mode, position) in &mut query {
+        if *mode == GhostMode::Eaten && position.0 == map.ghost_start {
+            *mode = GhostMode::Chase;
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    common::Direction,
+    components::{Movable, Position},
+    map::{Map, TilePos},
+    nav::NavGraph,
+};
+
+use super::{blinky::ghost_moves, AiDecision, GhostMode};
+
+/// How often casual ghosts turn a random way instead of the best one.
+const CASUAL_RANDOM_TURN_CHANCE: f32 = 0.3;
+/// How long casual ghosts take to notice where Pac-Man went.
+const CASUAL_REACTION_SECS: f32 = 1.0;
+
+/// How the ghosts find their way to Pac-Man.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AiProfile {
+    /// The arcade rules: take the tile closest to the target as the crow flies.
+    #[default]
+    Arcade,
+    /// Follow the shortest path through the maze, the ghosts spreading out to
+    /// cut Pac-Man off once there are several, see [flanking_target].
+    Relentless,
+    /// Turn a random way now and then and only react late to Pac-Man's moves.
+    Casual,
+}
+
+impl AiProfile {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "arcade" => Some(AiProfile::Arcade),
+            "relentless" => Some(AiProfile::Relentless),
+            "casual" => Some(AiProfile::Casual),
+            _ => None,
+        }
+    }
+}
+
+/// The tile a relentless ghost at [ghost] chases: the ghost closest to
+/// Pac-Man goes straight for him while the others cut him off, alternately at
+/// the junction ahead of him and the one behind him.
+///
+/// Blinky being the only ghost for now, he is always the closest one and
+/// always goes straight for Pac-Man: the flanking is there for the ghosts
+/// still to come.
+pub fn flanking_target(
+    nav: &NavGraph,
+    ghost: &TilePos,
+    ghosts: &[TilePos],
+    pacman: &TilePos,
+    pacman_direction: &Direction,
+) -> TilePos {
+    let distance = |tile: &TilePos| nav.distance(tile, pacman).unwrap_or(u32::MAX);
+    let rank = ghosts
+        .iter()
+        .filter(|other| distance(other) < distance(ghost))
+        .count();
+
+    let junction = match rank {
+        0 => None,
+        rank if rank % 2 == 1 => nav.junction_ahead(pacman, pacman_direction),
+        _ => nav.junction_ahead(pacman, &pacman_direction.opposite()),
+    };
+    junction.unwrap_or_else(|| pacman.clone())
+}
+
+/// Where casual ghosts believe Pac-Man is, only updated every so often.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct CasualReaction {
+    timer: Timer,
+    target: Option<TilePos>,
+}
+
+impl Default for CasualReaction {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(CASUAL_REACTION_SECS, TimerMode::Repeating),
+            target: None,
+        }
+    }
+}
+
+impl CasualReaction {
+    pub fn target(&mut self, delta: std::time::Duration, pacman: &TilePos) -> TilePos {
+        self.timer.tick(delta);
+        if self.timer.just_finished() || self.target.is_none() {
+            self.target = Some(pacman.clone());
+        }
+        self.target.clone().unwrap_or_else(|| pacman.clone())
+    }
+}
+
+/// Heads for [target] along the shortest path through the maze.
+pub struct PathDecision<'a> {
+    pub target: TilePos,
+    pub nav: &'a NavGraph,
+}
+
+impl AiDecision for PathDecision<'_> {
+    fn take_decision(
+        &self,
+        map: &Map,
+        position: &Position,
+        movable: &Movable,
+        mode: &GhostMode,
+    ) -> (TilePos, Direction) {
+        ghost_moves(map, position, movable, mode)
+            .into_iter()
+            .min_by_key(|(pos, dir)| {
+                (
+                    self.nav.distance(pos, &self.target).unwrap_or(u32::MAX),
+                    pos.dist_sq_to(&self.target),
+                    dir.arcade_priority(),
+                )
+            })
+            .expect("A relentless ghost has nowhere to go")
+    }
+}
+
+/// The dice the casual ghosts throw, kept as a seed so that every machine of
+/// a networked game throws the same ones.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct AiDice {
+    seed: u64,
+}
+
+impl Default for AiDice {
+    fn default() -> Self {
+        Self::new(fastrand::u64(..))
+    }
+}
+
+impl AiDice {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// A throw deciding whether a casual ghost turns a random way, and which.
+    pub fn throw(&mut self) -> (f32, usize) {
+        let mut rng = fastrand::Rng::with_seed(self.seed);
+        let throw = (rng.f32(), rng.usize(..));
+        self.seed = rng.get_seed();
+        throw
+    }
+}
+
+/// Heads for [target] like the arcade ghosts, but turns a random way every
+/// now and then, as the [AiDice] [throw] says.
+pub struct CasualDecision {
+    pub target: TilePos,
+    pub throw: (f32, usize),
+}
+
+impl AiDecision for CasualDecision {
+    fn take_decision(
+        &self,
+        map: &Map,
+        position: &Position,
+        movable: &Movable,
+        mode: &GhostMode,
+    ) -> (TilePos, Direction) {
+        let mut moves = ghost_moves(map, position, movable, mode);
+        let (chance, pick) = self.throw;
+        // A ghost with nowhere to go fails below, as with the other decisions.
+        if chance < CASUAL_RANDOM_TURN_CHANCE && !moves.is_empty() {
+            return moves.swap_remove(pick % moves.len());
+        }
+
+        moves
+            .into_iter()
+            .min_by_key(|(pos, dir)| (pos.dist_sq_to(&self.target), dir.arcade_priority()))
+            .expect("A casual ghost has nowhere to go")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn casual_ghosts_react_late() {
+        let mut reaction = CasualReaction::default();
+        let (before, after) = (TilePos { x: 1, y: 1 }, TilePos { x: 5, y: 1 });
+        assert_eq!(reaction.target(Duration::ZERO, &before), before);
+
+        let half = Duration::from_secs_f32(CASUAL_REACTION_SECS / 2.0);
+        assert_eq!(reaction.target(half, &after), before);
+        assert_eq!(reaction.target(half, &after), after);
+    }
+
+    #[test]
+    fn casual_ghosts_turn_a_random_way_now_and_then() {
+        let map = Map::from_layout(&["#####", "##.##", "#...#", "##.##", "#####"]).unwrap();
+        // At a crossroads, having come in from the right, with Pac-Man up.
+        let position = Position(TilePos { x: 2, y: 2 });
+        let movable = Movable::new(TilePos { x: 1, y: 2 }, 0.0, 1.0, Direction::Left);
+        let target = TilePos { x: 2, y: 0 };
+
+        let mut dice = AiDice::new(7);
+        let decisions = 3000;
+        let wrong_turns = (0..decisions)
+            .filter(|_| {
+                let decision = CasualDecision {
+                    target: target.clone(),
+                    throw: dice.throw(),
+                };
+                let (_, dir) = decision.take_decision(&map, &position, &movable, &GhostMode::Chase);
+                dir != Direction::Up
+            })
+            .count();
+        // A random turn is the right one a third of the time.
+        let rate = wrong_turns as f32 / decisions as f32;
+        let expected = CASUAL_RANDOM_TURN_CHANCE * 2.0 / 3.0;
+        assert!((rate - expected).abs() < 0.03, "{rate} wrong turns");
+    }
+
+    #[test]
+    fn the_same_dice_throw_the_same() {
+        let (mut dice, mut again) = (AiDice::new(42), AiDice::new(42));
+        for _ in 0..10 {
+            assert_eq!(dice.throw(), again.throw());
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use bevy::prelude::*;
+
+use crate::ai::Ghost;
+use crate::common::Direction;
+use crate::components::{Movable, Player, Position, SpriteSheets};
+use crate::config::GameConfig;
+use crate::events::PacmanDied;
+use crate::game_state::GameState;
+use crate::map::{Map, MapType, OpenContent, TilePos};
+use crate::movement::NextDirection;
+use crate::party::PartyMode;
+use crate::players::Players;
+use crate::text::ArcadeTextBundle;
+
+struct RollCallEntry {
+    character: &'static str,
+    nickname: &'static str,
+    color: Color,
+}
+
+const ROLL_CALL_GHOSTS: [RollCallEntry; 4] = [
+    RollCallEntry {
+        character: "-SHADOW",
+        nickname: "\"BLINKY\"",
+        color: Color::rgb(1.0, 0.0, 0.0),
+    },
+    RollCallEntry {
+        character: "-SPEEDY",
+        nickname: "\"PINKY\"",
+        color: Color::rgb(1.0, 0.72, 1.0),
+    },
+    RollCallEntry {
+        character: "-BASHFUL",
+        nickname: "\"INKY\"",
+        color: Color::rgb(0.0, 1.0, 1.0),
+    },
+    RollCallEntry {
+        character: "-POKEY",
+        nickname: "\"CLYDE\"",
+        color: Color::rgb(1.0, 0.72, 0.32),
+    },
+];
+const POINTS_TABLE: [(MapType, &str); 2] = [
+    (MapType::Open(OpenContent::Food), "10 PTS"),
+    (MapType::Open(OpenContent::GhostEater), "50 PTS"),
+];
+
+/// Number of steps in the attract sequence: one per ghost, one for the
+/// points table and a few empty steps to let the player read it.
+const ATTRACT_STEPS: usize = ROLL_CALL_GHOSTS.len() + 4;
+const DEMO_DURATION_SECS: f32 = 30.0;
+
+#[derive(Component)]
+pub struct AttractEntity;
+
+#[derive(Resource)]
+pub struct AttractSequence {
+    timer: Timer,
+    step: usize,
+}
+
+#[derive(Resource, Deref, DerefMut)]
+pub struct DemoTimer(Timer);
+
+/// Pac-Man is driven by the computer rather than the keyboard.
+#[derive(Component, Default)]
+pub struct DemoPlayer {
+    decided_for: Option<TilePos>,
+}
+
+pub fn start_attract(mut commands: Commands) {
+    commands.insert_resource(AttractSequence {
+        timer: Timer::from_seconds(1.0, TimerMode::Repeating),
+        step: 0,
+    });
+
+    commands.spawn((
+        ArcadeTextBundle::new("CHARACTER / NICKNAME", TilePos { x: 7, y: 3 }, Color::WHITE),
+        AttractEntity,
+    ));
+}
+
+pub fn roll_call(
+    mut commands: Commands,
+    time: Res<Time>,
+    sprite_sheets: Res<SpriteSheets>,
+    mut sequence: ResMut<AttractSequence>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    sequence.timer.tick(time.delta());
+    if !sequence.timer.just_finished() {
+        return;
+    }
+
+    let step = sequence.step;
+    sequence.step += 1;
+
+    if let Some(entry) = ROLL_CALL_GHOSTS.get(step) {
+        let y = 5 + 3 * step as i32;
+        spawn_attract_sprite(
+            &mut commands,
+            sprite_sheets.characters.clone(),
+            sprite_sheets.manifest.roll_call.sprites()[step],
+            TilePos { x: 4, y },
+        );
+        commands.spawn((
+            ArcadeTextBundle::new(entry.character, TilePos { x: 7, y }, entry.color),
+            AttractEntity,
+        ));
+        commands.spawn((
+            ArcadeTextBundle::new(entry.nickname, TilePos { x: 18, y }, entry.color),
+            AttractEntity,
+        ));
+    } else if step == ROLL_CALL_GHOSTS.len() {
+        for (i, (tile, points)) in POINTS_TABLE.iter().enumerate() {
+            let y = 20 + 2 * i as i32;
+            spawn_attract_sprite(
+                &mut commands,
+                sprite_sheets.maze.clone(),
+                sprite_sheets.manifest.tile_index(tile),
+                TilePos { x: 10, y },
+            );
+            commands.spawn((
+                ArcadeTextBundle::new(*points, TilePos { x: 12, y }, Color::WHITE),
+                AttractEntity,
+            ));
+        }
+    } else if step >= ATTRACT_STEPS {
+        next_state.set(GameState::Demo);
+    }
+}
+
+fn spawn_attract_sprite(
+    commands: &mut Commands,
+    texture_atlas: Handle<TextureAtlas>,
+    sprite_index: usize,
+    tile_pos: TilePos,
+) {
+    let pos = tile_pos.to_display_pos();
+    commands.spawn((
+        SpriteSheetBundle {
+            texture_atlas,
+            sprite: TextureAtlasSprite::new(sprite_index),
+            transform: Transform::from_translation(Vec3::new(pos.x, -pos.y, 0.0)),
+            ..default()
+        },
+        AttractEntity,
+    ));
+}
+
+pub fn start_demo(mut commands: Commands, player_query: Query<Entity, With<Player>>) {
+    commands.insert_resource(DemoTimer(Timer::from_seconds(
+        DEMO_DURATION_SECS,
+        TimerMode::Once,
+    )));
+
+    for player in player_query.iter() {
+        commands.entity(player).insert(DemoPlayer::default());
+    }
+}
+
+/// The demo ends after a while, or as soon as Pac-Man gets caught.
+pub fn end_demo(
+    time: Res<Time>,
+    mut timer: ResMut<DemoTimer>,
+    mut died_events: EventReader<PacmanDied>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    timer.tick(time.delta());
+    if timer.just_finished() || died_events.iter().count() > 0 {
+        next_state.set(GameState::Attract);
+    }
+}
+
+/// Any key press during the attract loop or the demo starts a real game, for
+/// two players if that key is 2 or a [PartyMode] was asked for.
+pub fn start_game_on_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    party: Res<PartyMode>,
+    config: Res<GameConfig>,
+    mut players: ResMut<Players>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.get_just_pressed().next().is_some() {
+        *players = if *party != PartyMode::Arcade {
+            Players::party(*party, &config)
+        } else if keyboard_input.just_pressed(KeyCode::Key2) {
+            Players::new(2, &config)
+        } else {
+            Players::new(1, &config)
+        };
+        next_state.set(GameState::Playing);
+    }
+}
+
+pub fn despawn_with<T: Component>(mut commands: Commands, query: Query<Entity, With<T>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Steers the demo Pac-Man, picking at every tile the way that keeps it the
+/// furthest away from the closest ghost.
+pub fn demo_steer(
+    map: Res<Map>,
+    mut player_query: Query<(&Movable, &mut NextDirection, &mut DemoPlayer), With<Player>>,
+    ghost_query: Query<&Position, With<Ghost>>,
+) {
+    for (movable, mut next, mut demo) in &mut player_query {
+        if demo.decided_for.as_ref() == Some(&movable.target_tile) {
+            continue;
+        }
+
+        let options = map
+            .get_empty_neighbours(&movable.target_tile)
+            .filter(|(pos, _)| map.get_at(pos) != MapType::GhostOnlyBarrier)
+            .collect::<Vec<(TilePos, Direction)>>();
+
+        let forward_options = options
+            .iter()
+            .filter(|(_, dir)| *dir != movable.direction.opposite())
+            .collect::<Vec<&(TilePos, Direction)>>();
+
+        // Only turn back when stuck in a dead end.
+        let candidates = if forward_options.is_empty() {
+            options.iter().collect()
+        } else {
+            forward_options
+        };
+
+        let closest_ghost_dist = |pos: &TilePos| {
+            ghost_query
+                .iter()
+                .map(|ghost_pos| pos.dist_sq_to(ghost_pos))
+                .min()
+                .unwrap_or(i32::MAX)
+        };
+
+        let best = candidates
+            .into_iter()
+            .max_by_key(|(pos, _)| closest_ghost_dist(pos));
+
+        if let Some((_, dir)) = best {
+            next.0 = Some(dir.clone());
+        }
+        demo.decided_for = Some(movable.target_tile.clone());
+    }
+}
@@ -0,0 +1,224 @@
+use std::{sync::Arc, time::Duration};
+
+use bevy::{
+    audio::Source,
+    prelude::*,
+    reflect::{TypePath, TypeUuid},
+};
+
+use crate::ai::{Ghost, GhostMode};
+use crate::components::Pellet;
+use crate::events::{PacmanDied, PelletEaten};
+use crate::hud::ReadyText;
+use crate::map::Map;
+
+mod sounds;
+mod wsg;
+
+/// How much faster the siren gets once every pellet has been eaten.
+const SIREN_MAX_SPEEDUP: f32 = 0.6;
+
+/// A sound synthesised in-process rather than loaded from a file.
+#[derive(TypeUuid, TypePath, Clone)]
+#[uuid = "6f5d3c0e-7a0b-4c4e-9a57-2b8f4f2c1d90"]
+pub struct SynthSound {
+    samples: Arc<[f32]>,
+}
+
+impl SynthSound {
+    fn new(samples: Vec<f32>) -> Self {
+        Self {
+            samples: samples.into(),
+        }
+    }
+}
+
+pub struct SynthDecoder {
+    samples: Arc<[f32]>,
+    position: usize,
+}
+
+impl Iterator for SynthDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.samples.get(self.position).copied();
+        self.position += 1;
+        sample
+    }
+}
+
+impl Source for SynthDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        wsg::SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f32(
+            self.samples.len() as f32 / wsg::SAMPLE_RATE as f32,
+        ))
+    }
+}
+
+impl Decodable for SynthSound {
+    type DecoderItem = f32;
+    type Decoder = SynthDecoder;
+
+    fn decoder(&self) -> Self::Decoder {
+        SynthDecoder {
+            samples: self.samples.clone(),
+            position: 0,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct Sounds {
+    chomp: [Handle<SynthSound>; 2],
+    siren: Handle<SynthSound>,
+    frightened: Handle<SynthSound>,
+    eyes_returning: Handle<SynthSound>,
+    death: Handle<SynthSound>,
+    intro: Handle<SynthSound>,
+}
+
+/// Marks every sound played during a game, stopped when the game ends.
+#[derive(Component)]
+pub struct GameSound;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum BackgroundKind {
+    Siren,
+    Frightened,
+    EyesReturning,
+}
+
+/// The sound looping in the background while playing.
+#[derive(Component)]
+pub struct BackgroundLoop(BackgroundKind);
+
+#[derive(Resource, Default)]
+pub struct ChompState {
+    first_half: bool,
+}
+
+pub fn synthesise_sounds(mut commands: Commands, mut assets: ResMut<Assets<SynthSound>>) {
+    let mut add = |samples| assets.add(SynthSound::new(samples));
+
+    commands.insert_resource(Sounds {
+        chomp: [add(sounds::chomp(true)), add(sounds::chomp(false))],
+        siren: add(sounds::siren()),
+        frightened: add(sounds::frightened()),
+        eyes_returning: add(sounds::eyes_returning()),
+        death: add(sounds::death()),
+        intro: add(sounds::intro()),
+    });
+}
+
+fn play(commands: &mut Commands, source: &Handle<SynthSound>, settings: PlaybackSettings) {
+    commands.spawn((
+        AudioSourceBundle {
+            source: source.clone(),
+            settings,
+        },
+        GameSound,
+    ));
+}
+
+pub fn play_intro(mut commands: Commands, sounds: Res<Sounds>) {
+    play(&mut commands, &sounds.intro, PlaybackSettings::DESPAWN);
+}
+
+pub fn play_chomp(
+    mut commands: Commands,
+    sounds: Res<Sounds>,
+    mut chomp: ResMut<ChompState>,
+    mut pellet_events: EventReader<PelletEaten>,
+) {
+    for _ in pellet_events.iter() {
+        chomp.first_half = !chomp.first_half;
+        let index = if chomp.first_half { 0 } else { 1 };
+        play(
+            &mut commands,
+            &sounds.chomp[index],
+            PlaybackSettings::DESPAWN,
+        );
+    }
+}
+
+pub fn play_death(
+    mut commands: Commands,
+    sounds: Res<Sounds>,
+    mut died_events: EventReader<PacmanDied>,
+) {
+    if died_events.iter().count() > 0 {
+        play(&mut commands, &sounds.death, PlaybackSettings::DESPAWN);
+    }
+}
+
+/// Picks the background loop matching the ghosts' state, starting it once the
+/// intro tune is over, and raises the siren's pitch as pellets get eaten.
+pub fn update_background_loop(
+    mut commands: Commands,
+    sounds: Res<Sounds>,
+    ready_query: Query<(), With<ReadyText>>,
+    ghost_query: Query<&GhostMode, With<Ghost>>,
+    map: Res<Map>,
+    pellet_query: Query<(), With<Pellet>>,
+    loop_query: Query<(Entity, &BackgroundLoop, Option<&AudioSink>)>,
+) {
+    if !ready_query.is_empty() {
+        return;
+    }
+
+    let kind = if ghost_query.iter().any(|mode| *mode == GhostMode::Eaten) {
+        BackgroundKind::EyesReturning
+    } else if ghost_query
+        .iter()
+        .any(|mode| *mode == GhostMode::Frightened)
+    {
+        BackgroundKind::Frightened
+    } else {
+        BackgroundKind::Siren
+    };
+
+    let remaining_pellets = pellet_query.iter().count();
+
+    let mut playing = false;
+    for (entity, background, sink) in loop_query.iter() {
+        if background.0 != kind {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        playing = true;
+        if let (BackgroundKind::Siren, Some(sink)) = (kind, sink) {
+            let eaten = 1.0 - remaining_pellets as f32 / map.pellet_count().max(1) as f32;
+            sink.set_speed(1.0 + eaten * SIREN_MAX_SPEEDUP);
+        }
+    }
+
+    if !playing {
+        let source = match kind {
+            BackgroundKind::Siren => &sounds.siren,
+            BackgroundKind::Frightened => &sounds.frightened,
+            BackgroundKind::EyesReturning => &sounds.eyes_returning,
+        };
+        commands.spawn((
+            AudioSourceBundle {
+                source: source.clone(),
+                settings: PlaybackSettings::LOOP,
+            },
+            BackgroundLoop(kind),
+            GameSound,
+        ));
+    }
+}
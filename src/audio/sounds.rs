@@ -0,0 +1,111 @@
+use super::wsg::{note, render, rest, sweep, Frame, Waveform};
+
+/// The two halves of the "waka waka", alternated on every pellet eaten.
+pub fn chomp(first_half: bool) -> Vec<f32> {
+    let (from, to) = if first_half {
+        (450.0, 180.0)
+    } else {
+        (180.0, 450.0)
+    };
+    render(&[sweep(Waveform::Triangle, from, to, 8, 12)])
+}
+
+/// One period of the siren, played in a loop.
+pub fn siren() -> Vec<f32> {
+    let mut voice = sweep(Waveform::Sine, 380.0, 700.0, 14, 10);
+    voice.extend(sweep(Waveform::Sine, 700.0, 380.0, 14, 10));
+    render(&[voice])
+}
+
+pub fn frightened() -> Vec<f32> {
+    render(&[sweep(Waveform::Organ, 200.0, 520.0, 8, 10)])
+}
+
+pub fn eyes_returning() -> Vec<f32> {
+    render(&[sweep(Waveform::Saw, 1500.0, 700.0, 6, 9)])
+}
+
+/// Warbles sliding down in pitch, followed by two pops.
+pub fn death() -> Vec<f32> {
+    let mut voice = Vec::new();
+    for i in 0..11 {
+        let base = 900.0 - i as f32 * 55.0;
+        voice.extend(sweep(Waveform::Sine, base, base - 250.0, 5, 12));
+        voice.extend(sweep(Waveform::Sine, base - 250.0, base, 3, 12));
+    }
+    voice.extend(rest(10));
+    for _ in 0..2 {
+        voice.extend(sweep(Waveform::Triangle, 100.0, 1200.0, 8, 13));
+        voice.extend(rest(4));
+    }
+    render(&[voice])
+}
+
+/// The tune played before the first level, as a melody over a bass line.
+/// Durations are expressed in thirty-second notes.
+pub fn intro() -> Vec<f32> {
+    const MELODY: [(u8, usize); 31] = [
+        (71, 2),
+        (83, 2),
+        (78, 2),
+        (75, 2),
+        (83, 1),
+        (78, 3),
+        (75, 4),
+        (72, 2),
+        (84, 2),
+        (79, 2),
+        (76, 2),
+        (84, 1),
+        (79, 3),
+        (76, 4),
+        (71, 2),
+        (83, 2),
+        (78, 2),
+        (75, 2),
+        (83, 1),
+        (78, 3),
+        (75, 4),
+        (75, 1),
+        (76, 1),
+        (77, 2),
+        (77, 1),
+        (78, 1),
+        (79, 2),
+        (79, 1),
+        (80, 1),
+        (81, 2),
+        (83, 4),
+    ];
+    const BASS: [(u8, usize); 16] = [
+        (35, 6),
+        (47, 2),
+        (35, 6),
+        (47, 2),
+        (36, 6),
+        (48, 2),
+        (36, 6),
+        (48, 2),
+        (35, 6),
+        (47, 2),
+        (35, 6),
+        (47, 2),
+        (42, 4),
+        (44, 4),
+        (46, 4),
+        (47, 4),
+    ];
+    const FRAMES_PER_THIRTY_SECOND: usize = 4;
+
+    let voice = |notes: &[(u8, usize)], waveform: Waveform| -> Vec<Frame> {
+        notes
+            .iter()
+            .flat_map(|&(n, length)| note(waveform, n, length * FRAMES_PER_THIRTY_SECOND))
+            .collect()
+    };
+
+    render(&[
+        voice(&MELODY, Waveform::Organ),
+        voice(&BASS, Waveform::Triangle),
+    ])
+}
@@ -0,0 +1,175 @@
+//! A small emulation of the Namco WSG (waveform sound generator) used by the
+//! arcade board: a few voices, each playing back a 32 sample, 4-bit waveform
+//! at a frequency and volume set once per video frame.
+
+pub const SAMPLE_RATE: u32 = 24_000;
+/// The sound registers are written by the game once per video frame.
+const FRAME_RATE: u32 = 60;
+const SAMPLES_PER_FRAME: usize = (SAMPLE_RATE / FRAME_RATE) as usize;
+const WAVEFORM_LENGTH: usize = 32;
+const MAX_VOLUME: u8 = 15;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Waveform {
+    Sine,
+    Organ,
+    Triangle,
+    Saw,
+}
+
+impl Waveform {
+    fn table(&self) -> &'static [u8; WAVEFORM_LENGTH] {
+        match self {
+            Waveform::Sine => &[
+                8, 9, 10, 12, 13, 14, 14, 15, 15, 15, 14, 14, 13, 12, 10, 9, 8, 6, 5, 3, 2, 1, 1,
+                0, 0, 0, 1, 1, 2, 3, 5, 6,
+            ],
+            Waveform::Organ => &[
+                8, 9, 11, 13, 14, 14, 14, 13, 12, 11, 10, 9, 9, 8, 8, 8, 8, 7, 7, 7, 6, 6, 5, 4, 3,
+                2, 1, 1, 1, 2, 4, 6,
+            ],
+            Waveform::Triangle => &[
+                0, 1, 2, 3, 4, 5, 6, 7, 8, 8, 9, 10, 11, 12, 13, 14, 15, 14, 13, 12, 11, 10, 9, 8,
+                8, 7, 6, 5, 4, 3, 2, 1,
+            ],
+            Waveform::Saw => &[
+                0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12,
+                13, 13, 14, 14, 15, 15,
+            ],
+        }
+    }
+}
+
+/// The register values of a voice during one video frame.
+#[derive(Clone, Copy, Debug)]
+pub struct Frame {
+    pub frequency: f32,
+    pub volume: u8,
+    pub waveform: Waveform,
+}
+
+impl Frame {
+    pub fn silent() -> Self {
+        Self {
+            frequency: 0.0,
+            volume: 0,
+            waveform: Waveform::Sine,
+        }
+    }
+}
+
+/// Returns the frequency of the MIDI note [note] (69 being A4 at 440 Hz).
+pub fn note_frequency(note: u8) -> f32 {
+    440.0 * 2f32.powf((note as f32 - 69.0) / 12.0)
+}
+
+/// A frequency slide from [from] to [to] Hz over [frames] frames.
+pub fn sweep(waveform: Waveform, from: f32, to: f32, frames: usize, volume: u8) -> Vec<Frame> {
+    (0..frames)
+        .map(|i| Frame {
+            frequency: from + (to - from) * i as f32 / frames.max(1) as f32,
+            volume,
+            waveform,
+        })
+        .collect()
+}
+
+/// A note held for [frames] frames with a short decay at its start.
+pub fn note(waveform: Waveform, note: u8, frames: usize) -> Vec<Frame> {
+    let frequency = note_frequency(note);
+    (0..frames)
+        .map(|i| Frame {
+            frequency,
+            volume: MAX_VOLUME.saturating_sub(i as u8).max(8),
+            waveform,
+        })
+        .collect()
+}
+
+pub fn rest(frames: usize) -> Vec<Frame> {
+    vec![Frame::silent(); frames]
+}
+
+/// Renders the voices, played together, as mono samples at [SAMPLE_RATE].
+/// The result is as long as the longest voice.
+pub fn render(voices: &[Vec<Frame>]) -> Vec<f32> {
+    let frames = voices.iter().map(|voice| voice.len()).max().unwrap_or(0);
+    let mut samples = vec![0.0; frames * SAMPLES_PER_FRAME];
+
+    for voice in voices {
+        let mut phase = 0.0;
+        for (frame_index, frame) in voice.iter().enumerate() {
+            let step = frame.frequency * WAVEFORM_LENGTH as f32 / SAMPLE_RATE as f32;
+            let table = frame.waveform.table();
+            let volume = frame.volume.min(MAX_VOLUME) as f32 / MAX_VOLUME as f32;
+
+            let start = frame_index * SAMPLES_PER_FRAME;
+            for sample in &mut samples[start..start + SAMPLES_PER_FRAME] {
+                let value = table[phase as usize % WAVEFORM_LENGTH] as f32;
+                // Centre the 4-bit sample around zero.
+                *sample += (value - 7.5) / 7.5 * volume;
+                phase = (phase + step) % WAVEFORM_LENGTH as f32;
+            }
+        }
+    }
+
+    let voice_count = voices.len().max(1) as f32;
+    samples.iter_mut().for_each(|sample| *sample /= voice_count);
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn held(frequency: f32, volume: u8, frames: usize) -> Vec<Frame> {
+        vec![
+            Frame {
+                frequency,
+                volume,
+                waveform: Waveform::Sine,
+            };
+            frames
+        ]
+    }
+
+    #[test]
+    fn plays_a_frame_of_samples_per_frame() {
+        let samples = render(&[held(440.0, 15, 3), rest(5)]);
+        assert_eq!(samples.len(), 5 * SAMPLES_PER_FRAME);
+        assert!(render(&[rest(2)]).iter().all(|sample| *sample == 0.0));
+    }
+
+    #[test]
+    fn steps_through_the_waveform_at_the_frequency() {
+        // A sample of the waveform for every sample played.
+        let frequency = (SAMPLE_RATE / WAVEFORM_LENGTH as u32) as f32;
+        let samples = render(&[held(frequency, MAX_VOLUME, 1)]);
+        let table = Waveform::Sine.table();
+        for (i, sample) in samples.iter().enumerate() {
+            let expected = (table[i % WAVEFORM_LENGTH] as f32 - 7.5) / 7.5;
+            assert!((sample - expected).abs() < 1e-4, "sample {i}");
+        }
+    }
+
+    #[test]
+    fn a_second_of_a4_has_440_cycles() {
+        let samples = render(&[held(note_frequency(69), MAX_VOLUME, FRAME_RATE as usize)]);
+        let rising = samples
+            .windows(2)
+            .filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0)
+            .count();
+        assert!((439..=441).contains(&rising), "{rising} cycles");
+    }
+
+    #[test]
+    fn voices_are_scaled_by_their_volume_and_mixed() {
+        let loud = render(&[held(1000.0, MAX_VOLUME, 1)]);
+        let quiet = render(&[held(1000.0, 5, 1)]);
+        let mixed = render(&[held(1000.0, MAX_VOLUME, 1), rest(1)]);
+        for ((loud, quiet), mixed) in loud.iter().zip(&quiet).zip(&mixed) {
+            assert!((quiet - loud / 3.0).abs() < 1e-4);
+            assert!((mixed - loud / 2.0).abs() < 1e-4);
+        }
+    }
+}
@@ -0,0 +1,136 @@
+//! Lets a program play in place of the keyboard, so that bots written in
+//! any language can take on the ghosts. Started with `--bot`, the game
+//! writes what is going on to its standard output every frame, a line of
+//! JSON as sent to spectators (see [crate::spectate]), and reads the bot's
+//! moves from its standard input, a line of JSON each:
+//!
+//! ```json
+//! {"direction":"left"}
+//! ```
+//!
+//! The bot drives the first Pac-Man, who turns that way as soon as the maze
+//! lets him, as if the key had been pressed. The game doesn't wait for the
+//! bot, which may move whenever it likes. A move also starts a game from
+//! the attract screens, and `{"direction":null}` does nothing.
+//!
+//! With `--lockstep`, the game waits for the bot instead: every frame after
+//! the first is only played once the bot answered the last one, with a move
+//! or `{"direction":null}`, so that slow bots miss nothing.
+//!
+//! With `--headless` as well, the game runs without a window or sound,
+//! drawing nothing, for bots to be trained on machines without a display.
+//! Frames are then played as fast as the bot keeps up, each moving the game
+//! on by a tick as if it took [crate::movement::pixel::FRAME_SECS].
+
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+
+use bevy::{app::AppExit, prelude::*};
+use serde::Deserialize;
+
+use crate::common::Direction;
+use crate::party::PARTY_CONTROLS;
+use crate::spectate::GameObserver;
+
+#[derive(Deserialize)]
+struct BotMove {
+    direction: Option<Direction>,
+}
+
+/// The moves read from the bot, and the frames it was told about.
+#[derive(Resource)]
+pub struct BotLink {
+    /// A line each, without a move for those doing nothing.
+    moves: Mutex<Receiver<Option<Direction>>>,
+    frame: u64,
+    /// Whether each frame waits for the bot's answer to the last one.
+    lockstep: bool,
+}
+
+impl BotLink {
+    /// Starts reading the bot's moves from the standard input, in the
+    /// background as reading waits for the bot.
+    pub fn start(lockstep: bool) -> Self {
+        let (sender, moves) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                // A line the bot got wrong still answers a frame.
+                let dir = serde_json::from_str::<BotMove>(&line)
+                    .map(|bot_move| bot_move.direction)
+                    .unwrap_or_else(|err| {
+                        eprintln!("Can't read the bot's move '{line}': {err}");
+                        None
+                    });
+                if sender.send(dir).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            moves: Mutex::new(moves),
+            frame: 0,
+            lockstep,
+        }
+    }
+}
+
+/// Presses the key of the bot's latest move for a frame, the way a player
+/// would. In lockstep, waits for the bot to answer the last frame first, the
+/// game ending if it never does.
+pub fn read_bot_moves(
+    link: Res<BotLink>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut held: Local<Option<KeyCode>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if let Some(key) = held.take() {
+        keys.release(key);
+    }
+
+    let Ok(moves) = link.moves.lock() else {
+        return;
+    };
+    // Nothing was sent to the bot before the first frame.
+    let dir = if link.lockstep && link.frame > 0 {
+        match moves.recv() {
+            Ok(dir) => dir,
+            Err(_) => {
+                exit.send(AppExit);
+                return;
+            }
+        }
+    } else {
+        moves.try_iter().flatten().last()
+    };
+    let key = dir.and_then(|dir| PARTY_CONTROLS[0].key(&dir));
+    if let Some(key) = key {
+        keys.press(key);
+        *held = Some(key);
+    }
+}
+
+/// Tells the bot what is going on, the game ending once the bot is gone.
+pub fn send_bot_observation(
+    mut link: ResMut<BotLink>,
+    observer: GameObserver,
+    mut exit: EventWriter<AppExit>,
+) {
+    link.frame += 1;
+    // The bot is told what the maze looks like from the start.
+    let line = observer.view_line(link.frame, link.frame == 1);
+    let mut stdout = io::stdout().lock();
+    if stdout
+        .write_all(&line)
+        .and_then(|_| stdout.flush())
+        .is_err()
+    {
+        exit.send(AppExit);
+    }
+}
@@ -0,0 +1,120 @@
+use bevy::{
+    prelude::*,
+    render::camera::{ScalingMode, Viewport},
+    window::{PrimaryWindow, WindowMode, WindowResized},
+};
+
+use crate::config::GameConfig;
+use crate::map::{Map, TILE_SIZE};
+
+/// Rows above the maze reserved for the score display.
+const HUD_TOP_ROWS: usize = 3;
+/// Rows below the maze reserved for lives and fruits.
+const HUD_BOTTOM_ROWS: usize = 2;
+
+#[derive(Resource, Default)]
+pub struct CameraLayout {
+    /// Rotates the playfield by 90 degrees, for monitors mounted like a
+    /// horizontal cabinet screen.
+    pub rotated: bool,
+}
+
+pub fn spawn_camera(mut commands: Commands) {
+    commands.spawn(Camera2dBundle::default());
+}
+
+pub fn toggle_camera_layout(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut layout: ResMut<CameraLayout>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F10) {
+        layout.rotated = !layout.rotated;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::F11) {
+        for mut window in &mut window_query {
+            window.mode = match window.mode {
+                WindowMode::Windowed => WindowMode::BorderlessFullscreen,
+                _ => WindowMode::Windowed,
+            };
+        }
+    }
+}
+
+/// Scales the playfield by the largest integer factor fitting the window and
+/// centres it, leaving black bars around it.
+pub fn fit_camera(
+    layout: Res<CameraLayout>,
+    map: Res<Map>,
+    config: Res<GameConfig>,
+    mut resized_events: EventReader<WindowResized>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut camera_query: Query<(&mut Camera, &mut OrthographicProjection, &mut Transform)>,
+) {
+    let resized = resized_events.iter().count() > 0;
+    if !resized && !layout.is_changed() && !map.is_changed() {
+        return;
+    }
+
+    let playfield_width = (map.width() as i32 * TILE_SIZE) as f32;
+    let playfield_height =
+        ((map.height() + HUD_TOP_ROWS + HUD_BOTTOM_ROWS) as i32 * TILE_SIZE) as f32;
+
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+
+    let (view_width, view_height) = if layout.rotated {
+        (playfield_height, playfield_width)
+    } else {
+        (playfield_width, playfield_height)
+    };
+
+    let window_width = window.physical_width();
+    let window_height = window.physical_height();
+    if window_width == 0 || window_height == 0 {
+        return;
+    }
+
+    // Only fall back to fractional scaling when the window is too small to
+    // show the playfield at its original size.
+    let scale = (window_width as f32 / view_width).min(window_height as f32 / view_height);
+    let scale = match config.camera_scale {
+        Some(scale) => scale,
+        None if scale >= 1.0 => scale.floor(),
+        None => scale,
+    };
+
+    let viewport_size = UVec2::new(
+        ((view_width * scale) as u32).clamp(1, window_width),
+        ((view_height * scale) as u32).clamp(1, window_height),
+    );
+    let viewport_position = (UVec2::new(window_width, window_height) - viewport_size) / 2;
+
+    // Tiles are drawn centred on their position, so the playfield spans half
+    // a tile to the left and above the first tile.
+    let half_tile = TILE_SIZE as f32 / 2.0;
+    let centre = Vec2::new(
+        playfield_width / 2.0 - half_tile,
+        playfield_height / 2.0 - half_tile - (HUD_TOP_ROWS as i32 * TILE_SIZE) as f32,
+    );
+
+    for (mut camera, mut projection, mut transform) in &mut camera_query {
+        camera.viewport = Some(Viewport {
+            physical_position: viewport_position,
+            physical_size: viewport_size,
+            ..default()
+        });
+        projection.scaling_mode = ScalingMode::Fixed {
+            width: view_width,
+            height: view_height,
+        };
+        transform.translation = Vec3::new(centre.x, -centre.y, transform.translation.z);
+        transform.rotation = if layout.rotated {
+            Quat::from_rotation_z(std::f32::consts::FRAC_PI_2)
+        } else {
+            Quat::IDENTITY
+        };
+    }
+}
@@ -1,5 +1,10 @@
-#[derive(PartialEq, Clone, Debug)]
+use bevy::reflect::Reflect;
+use serde::{Deserialize, Serialize};
+
+#[derive(PartialEq, Eq, Hash, Clone, Debug, Default, Reflect, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Direction {
+    #[default]
     Up,
     Right,
     Down,
@@ -15,4 +20,15 @@ impl Direction {
             Direction::Left => Direction::Right,
         }
     }
+
+    /// The order in which the arcade ghosts prefer directions when several
+    /// are equally good, lower being preferred.
+    pub fn arcade_priority(&self) -> u8 {
+        match self {
+            Direction::Up => 0,
+            Direction::Left => 1,
+            Direction::Down => 2,
+            Direction::Right => 3,
+        }
+    }
 }
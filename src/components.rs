@@ -1,15 +1,20 @@
 use bevy::prelude::*;
 
 use crate::common::Direction;
-use crate::map::TilePos;
+use crate::map::{MapType, OpenContent, TilePos};
+use crate::mazes::MazePalette;
+use crate::sprites::SpriteManifest;
 
-#[derive(Component)]
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
 pub struct Player;
 
-#[derive(Component, Deref, DerefMut, PartialEq, Debug, Clone)]
+#[derive(Component, Deref, DerefMut, PartialEq, Debug, Clone, Default, Reflect)]
+#[reflect(Component)]
 pub struct Position(pub TilePos);
 
-#[derive(Component, Clone, Debug)]
+#[derive(Component, Clone, Debug, Default, Reflect)]
+#[reflect(Component)]
 pub struct Movable {
     pub target_tile: TilePos,
     pub progress: f32,
@@ -28,7 +33,8 @@ impl Movable {
     }
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
 pub struct AnimationIndices {
     pub current_index: usize,
     pub sprite_indices_right: Vec<usize>,
@@ -49,5 +55,99 @@ impl AnimationIndices {
     }
 }
 
-#[derive(Component, Deref, DerefMut)]
+#[derive(Component, Deref, DerefMut, Default, Reflect)]
+#[reflect(Component)]
 pub struct AnimationTimer(pub Timer);
+
+/// The animation a ghost returns to once it is no longer frightened or eaten.
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct NormalAnimation(pub AnimationIndices);
+
+/// Marks entities belonging to a running level (maze tiles and characters),
+/// despawned together when the level is torn down.
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct LevelEntity;
+
+/// Defaults to empty handles, for when nothing gets drawn with them.
+#[derive(Resource, Default)]
+pub struct SpriteSheets {
+    pub characters: Handle<TextureAtlas>,
+    pub maze: Handle<TextureAtlas>,
+    pub font: Handle<TextureAtlas>,
+    /// Which of their sprites draw what.
+    pub manifest: SpriteManifest,
+}
+
+/// A pellet that Pac-Man can eat, worth [points].
+/// Power pellets frighten the ghosts.
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Pellet {
+    pub points: u32,
+    pub power: bool,
+}
+
+impl Pellet {
+    /// The pellet a maze tile starts with, if any.
+    pub fn for_tile(tile: &MapType) -> Option<Self> {
+        match tile {
+            MapType::Open(OpenContent::Food) => Some(Pellet {
+                points: 10,
+                power: false,
+            }),
+            MapType::Open(OpenContent::GhostEater) => Some(Pellet {
+                points: 50,
+                power: true,
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct Score {
+    pub current: u32,
+    pub high: u32,
+}
+
+/// The level being played, counting from 1.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct Level(pub u32);
+
+impl Default for Level {
+    fn default() -> Self {
+        Self(1)
+    }
+}
+
+/// How long the maze flashes once every pellet has been eaten.
+const LEVEL_CLEAR_SECS: f32 = 2.0;
+/// How long the walls stay white, then in colour, while flashing.
+const FLASH_SECS: f32 = 0.25;
+
+/// Pac-Man ate every pellet: the game stands still while the maze flashes,
+/// then moves on to the next level.
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct LevelClear {
+    pub timer: Timer,
+    /// The colours of the maze before it started flashing.
+    pub palette: MazePalette,
+}
+
+impl LevelClear {
+    pub fn new(palette: MazePalette) -> Self {
+        Self {
+            timer: Timer::from_seconds(LEVEL_CLEAR_SECS, TimerMode::Once),
+            palette,
+        }
+    }
+
+    pub fn flash_on(&self) -> bool {
+        (self.timer.elapsed_secs() / FLASH_SECS) as u32 % 2 == 1
+    }
+}
@@ -0,0 +1,375 @@
+//! The settings of the game, read from a RON file, `pacman.ron` unless
+//! another is given with `--config FILE`, every one of them being optional:
+//!
+//! ```ron
+//! (
+//!     level: 3,
+//!     lives: 5,
+//!     ai: relentless,
+//!     mazes: "generated",
+//!     maze_width: 32,
+//!     movement: pixels,
+//!     party: coop,
+//!     pacman_speed: 10.0,
+//!     pacman_start: Some((x: 13, y: 23)),
+//!     spectate: Some(7778),
+//! )
+//! ```
+//!
+//! The command line has the last word, with `--level N`, `--lives N`,
+//! `--ai PROFILE`, `--movement MODEL`, `--party MODE`, `--mazes SET`,
+//! `--size WIDTHxHEIGHT`, `--seed N`, `--god`, `--window WIDTHxHEIGHT`,
+//! `--save-file FILE`, `--resume`, `--edit FILE`, `--host [PORT]`,
+//! `--join HOST[:PORT]`, `--spectate [PORT]`, `--bot`, `--lockstep`,
+//! `--headless` and `--tui`.
+
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::ai::profile::{AiDice, AiProfile};
+use crate::map::generate::{MazeParams, MAX_HEIGHT, MAX_WIDTH};
+use crate::map::TilePos;
+use crate::mazes::MazeSet;
+use crate::movement::MovementModel;
+use crate::party::PartyMode;
+use crate::{net, spectate};
+
+const DEFAULT_CONFIG_FILE: &str = "pacman.ron";
+const DEFAULT_SAVE_FILE: &str = "pacman-save.ron";
+
+/// How the game is set up, for every system to go by.
+#[derive(Resource, Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GameConfig {
+    /// The size the window opens at, in logical pixels.
+    pub window_width: f32,
+    pub window_height: f32,
+    /// How many screen pixels a pixel of the playfield takes, the largest
+    /// whole number fitting the window when unset.
+    pub camera_scale: Option<f32>,
+    /// The level games start on, from 1.
+    pub level: u32,
+    pub lives: u32,
+    /// How the ghosts chase Pac-Man.
+    pub ai: AiProfile,
+    /// How the characters get from one tile to the next.
+    pub movement: MovementModel,
+    /// How two players share the maze.
+    pub party: PartyMode,
+    /// The mazes the levels are played in, see [MazeSet::from_name].
+    pub mazes: String,
+    /// The size of generated mazes, see [MazeParams].
+    pub maze_width: usize,
+    pub maze_height: usize,
+    /// Seeds whatever is left to chance, for games to play out the same
+    /// every time.
+    pub seed: Option<u64>,
+    /// The ghosts can't catch Pac-Man.
+    pub god_mode: bool,
+    /// Speeds in tiles per second.
+    pub pacman_speed: f32,
+    pub ghost_speed: f32,
+    /// Where the characters start in every maze, in place of its own tiles.
+    pub pacman_start: Option<TilePos>,
+    pub ghost_start: Option<TilePos>,
+    /// Where games get quick-saved, see [crate::save].
+    pub save_file: PathBuf,
+    /// Picks the saved game up again as soon as the game starts.
+    pub resume: bool,
+    /// The maze file to start in the editor on, see [crate::editor].
+    pub edit: Option<PathBuf>,
+    /// Waits on this port for another machine to play with, see [crate::net].
+    pub host: Option<u16>,
+    /// Plays with the machine hosting at this `HOST[:PORT]` address.
+    pub join: Option<String>,
+    /// Lets programs watch the game on this port, see [crate::spectate].
+    pub spectate: Option<u16>,
+    /// The game is played by a program, see [crate::bot].
+    pub bot: bool,
+    /// Every frame waits for the bot's move.
+    pub lockstep: bool,
+    /// Nothing gets drawn, for bots to play as fast as they can.
+    pub headless: bool,
+    /// The game is drawn in the terminal, see [crate::terminal].
+    pub tui: bool,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            window_width: 672.0,
+            window_height: 864.0,
+            camera_scale: None,
+            level: 1,
+            lives: 3,
+            ai: AiProfile::default(),
+            movement: MovementModel::default(),
+            party: PartyMode::default(),
+            mazes: "classic".into(),
+            maze_width: MazeParams::default().width,
+            maze_height: MazeParams::default().height,
+            seed: None,
+            god_mode: false,
+            pacman_speed: 11.5,
+            ghost_speed: 7.0,
+            pacman_start: None,
+            ghost_start: None,
+            save_file: DEFAULT_SAVE_FILE.into(),
+            resume: false,
+            edit: None,
+            host: None,
+            join: None,
+            spectate: None,
+            bot: false,
+            lockstep: false,
+            headless: false,
+            tui: false,
+        }
+    }
+}
+
+impl GameConfig {
+    /// Reads the configuration file, then the command line.
+    pub fn load() -> Self {
+        let args = Args(std::env::args().collect());
+        let mut config = match args.value_of("--config") {
+            Some(path) => Self::read(PathBuf::from(path), true),
+            None => Self::read(PathBuf::from(DEFAULT_CONFIG_FILE), false),
+        };
+
+        if let Some(level) = args.parse::<u32>("--level", "a level from 1") {
+            config.level = level.max(1);
+        }
+        if let Some(lives) = args.parse::<u32>("--lives", "a number of lives from 1") {
+            config.lives = lives.max(1);
+        }
+        if let Some(seed) = args.parse("--seed", "a number") {
+            config.seed = Some(seed);
+        }
+        if args.has("--god") {
+            config.god_mode = true;
+        }
+        if let Some(name) = args.value_of("--ai") {
+            match AiProfile::from_name(name) {
+                Some(ai) => config.ai = ai,
+                None => {
+                    eprintln!("Unknown AI profile '{name}', expected arcade, relentless or casual")
+                }
+            }
+        }
+        if let Some(name) = args.value_of("--movement") {
+            match MovementModel::from_name(name) {
+                Some(movement) => config.movement = movement,
+                None => eprintln!("Unknown movement model '{name}', expected tiles or pixels"),
+            }
+        }
+        if let Some(name) = args.value_of("--party") {
+            match PartyMode::from_name(name) {
+                Some(party) => config.party = party,
+                None => eprintln!("Unknown party mode '{name}', expected arcade, coop or versus"),
+            }
+        }
+        if let Some(name) = args.value_of("--mazes") {
+            config.mazes = name.into();
+        }
+        if let Some((width, height)) = args.size("--size") {
+            if width > MAX_WIDTH || height > MAX_HEIGHT {
+                eprintln!("Mazes are at most {MAX_WIDTH}x{MAX_HEIGHT}, not {width}x{height}");
+            } else {
+                config.maze_width = width;
+                config.maze_height = height;
+            }
+        }
+        if let Some((width, height)) = args.size("--window") {
+            config.window_width = width;
+            config.window_height = height;
+        }
+        if let Some(path) = args.value_of("--save-file") {
+            config.save_file = path.into();
+        }
+        if args.has("--resume") {
+            config.resume = true;
+        }
+        if let Some(path) = args.value_of("--edit") {
+            config.edit = Some(path.into());
+        }
+        if let Some(port) = args.port("--host", net::DEFAULT_PORT) {
+            config.host = Some(port);
+        }
+        if let Some(address) = args.value_of("--join") {
+            config.join = Some(address.into());
+        }
+        if let Some(port) = args.port("--spectate", spectate::DEFAULT_PORT) {
+            config.spectate = Some(port);
+        }
+        config.bot |= args.has("--bot");
+        config.lockstep |= args.has("--lockstep");
+        config.headless |= args.has("--headless");
+        config.tui |= args.has("--tui");
+        config
+    }
+
+    /// Reads [path], going with the defaults when it can't, which only
+    /// deserves a word when the file was [asked_for].
+    fn read(path: PathBuf, asked_for: bool) -> Self {
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) => {
+                if asked_for {
+                    eprintln!("Could not read {}: {err}", path.display());
+                }
+                return Self::default();
+            }
+        };
+        ron::from_str(&text).unwrap_or_else(|err| {
+            eprintln!("Could not read {}: {err}", path.display());
+            Self::default()
+        })
+    }
+
+    /// The mazes to play in, the generated ones following [seed].
+    pub fn maze_set(&self) -> MazeSet {
+        let mut maze_set = MazeSet::from_name(&self.mazes).unwrap_or_else(|| {
+            eprintln!(
+                "Unknown maze set '{}', expected classic, ms-pacman or generated",
+                self.mazes
+            );
+            MazeSet::default()
+        });
+        if let MazeSet::Generated(params) = &mut maze_set {
+            *params = self.maze_params();
+        }
+        maze_set
+    }
+
+    /// The mazes to generate, following [seed].
+    pub fn maze_params(&self) -> MazeParams {
+        MazeParams {
+            width: self.maze_width,
+            height: self.maze_height,
+            seed: self.seed.unwrap_or_default(),
+        }
+    }
+
+    pub fn ai_dice(&self) -> AiDice {
+        self.seed.map_or_else(AiDice::default, AiDice::new)
+    }
+}
+
+/// The command line arguments, each flag followed by its value if it takes
+/// one.
+struct Args(Vec<String>);
+
+impl Args {
+    fn has(&self, flag: &str) -> bool {
+        self.0.iter().any(|arg| arg == flag)
+    }
+
+    fn value_of(&self, flag: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .skip_while(|arg| *arg != flag)
+            .nth(1)
+            .map(String::as_str)
+    }
+
+    /// The value of [flag] read as a [T], which is [expected].
+    fn parse<T: FromStr>(&self, flag: &str, expected: &str) -> Option<T> {
+        let value = self.value_of(flag)?;
+        value
+            .parse()
+            .map_err(|_| eprintln!("Invalid {flag} '{value}', expected {expected}"))
+            .ok()
+    }
+
+    /// The `<width>x<height>` value of [flag].
+    fn size<T: FromStr>(&self, flag: &str) -> Option<(T, T)> {
+        let value = self.value_of(flag)?;
+        let size = value
+            .split_once('x')
+            .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)));
+        if size.is_none() {
+            eprintln!("Invalid {flag} '{value}', expected <width>x<height>");
+        }
+        size
+    }
+
+    /// The port given after [flag], [default] when it is left out.
+    fn port(&self, flag: &str, default: u16) -> Option<u16> {
+        let i = self.0.iter().position(|arg| arg == flag)?;
+        match self.0.get(i + 1).filter(|value| !value.starts_with("--")) {
+            None => Some(default),
+            Some(port) => port.parse().ok().or_else(|| {
+                eprintln!("Expected a port number after {flag}");
+                None
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Args {
+        Args(args.iter().map(|arg| arg.to_string()).collect())
+    }
+
+    #[test]
+    fn flags_are_read_as_their_own_type() {
+        let args = args(&["pacman", "--level", "4294967297", "--seed", "4294967297"]);
+        assert_eq!(args.parse::<u32>("--level", "a level from 1"), None);
+        assert_eq!(args.parse::<u64>("--seed", "a number"), Some(4294967297));
+        assert_eq!(args.parse::<u32>("--lives", "a number of lives"), None);
+    }
+
+    #[test]
+    fn ports_can_be_left_out() {
+        let args = args(&["pacman", "--host", "--spectate", "9000", "--join"]);
+        assert_eq!(args.port("--host", 1), Some(1));
+        assert_eq!(args.port("--spectate", 1), Some(9000));
+        assert_eq!(args.port("--bot", 1), None);
+    }
+
+    #[test]
+    fn every_flag_can_be_set_in_the_file() {
+        let config = ron::from_str::<GameConfig>(
+            r#"(
+                movement: pixels,
+                party: versus,
+                mazes: "generated",
+                maze_width: 40,
+                maze_height: 35,
+                save_file: "elsewhere.ron",
+                resume: true,
+                edit: Some("maze.txt"),
+                host: Some(7000),
+                join: Some("localhost"),
+                spectate: Some(7001),
+                bot: true,
+                lockstep: true,
+                headless: true,
+                tui: true,
+            )"#,
+        )
+        .unwrap();
+        assert_eq!(config.movement, MovementModel::Pixels);
+        assert_eq!(config.party, PartyMode::Versus);
+        assert_eq!(
+            config.maze_set(),
+            MazeSet::Generated(MazeParams {
+                width: 40,
+                height: 35,
+                seed: 0,
+            })
+        );
+        assert_eq!(config.save_file, PathBuf::from("elsewhere.ron"));
+        assert_eq!(config.edit, Some(PathBuf::from("maze.txt")));
+        assert_eq!((config.host, config.spectate), (Some(7000), Some(7001)));
+        assert!(config.resume && config.bot && config.lockstep);
+        assert!(config.headless && config.tui);
+    }
+}
@@ -0,0 +1,408 @@
+//! A console for testers to reach the states that are hard to play into,
+//! dropped down over the top of the maze with the `` ` `` key. It takes
+//! the keyboard while open, and knows these commands:
+//!
+//! - `level N` jumps to level N
+//! - `god` stops the ghosts from catching Pac-Man, or lets them again
+//! - `eat all` clears the maze of its pellets
+//! - `ghost mode chase|frightened|eaten` puts every ghost in that mode
+//! - `teleport X Y` moves Pac-Man to that tile
+//! - `speed 2x` runs the game twice as fast, `speed 1x` back as usual
+//! - `spawn fruit` puts the bonus fruit of the level in the maze
+//! - `pause` stops the game or starts it again, `step` plays a single tick
+//!   of it while stopped
+//!
+//! Networked games leave the console shut, the other machine knowing
+//! nothing of its doings.
+
+use std::time::{Duration, Instant};
+
+use bevy::ecs::system::SystemState;
+use bevy::prelude::*;
+use bevy::window::ReceivedCharacter;
+
+use crate::ai::{FrightenedTimer, Ghost, GhostMode};
+use crate::components::{
+    Level, LevelClear, LevelEntity, Movable, Pellet, Player, Position, SpriteSheets,
+};
+use crate::config::GameConfig;
+use crate::debug::mode_name;
+use crate::fruit::{self, Fruit};
+use crate::game_state::GameState;
+use crate::map::{Map, MapType, TilePos, TILE_SIZE};
+use crate::movement::pixel::{PixelPosition, FRAME_SECS};
+use crate::movement::NextDirection;
+use crate::net::NetLink;
+use crate::party::PlayerIndex;
+use crate::text::{ArcadeText, ArcadeTextBundle};
+use crate::LevelLoader;
+
+/// Lines of output kept above the command being typed.
+const OUTPUT_ROWS: usize = 5;
+const OUTPUT_COLOR: Color = Color::rgb(0.6, 1.0, 0.6);
+const BACKDROP_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.85);
+
+const HELP: [&str; OUTPUT_ROWS] = [
+    "LEVEL N  GOD  EAT ALL",
+    "GHOST MODE CHASE/EATEN",
+    "GHOST MODE FRIGHTENED",
+    "TELEPORT X Y  SPEED 2X",
+    "PAUSE  STEP  SPAWN FRUIT",
+];
+
+#[derive(Resource, Default)]
+pub struct Console {
+    open: bool,
+    /// The command being typed.
+    input: String,
+    /// The command typed in full, to run.
+    command: Option<String>,
+    output: Vec<String>,
+    /// Whether the game is stopped, but for a tick now and then with
+    /// [step].
+    paused: bool,
+    stepping: bool,
+}
+
+impl Console {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    fn print(&mut self, line: impl Into<String>) {
+        self.output.push(line.into());
+        let scrolled = self.output.len().saturating_sub(OUTPUT_ROWS);
+        self.output.drain(..scrolled);
+    }
+}
+
+/// Whether the gameplay systems get to run this frame, which they don't
+/// while the console paused the game but for the tick it steps.
+pub fn game_running(console: Res<Console>) -> bool {
+    !console.paused || console.stepping
+}
+
+/// The lines of the console, the last one being the command being typed.
+#[derive(Component)]
+pub struct ConsoleLine(usize);
+
+/// The dark panel the console is drawn on.
+#[derive(Component)]
+pub struct ConsoleBackdrop;
+
+/// Opens and closes the console, and types into it. The keys pressed go to
+/// the console alone while it is open.
+pub fn console_input(
+    mut console: ResMut<Console>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    link: Option<Res<NetLink>>,
+) {
+    let typed = characters
+        .iter()
+        .map(|event| event.char)
+        .collect::<String>();
+    if link.is_some() {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Grave) {
+        console.open = !console.open;
+    } else if console.open {
+        // Terminals can hand over a whole command at once.
+        let typed = typed.chars().filter(|c| *c != '`' && !c.is_control());
+        console.input.extend(typed);
+        if keys.just_pressed(KeyCode::Back) {
+            console.input.pop();
+        }
+        if keys.just_pressed(KeyCode::Return) {
+            let command = std::mem::take(&mut console.input);
+            console.command = Some(command);
+        }
+        if keys.just_pressed(KeyCode::Escape) {
+            console.open = false;
+        }
+    } else {
+        return;
+    }
+    keys.reset_all();
+}
+
+/// Runs the command typed into the console.
+pub fn run_console_command(world: &mut World) {
+    let Some(command) = world.resource_mut::<Console>().command.take() else {
+        return;
+    };
+    let command = command.to_lowercase();
+    let words = command.split_whitespace().collect::<Vec<_>>();
+    if words.is_empty() {
+        return;
+    }
+    world
+        .resource_mut::<Console>()
+        .print(format!("- {}", words.join(" ")));
+
+    let in_game = matches!(
+        world.resource::<State<GameState>>().get(),
+        GameState::Playing | GameState::Demo
+    );
+    let reply = match words.as_slice() {
+        ["help"] => {
+            let mut console = world.resource_mut::<Console>();
+            for line in HELP {
+                console.print(line);
+            }
+            return;
+        }
+        ["god"] => {
+            let mut config = world.resource_mut::<GameConfig>();
+            config.god_mode = !config.god_mode;
+            if config.god_mode {
+                "GOD MODE ON".to_string()
+            } else {
+                "GOD MODE OFF".to_string()
+            }
+        }
+        ["speed", factor] => match factor.trim_end_matches('x').parse::<f32>() {
+            Ok(factor) if factor > 0.0 => {
+                world.resource_mut::<Time>().set_relative_speed(factor);
+                format!("SPEED {factor}X")
+            }
+            _ => "SPEED TAKES A FACTOR LIKE 2X".to_string(),
+        },
+        ["pause"] => {
+            let mut console = world.resource_mut::<Console>();
+            console.paused = !console.paused;
+            if console.paused {
+                "PAUSED".to_string()
+            } else {
+                "RUNNING".to_string()
+            }
+        }
+        ["step"] => {
+            world.resource_mut::<Console>().paused = true;
+            step(world);
+            "STEPPED A TICK".to_string()
+        }
+        _ if !in_game => "NOT IN A GAME".to_string(),
+        ["level", level] => match level.parse::<u32>() {
+            Ok(level) if level > 0 => {
+                jump_to_level(world, level);
+                format!("LEVEL {level}")
+            }
+            _ => "LEVEL TAKES A NUMBER FROM 1".to_string(),
+        },
+        ["spawn", "fruit"] => format!("FRUIT FOR {} POINTS", spawn_fruit(world)),
+        ["eat", "all"] => format!("ATE {} PELLETS", eat_all(world)),
+        ["ghost", "mode", mode] => {
+            let mode = match *mode {
+                "chase" => GhostMode::Chase,
+                "frightened" => GhostMode::Frightened,
+                "eaten" => GhostMode::Eaten,
+                _ => {
+                    world.resource_mut::<Console>().print("UNKNOWN GHOST MODE");
+                    return;
+                }
+            };
+            set_ghost_mode(world, &mode);
+            format!("GHOSTS {}", mode_name(&mode))
+        }
+        ["teleport", x, y] => match (x.parse(), y.parse()) {
+            (Ok(x), Ok(y)) => {
+                let tile = TilePos { x, y };
+                if teleport(world, &tile) {
+                    format!("PAC-MAN TO {x} {y}")
+                } else {
+                    format!("NO CORRIDOR AT {x} {y}")
+                }
+            }
+            _ => "TELEPORT TAKES X Y".to_string(),
+        },
+        _ => "UNKNOWN COMMAND - TRY HELP".to_string(),
+    };
+    world.resource_mut::<Console>().print(reply);
+}
+
+/// Plays a single [FRAME_SECS] tick of the stopped game, on a clock moved on
+/// by exactly that long: [FixedUpdate] once for the pixel movement, then the
+/// gameplay of [Update], as [crate::net] plays its ticks.
+fn step(world: &mut World) {
+    let clock = world.resource::<Time>().clone();
+    let now = clock.last_update().unwrap_or_else(Instant::now);
+    // A clock's first update only starts it, the second one moving it on.
+    let mut tick = Time::new(now);
+    tick.update_with_instant(now);
+    tick.update_with_instant(now + Duration::from_secs_f32(FRAME_SECS));
+    world.insert_resource(tick);
+
+    world.resource_mut::<Console>().stepping = true;
+    world.run_schedule(FixedUpdate);
+    world.run_schedule(Update);
+    world.resource_mut::<Console>().stepping = false;
+    world.insert_resource(clock);
+}
+
+/// Puts the fruit of the level in the maze, in place of any already there,
+/// returning what it is worth.
+fn spawn_fruit(world: &mut World) -> u32 {
+    let mut state = SystemState::<(
+        Commands,
+        Res<SpriteSheets>,
+        Res<Map>,
+        Res<Level>,
+        Query<Entity, With<Fruit>>,
+    )>::new(world);
+    let (mut commands, sprite_sheets, map, level, fruit_query) = state.get_mut(world);
+    for entity in &fruit_query {
+        commands.entity(entity).despawn();
+    }
+    let points = fruit::spawn_fruit(&mut commands, &sprite_sheets, &map, level.0);
+    state.apply(world);
+    points
+}
+
+/// Starts [level] over, as [crate::next_level] does.
+fn jump_to_level(world: &mut World, level: u32) {
+    world.resource_mut::<Level>().0 = level;
+    world.remove_resource::<LevelClear>();
+
+    let mut state =
+        SystemState::<(Commands, LevelLoader, Query<Entity, With<LevelEntity>>)>::new(world);
+    let (mut commands, loader, level_query) = state.get_mut(world);
+    for entity in level_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    loader.load(&mut commands, level);
+    state.apply(world);
+}
+
+/// Empties the maze without scoring, returning how many pellets were left.
+fn eat_all(world: &mut World) -> usize {
+    let mut state = SystemState::<(
+        Commands,
+        Res<SpriteSheets>,
+        Query<(Entity, &mut TextureAtlasSprite), With<Pellet>>,
+    )>::new(world);
+    let (mut commands, sprite_sheets, mut pellet_query) = state.get_mut(world);
+    let mut eaten = 0;
+    for (entity, mut sprite) in &mut pellet_query {
+        sprite.index = sprite_sheets.manifest.tiles.empty;
+        commands.entity(entity).remove::<Pellet>();
+        eaten += 1;
+    }
+    state.apply(world);
+    eaten
+}
+
+/// Frightened ghosts stay so for as long as after a power pellet.
+fn set_ghost_mode(world: &mut World, mode: &GhostMode) {
+    let mut timer = world.resource_mut::<FrightenedTimer>();
+    if *mode == GhostMode::Frightened {
+        timer.reset();
+        timer.unpause();
+    } else {
+        timer.pause();
+    }
+    let mut ghost_query = world.query_filtered::<&mut GhostMode, With<Ghost>>();
+    for mut ghost_mode in ghost_query.iter_mut(world) {
+        *ghost_mode = mode.clone();
+    }
+}
+
+/// Puts the first Pac-Man on [tile], if it is a corridor.
+fn teleport(world: &mut World, tile: &TilePos) -> bool {
+    let map = world.resource::<Map>();
+    if !map.contains(tile) || !matches!(map.get_at(tile), MapType::Open(_)) {
+        return false;
+    }
+
+    let mut pacman_query = world.query_filtered::<(
+        &mut Position,
+        &mut Movable,
+        &mut NextDirection,
+        Option<&mut PixelPosition>,
+        Option<&PlayerIndex>,
+    ), With<Player>>();
+    for (mut position, mut movable, mut next, pixel, player) in pacman_query.iter_mut(world) {
+        if player.is_some_and(|player| player.0 > 0) {
+            continue;
+        }
+        position.0 = tile.clone();
+        movable.target_tile = tile.clone();
+        movable.progress = 0.0;
+        next.0 = None;
+        if let Some(mut pixel) = pixel {
+            *pixel = PixelPosition::from_tile(tile);
+        }
+    }
+    true
+}
+
+/// Shows the console over the top rows of the maze while it is open.
+#[allow(clippy::type_complexity)]
+pub fn draw_console(
+    mut commands: Commands,
+    console: Res<Console>,
+    map: Res<Map>,
+    mut line_query: Query<(&ConsoleLine, &mut ArcadeText)>,
+    console_query: Query<Entity, Or<(With<ConsoleLine>, With<ConsoleBackdrop>)>>,
+) {
+    if !console.open {
+        for entity in console_query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+
+    let width = map.width();
+    if console_query.is_empty() {
+        let size = Vec2::new(width as f32, OUTPUT_ROWS as f32 + 1.0) * TILE_SIZE as f32;
+        let centre = (size - TILE_SIZE as f32) / 2.0;
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: BACKDROP_COLOR,
+                    custom_size: Some(size),
+                    ..default()
+                },
+                transform: Transform::from_xyz(centre.x, -centre.y, 2.0),
+                ..default()
+            },
+            ConsoleBackdrop,
+        ));
+        for row in 0..=OUTPUT_ROWS {
+            let mut line = ArcadeTextBundle::new(
+                "",
+                TilePos {
+                    x: 0,
+                    y: row as i32,
+                },
+                OUTPUT_COLOR,
+            );
+            line.spatial.transform.translation.z = 3.0;
+            commands.spawn((line, ConsoleLine(row)));
+        }
+        return;
+    }
+
+    for (ConsoleLine(row), mut text) in &mut line_query {
+        let line = match console.output.get(*row) {
+            Some(line) => line.clone(),
+            None if *row == OUTPUT_ROWS => {
+                // The end of a long command shows as it is typed.
+                let skipped = (console.input.chars().count() + 2).saturating_sub(width);
+                format!(
+                    "- {}",
+                    console.input.chars().skip(skipped).collect::<String>()
+                )
+            }
+            None => String::new(),
+        };
+        // Padded to the width of the maze, for the terminal to blank what
+        // is under the console.
+        let line = format!("{:width$}", line.chars().take(width).collect::<String>());
+        if text.text != line {
+            text.text = line;
+        }
+    }
+}
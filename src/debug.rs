@@ -0,0 +1,180 @@
+use bevy::prelude::*;
+
+use crate::ai::blinky::ghost_moves;
+use crate::ai::{Ghost, GhostColor, GhostMode, GhostTarget};
+use crate::components::{Movable, Position};
+use crate::map::{Map, TilePos, TILE_SIZE};
+use crate::nav::NavGraph;
+use crate::text::{ArcadeText, ArcadeTextBundle};
+use crate::visuals::get_display_pos;
+
+#[derive(Resource, Default)]
+pub struct DebugOverlay {
+    pub enabled: bool,
+}
+
+/// Text drawn by the overlay. Labels are kept from one frame to the next,
+/// those not needed left blank.
+#[derive(Component)]
+pub struct DebugLabel;
+
+/// A label to show this frame: its text, tile and colour.
+type Label = (String, TilePos, Color);
+
+pub fn toggle_debug_overlay(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut overlay: ResMut<DebugOverlay>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F1) {
+        overlay.enabled = !overlay.enabled;
+    }
+}
+
+fn world_pos(tile_pos: &TilePos) -> Vec2 {
+    let pos = tile_pos.to_display_pos();
+    Vec2::new(pos.x, -pos.y)
+}
+
+pub fn mode_name(mode: &GhostMode) -> &'static str {
+    match mode {
+        GhostMode::Chase => "CHASE",
+        GhostMode::Frightened => "FRIGHTENED",
+        GhostMode::Eaten => "EATEN",
+    }
+}
+
+/// Draws, for every ghost, its target tile, the tiles it will choose between
+/// at its next decision along with their distance to the target, and its mode.
+/// The ghost house, the tiles ghosts can't turn up at and the navigation
+/// graph are drawn too.
+pub fn draw_debug_overlay(
+    commands: Commands,
+    overlay: Res<DebugOverlay>,
+    map: Res<Map>,
+    nav: Res<NavGraph>,
+    mut gizmos: Gizmos,
+    ghost_query: Query<(&Position, &Movable, &GhostMode, &GhostTarget, &GhostColor), With<Ghost>>,
+    label_query: Query<(&mut ArcadeText, &mut Transform), With<DebugLabel>>,
+) {
+    let mut labels = Vec::new();
+    if overlay.enabled {
+        draw_ghost_targets(&mut gizmos, &mut labels, &map, &nav, &ghost_query);
+    }
+    show_labels(commands, labels, label_query);
+}
+
+/// Blanks the labels once the game is over.
+pub fn hide_debug_labels(
+    commands: Commands,
+    label_query: Query<(&mut ArcadeText, &mut Transform), With<DebugLabel>>,
+) {
+    show_labels(commands, Vec::new(), label_query);
+}
+
+fn draw_ghost_targets(
+    gizmos: &mut Gizmos,
+    labels: &mut Vec<Label>,
+    map: &Map,
+    nav: &NavGraph,
+    ghost_query: &Query<(&Position, &Movable, &GhostMode, &GhostTarget, &GhostColor), With<Ghost>>,
+) {
+    let tile_size = Vec2::splat(TILE_SIZE as f32);
+    for tile in map.ghost_house.iter() {
+        gizmos.rect_2d(world_pos(tile), 0.0, tile_size, Color::GRAY);
+    }
+    // Intersections chasing ghosts can't turn up at, with the way they
+    // can't take.
+    for tile in map.no_up_tiles.iter() {
+        let pos = world_pos(tile);
+        gizmos.rect_2d(pos, 0.0, tile_size, Color::ORANGE);
+        gizmos.line_2d(pos, pos + Vec2::Y * TILE_SIZE as f32, Color::ORANGE);
+    }
+
+    draw_nav_graph(gizmos, nav);
+
+    for (position, movable, mode, target, color) in ghost_query.iter() {
+        let color = color.0;
+        let display_pos = get_display_pos(map, position, movable);
+        let ghost_pos = Vec2::new(display_pos.x, -display_pos.y);
+
+        labels.push((
+            mode_name(mode).to_string(),
+            TilePos {
+                x: position.x - 2,
+                y: position.y - 2,
+            },
+            color,
+        ));
+
+        let Some(target) = &target.0 else {
+            continue;
+        };
+
+        let target_pos = world_pos(target);
+        gizmos.rect_2d(target_pos, 0.0, tile_size, color);
+        gizmos.line_2d(ghost_pos, target_pos, color);
+
+        if let Some(path) = nav.path(&movable.target_tile, target) {
+            draw_tile_path(gizmos, &movable.target_tile, &path, color.with_a(0.4));
+        }
+
+        // Decisions are taken when reaching the tile the ghost is heading to.
+        let decision_pos = Position(movable.target_tile.clone());
+        for (candidate, _) in ghost_moves(map, &decision_pos, movable, mode) {
+            gizmos.circle_2d(world_pos(&candidate), TILE_SIZE as f32 / 2.0, color);
+            labels.push((
+                format!("{}", candidate.dist_sq_to(target)),
+                candidate,
+                color,
+            ));
+        }
+    }
+}
+
+/// Shows [labels] on the label entities there are, spawning more when they
+/// run short and blanking those left over.
+fn show_labels(
+    mut commands: Commands,
+    labels: Vec<Label>,
+    mut label_query: Query<(&mut ArcadeText, &mut Transform), With<DebugLabel>>,
+) {
+    let mut free = label_query.iter_mut();
+    for (text, tile, color) in labels {
+        let Some((mut label, mut transform)) = free.next() else {
+            commands.spawn((ArcadeTextBundle::new(text, tile, color), DebugLabel));
+            continue;
+        };
+        // Only redraw the glyphs when the label reads differently.
+        if label.text != text || label.color != color {
+            *label = ArcadeText { text, color };
+        }
+        transform.translation = world_pos(&tile).extend(transform.translation.z);
+    }
+    for (mut label, _) in free {
+        if !label.text.is_empty() {
+            label.text.clear();
+        }
+    }
+}
+
+/// Draws the junctions of the maze and the corridors joining them.
+fn draw_nav_graph(gizmos: &mut Gizmos, nav: &NavGraph) {
+    let color = Color::DARK_GRAY;
+    for junction in nav.junctions() {
+        gizmos.circle_2d(world_pos(junction), TILE_SIZE as f32 / 4.0, color);
+    }
+    for corridor in nav.corridors() {
+        draw_tile_path(gizmos, &corridor.from, &corridor.tiles, color);
+    }
+}
+
+fn draw_tile_path(gizmos: &mut Gizmos, start: &TilePos, path: &[TilePos], color: Color) {
+    let mut previous = start;
+    for tile in path {
+        // Steps through a tunnel jump across the maze, leave them out.
+        if previous.dist_sq_to(tile) == 1 {
+            gizmos.line_2d(world_pos(previous), world_pos(tile), color);
+        }
+        previous = tile;
+    }
+}
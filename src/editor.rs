@@ -0,0 +1,512 @@
+use std::path::PathBuf;
+
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::components::{Position, SpriteSheets};
+use crate::config::GameConfig;
+use crate::game_state::GameState;
+use crate::map::layout::{EMPTY, FOOD, GHOST_EATER, GHOST_START, PACMAN_START, WALL};
+use crate::map::validate::{validate, Problem};
+use crate::map::{Map, MapType, TilePos, TILE_SIZE};
+use crate::mazes::MazePalette;
+use crate::text::{ArcadeText, ArcadeTextBundle};
+
+/// The ghost house stamped by [Tool::GhostHouse], its top left corner on the
+/// clicked tile. The ghosts start above the door.
+const GHOST_HOUSE: [&str; 5] = [
+    "###--###", //
+    "#      #", "#      #", "#      #", "########",
+];
+const GHOST_HOUSE_START: TilePos = TilePos { x: 3, y: -1 };
+
+/// What clicking on the maze paints.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tool {
+    #[default]
+    Wall,
+    Pellet,
+    PowerPellet,
+    Floor,
+    /// Opens the row at both edges of the maze.
+    Tunnel,
+    GhostHouse,
+    PacmanStart,
+    GhostStart,
+}
+
+impl Tool {
+    const ALL: [Tool; 8] = [
+        Tool::Wall,
+        Tool::Pellet,
+        Tool::PowerPellet,
+        Tool::Floor,
+        Tool::Tunnel,
+        Tool::GhostHouse,
+        Tool::PacmanStart,
+        Tool::GhostStart,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Tool::Wall => "WALL",
+            Tool::Pellet => "PELLET",
+            Tool::PowerPellet => "POWER PELLET",
+            Tool::Floor => "FLOOR",
+            Tool::Tunnel => "TUNNEL",
+            Tool::GhostHouse => "GHOST HOUSE",
+            Tool::PacmanStart => "PAC-MAN START",
+            Tool::GhostStart => "GHOST START",
+        }
+    }
+}
+
+/// The maze being edited, kept as the text it is saved as.
+#[derive(Resource)]
+pub struct MazeEditor {
+    rows: Vec<Vec<char>>,
+    pub path: PathBuf,
+    tool: Tool,
+    status: String,
+    /// What stops the maze from being played, found after every change.
+    problems: Vec<Problem>,
+}
+
+impl MazeEditor {
+    /// Edits [path], starting from the arcade maze when it doesn't exist yet.
+    pub fn new(path: PathBuf) -> Self {
+        let mut editor = Self {
+            rows: Vec::new(),
+            path,
+            tool: Tool::default(),
+            status: String::new(),
+            problems: Vec::new(),
+        };
+        if editor.path.exists() {
+            editor.load();
+        } else {
+            editor.rows = to_rows(&Map::classic().to_layout());
+            editor.status = "NEW MAZE".into();
+        }
+        editor
+    }
+
+    pub fn layout(&self) -> Vec<String> {
+        self.rows.iter().map(|row| row.iter().collect()).collect()
+    }
+
+    /// The edited maze, as it would be played.
+    pub fn map(&self) -> Option<Map> {
+        let layout = self.layout();
+        Map::from_layout(&layout.iter().map(String::as_str).collect::<Vec<&str>>()).ok()
+    }
+
+    fn load(&mut self) {
+        match std::fs::read_to_string(&self.path) {
+            Ok(text) => {
+                self.rows = text.lines().map(|line| line.chars().collect()).collect();
+                self.status = "LOADED".into();
+            }
+            Err(err) => {
+                eprintln!("Could not read {}: {err}", self.path.display());
+                self.status = "LOAD FAILED".into();
+            }
+        }
+    }
+
+    fn save(&mut self) {
+        let text = self.layout().join("\n") + "\n";
+        match std::fs::write(&self.path, text) {
+            Ok(()) => self.status = "SAVED".into(),
+            Err(err) => {
+                eprintln!("Could not write {}: {err}", self.path.display());
+                self.status = "SAVE FAILED".into();
+            }
+        }
+    }
+
+    fn get(&self, pos: &TilePos) -> Option<char> {
+        self.rows.get(pos.y as usize)?.get(pos.x as usize).copied()
+    }
+
+    fn set(&mut self, pos: &TilePos, tile: char) {
+        if pos.x < 0 || pos.y < 0 {
+            return;
+        }
+        if let Some(cell) = self
+            .rows
+            .get_mut(pos.y as usize)
+            .and_then(|row| row.get_mut(pos.x as usize))
+        {
+            *cell = tile;
+        }
+    }
+
+    /// There is only one of each start tile, placing it elsewhere moves it.
+    fn move_start(&mut self, start: char, pos: &TilePos) {
+        for cell in self.rows.iter_mut().flatten() {
+            if *cell == start {
+                *cell = EMPTY;
+            }
+        }
+        self.set(pos, start);
+    }
+
+    fn paint(&mut self, tool: Tool, pos: &TilePos) {
+        match tool {
+            Tool::Wall => self.set(pos, WALL),
+            Tool::Pellet => self.set(pos, FOOD),
+            Tool::PowerPellet => self.set(pos, GHOST_EATER),
+            Tool::Floor => self.set(pos, EMPTY),
+            Tool::Tunnel => {
+                let width = self.rows.get(pos.y as usize).map_or(0, Vec::len) as i32;
+                self.set(&TilePos { x: 0, y: pos.y }, EMPTY);
+                self.set(
+                    &TilePos {
+                        x: width - 1,
+                        y: pos.y,
+                    },
+                    EMPTY,
+                );
+            }
+            Tool::GhostHouse => {
+                for (y, row) in GHOST_HOUSE.iter().enumerate() {
+                    for (x, tile) in row.chars().enumerate() {
+                        let tile_pos = TilePos {
+                            x: pos.x + x as i32,
+                            y: pos.y + y as i32,
+                        };
+                        self.set(&tile_pos, tile);
+                    }
+                }
+                let start = TilePos {
+                    x: pos.x + GHOST_HOUSE_START.x,
+                    y: pos.y + GHOST_HOUSE_START.y,
+                };
+                self.move_start(GHOST_START, &start);
+            }
+            Tool::PacmanStart => self.move_start(PACMAN_START, pos),
+            Tool::GhostStart => self.move_start(GHOST_START, pos),
+        }
+    }
+}
+
+fn to_rows(layout: &[String]) -> Vec<Vec<char>> {
+    layout.iter().map(|row| row.chars().collect()).collect()
+}
+
+/// The maze being edited is played in [GameState::Playing] instead of the
+/// usual mazes, until going back to the editor.
+#[derive(Resource)]
+pub struct PlayTest(pub Map);
+
+/// Marks the entities drawing the editor, despawned when leaving it.
+#[derive(Component)]
+pub struct EditorEntity;
+
+#[derive(Component)]
+pub struct ToolText;
+
+#[derive(Component)]
+pub struct StatusText;
+
+/// Starts in the editor when launched with `--edit`.
+pub fn open_editor_from_config(
+    mut commands: Commands,
+    config: Res<GameConfig>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if let Some(path) = &config.edit {
+        commands.insert_resource(MazeEditor::new(path.clone()));
+        next_state.set(GameState::Editor);
+    }
+}
+
+/// F2 opens the editor from the attract loop, on `maze.txt` unless another
+/// file was given with `--edit`.
+pub fn open_editor_on_input(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    editor: Option<Res<MazeEditor>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F2) {
+        return;
+    }
+
+    if editor.is_none() {
+        commands.insert_resource(MazeEditor::new(PathBuf::from("maze.txt")));
+    }
+    next_state.set(GameState::Editor);
+}
+
+pub fn start_editor(
+    mut commands: Commands,
+    mut editor: ResMut<MazeEditor>,
+    mut palette: ResMut<MazePalette>,
+) {
+    commands.remove_resource::<PlayTest>();
+    *palette = MazePalette::CLASSIC;
+    // Draw the maze again, coming back from a play-test.
+    editor.set_changed();
+
+    let below_maze = editor.rows.len() as i32;
+    commands.spawn((
+        ArcadeTextBundle::new(
+            "",
+            TilePos {
+                x: 0,
+                y: below_maze,
+            },
+            Color::WHITE,
+        ),
+        ToolText,
+        EditorEntity,
+    ));
+    commands.spawn((
+        ArcadeTextBundle::new(
+            "",
+            TilePos {
+                x: 0,
+                y: below_maze + 1,
+            },
+            Color::YELLOW,
+        ),
+        StatusText,
+        EditorEntity,
+    ));
+}
+
+/// Number keys pick the tool, Ctrl+S saves, Ctrl+L reloads the file, F5
+/// play-tests the maze and Escape leaves the editor.
+pub fn editor_keys(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut editor: ResMut<MazeEditor>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    const TOOL_KEYS: [KeyCode; 8] = [
+        KeyCode::Key1,
+        KeyCode::Key2,
+        KeyCode::Key3,
+        KeyCode::Key4,
+        KeyCode::Key5,
+        KeyCode::Key6,
+        KeyCode::Key7,
+        KeyCode::Key8,
+    ];
+    for (key, tool) in TOOL_KEYS.iter().zip(Tool::ALL) {
+        if keyboard_input.just_pressed(*key) {
+            editor.tool = tool;
+        }
+    }
+
+    let ctrl = keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    if ctrl && keyboard_input.just_pressed(KeyCode::S) {
+        editor.save();
+    }
+    if ctrl && keyboard_input.just_pressed(KeyCode::L) {
+        editor.load();
+    }
+
+    // Mazes with problems could leave the ghosts stuck, or worse.
+    if keyboard_input.just_pressed(KeyCode::F5) {
+        match editor.map() {
+            Some(map) if editor.problems.is_empty() => {
+                commands.insert_resource(PlayTest(map));
+                next_state.set(GameState::Playing);
+            }
+            _ => {
+                for problem in editor.problems.iter() {
+                    eprintln!("{}: {problem}", editor.path.display());
+                }
+                editor.status = "FIX PROBLEMS FIRST".into();
+            }
+        }
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::Attract);
+    }
+}
+
+/// F5 during a play-test goes back to editing.
+pub fn end_play_test(
+    keyboard_input: Res<Input<KeyCode>>,
+    play_test: Option<Res<PlayTest>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if play_test.is_some() && keyboard_input.just_pressed(KeyCode::F5) {
+        next_state.set(GameState::Editor);
+    }
+}
+
+/// The tile under the mouse cursor, if any.
+fn hovered_tile(
+    window_query: &Query<&Window, With<PrimaryWindow>>,
+    camera_query: &Query<(&Camera, &GlobalTransform)>,
+) -> Option<TilePos> {
+    let cursor = window_query.get_single().ok()?.cursor_position()?;
+    let (camera, camera_transform) = camera_query.get_single().ok()?;
+
+    // The cursor is relative to the window, the camera only draws in its
+    // viewport.
+    let viewport_min = camera
+        .logical_viewport_rect()
+        .map_or(Vec2::ZERO, |rect| rect.min);
+    let world = camera.viewport_to_world_2d(camera_transform, cursor - viewport_min)?;
+
+    let tile = TILE_SIZE as f32;
+    Some(TilePos {
+        x: (world.x / tile + 0.5).floor() as i32,
+        y: (-world.y / tile + 0.5).floor() as i32,
+    })
+}
+
+/// The left mouse button paints with the current tool, the right one clears
+/// tiles back to empty floor.
+pub fn paint_maze(
+    mouse_input: Res<Input<MouseButton>>,
+    mut editor: ResMut<MazeEditor>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+) {
+    let tool = if mouse_input.pressed(MouseButton::Left) {
+        editor.tool
+    } else if mouse_input.pressed(MouseButton::Right) {
+        Tool::Floor
+    } else {
+        return;
+    };
+
+    let Some(pos) = hovered_tile(&window_query, &camera_query) else {
+        return;
+    };
+    if editor.get(&pos).is_none() {
+        return;
+    }
+
+    // Only touch the resource when the stroke changes something, so that the
+    // maze isn't tiled again every frame the button is held.
+    let before = editor.rows.clone();
+    editor.bypass_change_detection().paint(tool, &pos);
+    if editor.rows != before {
+        editor.status.clear();
+        editor.set_changed();
+    }
+}
+
+/// Tiles and validates the edited maze again after every change, respawning
+/// its tiles.
+pub fn refresh_editor_maze(
+    mut commands: Commands,
+    mut editor: ResMut<MazeEditor>,
+    sprite_sheets: Res<SpriteSheets>,
+    tile_query: Query<Entity, (With<EditorEntity>, With<MapType>)>,
+) {
+    if !editor.is_changed() {
+        return;
+    }
+
+    let layout = editor.layout();
+    let rows = layout.iter().map(String::as_str).collect::<Vec<&str>>();
+    let map = match Map::from_layout(&rows) {
+        Ok(map) => map,
+        Err(err) => {
+            eprintln!("{}: {err}", editor.path.display());
+            editor.status = "INVALID MAZE FILE".into();
+            editor.problems = vec![Problem::Layout(err)];
+            return;
+        }
+    };
+    editor.problems = validate(&rows);
+
+    for entity in tile_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    for (y, row) in map.rows().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
+            let tile_pos = TilePos {
+                x: x as i32,
+                y: y as i32,
+            };
+            let pos = tile_pos.to_display_pos();
+            commands.spawn((
+                Position(tile_pos),
+                tile.clone(),
+                SpriteSheetBundle {
+                    texture_atlas: sprite_sheets.maze.clone(),
+                    sprite: sprite_sheets.manifest.tile_sprite(tile),
+                    transform: Transform::from_translation(Vec3::new(pos.x, -pos.y, -1.0)),
+                    ..default()
+                },
+                EditorEntity,
+            ));
+        }
+    }
+    commands.insert_resource(map);
+}
+
+pub fn update_editor_text(
+    editor: Res<MazeEditor>,
+    mut tool_query: Query<&mut ArcadeText, (With<ToolText>, Without<StatusText>)>,
+    mut status_query: Query<&mut ArcadeText, (With<StatusText>, Without<ToolText>)>,
+) {
+    if !editor.is_changed() {
+        return;
+    }
+
+    let tool_number = Tool::ALL
+        .iter()
+        .position(|tool| *tool == editor.tool)
+        .unwrap_or(0)
+        + 1;
+    for mut text in &mut tool_query {
+        text.text = format!("{tool_number} {}", editor.tool.name());
+    }
+    let status = match editor.problems.len() {
+        _ if !editor.status.is_empty() => editor.status.clone(),
+        0 => "MAZE OK".to_string(),
+        1 => "1 PROBLEM".to_string(),
+        count => format!("{count} PROBLEMS"),
+    };
+    for mut text in &mut status_query {
+        text.text = status.clone();
+    }
+}
+
+/// Outlines the hovered tile, the start tiles and the tiles with problems.
+pub fn draw_editor_overlay(
+    editor: Res<MazeEditor>,
+    mut gizmos: Gizmos,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+) {
+    let world_pos = |tile: &TilePos| {
+        let pos = tile.to_display_pos();
+        Vec2::new(pos.x, -pos.y)
+    };
+    let tile_size = Vec2::splat(TILE_SIZE as f32);
+
+    if let Some(pos) = hovered_tile(&window_query, &camera_query) {
+        gizmos.rect_2d(world_pos(&pos), 0.0, tile_size, Color::WHITE);
+    }
+
+    for pos in editor.problems.iter().filter_map(Problem::pos) {
+        gizmos.rect_2d(world_pos(pos), 0.0, tile_size, Color::RED);
+    }
+
+    for (y, row) in editor.rows.iter().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
+            let color = match *tile {
+                PACMAN_START => Color::YELLOW,
+                GHOST_START => Color::RED,
+                _ => continue,
+            };
+            let pos = TilePos {
+                x: x as i32,
+                y: y as i32,
+            };
+            gizmos.circle_2d(world_pos(&pos), TILE_SIZE as f32 / 2.0, color);
+        }
+    }
+}
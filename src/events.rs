@@ -0,0 +1,30 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+#[derive(Event)]
+pub struct PelletEaten {
+    /// Whether the pellet was a power pellet, frightening the ghosts.
+    pub power: bool,
+}
+
+#[derive(Event)]
+pub struct GhostEaten;
+
+#[derive(Event)]
+pub struct PacmanDied;
+
+/// Every gameplay event, for a game to start without any left over.
+#[derive(SystemParam)]
+pub struct GameEvents<'w> {
+    pellet_eaten: ResMut<'w, Events<PelletEaten>>,
+    ghost_eaten: ResMut<'w, Events<GhostEaten>>,
+    pacman_died: ResMut<'w, Events<PacmanDied>>,
+}
+
+impl GameEvents<'_> {
+    pub fn clear(&mut self) {
+        self.pellet_eaten.clear();
+        self.ghost_eaten.clear();
+        self.pacman_died.clear();
+    }
+}
@@ -0,0 +1,121 @@
+//! The bonus fruit, worth more the further the game goes. It shows where
+//! Pac-Man starts, below the ghost house, and is gone again if not eaten in
+//! time. For now only the console's `spawn fruit` puts one in the maze.
+
+use bevy::prelude::*;
+
+use crate::components::{LevelEntity, Player, Position, Score, SpriteSheets};
+use crate::map::Map;
+use crate::party::PlayerIndex;
+use crate::players::{award_points, Players};
+
+/// How long a fruit stays uneaten, as in the arcade.
+const FRUIT_SECS: f32 = 9.5;
+/// What each fruit is worth, from the cherry of the first level to the key
+/// of the thirteenth and every level after.
+const FRUIT_POINTS: [u32; 8] = [100, 300, 500, 700, 1000, 2000, 3000, 5000];
+
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Fruit {
+    /// Which of the arcade's fruits it is, see [FRUIT_POINTS].
+    pub kind: usize,
+    /// Counts down the time left to eat it.
+    pub timer: Timer,
+}
+
+impl Fruit {
+    /// The fruit of [level]: a cherry, a strawberry, then each fruit after
+    /// for two levels.
+    pub fn for_level(level: u32) -> Self {
+        let kind = match level {
+            0 | 1 => 0,
+            2 => 1,
+            level => ((level as usize - 1) / 2 + 1).min(FRUIT_POINTS.len() - 1),
+        };
+        Self {
+            kind,
+            timer: Timer::from_seconds(FRUIT_SECS, TimerMode::Once),
+        }
+    }
+
+    pub fn points(&self) -> u32 {
+        FRUIT_POINTS[self.kind]
+    }
+}
+
+/// Puts the fruit of [level] in the maze, returning what it is worth.
+pub fn spawn_fruit(
+    commands: &mut Commands,
+    sprite_sheets: &SpriteSheets,
+    map: &Map,
+    level: u32,
+) -> u32 {
+    let fruit = Fruit::for_level(level);
+    let points = fruit.points();
+    let pos = map.pacman_start.to_display_pos();
+    commands.spawn((
+        Position(map.pacman_start.clone()),
+        SpriteSheetBundle {
+            texture_atlas: sprite_sheets.characters.clone(),
+            sprite: TextureAtlasSprite::new(sprite_sheets.manifest.fruit_sprite(fruit.kind)),
+            transform: Transform::from_translation(Vec3::new(pos.x, -pos.y, 0.0)),
+            ..default()
+        },
+        fruit,
+        LevelEntity,
+    ));
+    points
+}
+
+/// Scores the fruit for the player whose Pac-Man gets to it first.
+pub fn eat_fruit(
+    mut commands: Commands,
+    mut score: ResMut<Score>,
+    mut players: ResMut<Players>,
+    player_query: Query<(&Position, Option<&PlayerIndex>), With<Player>>,
+    fruit_query: Query<(Entity, &Position, &Fruit)>,
+) {
+    let current = players.current();
+    let mut pacmen = player_query
+        .iter()
+        .map(|(pos, index)| (pos, index.map_or(current, |index| index.0)))
+        .collect::<Vec<_>>();
+    pacmen.sort_by_key(|(_, player)| *player);
+
+    for (entity, fruit_pos, fruit) in &fruit_query {
+        if let Some((_, player)) = pacmen.iter().find(|(pos, _)| *pos == fruit_pos) {
+            award_points(&mut score, &mut players, *player, fruit.points());
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Takes away the fruit left uneaten for too long.
+pub fn expire_fruit(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut fruit_query: Query<(Entity, &mut Fruit)>,
+) {
+    for (entity, mut fruit) in &mut fruit_query {
+        if fruit.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fruit_follows_the_arcade_levels() {
+        let points = (1..=14)
+            .map(|level| Fruit::for_level(level).points())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            points,
+            [100, 300, 500, 500, 700, 700, 1000, 1000, 2000, 2000, 3000, 3000, 5000, 5000]
+        );
+    }
+}
@@ -0,0 +1,18 @@
+use bevy::prelude::*;
+
+#[derive(States, Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum GameState {
+    /// The arcade attract loop: character introductions and points table.
+    #[default]
+    Attract,
+    /// A game played by the computer while waiting for a player.
+    Demo,
+    Playing,
+    /// Building a maze with the mouse.
+    Editor,
+}
+
+/// Returns true while the maze and its characters should be simulated.
+pub fn in_game(state: Res<State<GameState>>) -> bool {
+    matches!(state.get(), GameState::Demo | GameState::Playing)
+}
@@ -0,0 +1,188 @@
+use bevy::prelude::*;
+
+use crate::components::{LevelEntity, Score, SpriteSheets};
+use crate::map::{Map, TilePos, TILE_SIZE};
+use crate::players::{best_score, Players};
+use crate::text::{ArcadeText, ArcadeTextBundle};
+
+/// "READY!" stays up for as long as the intro tune plays.
+const READY_DURATION_SECS: f32 = 4.3;
+const PLAYER_NAMES: [&str; 2] = ["PLAYER ONE", "PLAYER TWO"];
+
+/// The score of the player at [0], counting from 0.
+#[derive(Component)]
+pub struct ScoreText(pub usize);
+
+#[derive(Component)]
+pub struct HighScoreText;
+
+#[derive(Component, Deref, DerefMut, Default, Reflect)]
+#[reflect(Component)]
+pub struct ReadyText(Timer);
+
+pub fn spawn_hud(mut commands: Commands) {
+    commands.spawn(ArcadeTextBundle::new(
+        "1UP",
+        TilePos { x: 3, y: -3 },
+        Color::WHITE,
+    ));
+    commands.spawn(ArcadeTextBundle::new(
+        "HIGH SCORE",
+        TilePos { x: 9, y: -3 },
+        Color::WHITE,
+    ));
+    commands.spawn(ArcadeTextBundle::new(
+        "2UP",
+        TilePos { x: 22, y: -3 },
+        Color::WHITE,
+    ));
+    commands.spawn((
+        ArcadeTextBundle::new(format_score(0), TilePos { x: 1, y: -2 }, Color::WHITE),
+        ScoreText(0),
+    ));
+    commands.spawn((
+        ArcadeTextBundle::new("", TilePos { x: 20, y: -2 }, Color::WHITE),
+        ScoreText(1),
+    ));
+    commands.spawn((
+        ArcadeTextBundle::new(format_score(0), TilePos { x: 11, y: -2 }, Color::WHITE),
+        HighScoreText,
+    ));
+}
+
+/// Right-aligns [score] on six columns, the way the arcade shows it
+/// (an empty score is still shown as "00").
+fn format_score(score: u32) -> String {
+    if score == 0 {
+        format!("{:>6}", "00")
+    } else {
+        format!("{score:>6}")
+    }
+}
+
+pub fn reset_score(mut score: ResMut<Score>) {
+    score.current = 0;
+}
+
+pub fn update_high_score(mut score: ResMut<Score>, players: Res<Players>) {
+    let best = best_score(&score, &players);
+    if best > score.high {
+        score.high = best;
+    }
+}
+
+/// Shows the score of every player, the one at the controls being
+/// [Score::current]. Players not in the game have none.
+pub fn update_score_text(
+    score: Res<Score>,
+    players: Res<Players>,
+    mut score_query: Query<(&mut ArcadeText, &ScoreText), Without<HighScoreText>>,
+    mut high_score_query: Query<&mut ArcadeText, (With<HighScoreText>, Without<ScoreText>)>,
+) {
+    if !score.is_changed() && !players.is_changed() {
+        return;
+    }
+
+    for (mut text, ScoreText(player)) in &mut score_query {
+        text.text = if *player == players.current() {
+            format_score(score.current)
+        } else {
+            players
+                .turn(*player)
+                .map_or_else(String::new, |turn| format_score(turn.score))
+        };
+    }
+    for mut text in &mut high_score_query {
+        text.text = format_score(score.high);
+    }
+}
+
+pub fn spawn_ready_text(mut commands: Commands) {
+    commands.spawn((
+        ArcadeTextBundle::new("READY!", TilePos { x: 11, y: 17 }, Color::YELLOW),
+        ReadyText(Timer::from_seconds(READY_DURATION_SECS, TimerMode::Once)),
+        LevelEntity,
+    ));
+}
+
+pub fn hide_ready_text(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut ReadyText)>,
+) {
+    for (entity, mut timer) in &mut query {
+        timer.tick(time.delta());
+        if timer.just_finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Says whose turn it is in a two-player game, for as long as "READY!".
+pub fn spawn_player_text(mut commands: Commands, players: Res<Players>) {
+    if !players.take_turns() {
+        return;
+    }
+
+    commands.spawn((
+        ArcadeTextBundle::new(
+            PLAYER_NAMES[players.current()],
+            TilePos { x: 9, y: 11 },
+            Color::CYAN,
+        ),
+        ReadyText(Timer::from_seconds(READY_DURATION_SECS, TimerMode::Once)),
+        LevelEntity,
+    ));
+}
+
+/// One of the lives left in reserve, below the maze.
+#[derive(Component)]
+pub struct LifeIcon;
+
+/// Shows the lives the player at the controls has left besides the one
+/// being played.
+pub fn update_life_icons(
+    mut commands: Commands,
+    players: Res<Players>,
+    map: Res<Map>,
+    sprite_sheets: Res<SpriteSheets>,
+    icon_query: Query<Entity, With<LifeIcon>>,
+) {
+    if !players.is_changed() && !map.is_changed() {
+        return;
+    }
+
+    for entity in icon_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    // Icons are two tiles wide, centred between the tiles they cover.
+    let half_tile = TILE_SIZE as f32 / 2.0;
+    for i in 0..players.lives().saturating_sub(1) {
+        let pos = TilePos {
+            x: 2 + 2 * i as i32,
+            y: map.height() as i32,
+        }
+        .to_display_pos();
+        commands.spawn((
+            SpriteSheetBundle {
+                texture_atlas: sprite_sheets.characters.clone(),
+                sprite: TextureAtlasSprite::new(sprite_sheets.manifest.life_icon),
+                transform: Transform::from_translation(Vec3::new(
+                    pos.x + half_tile,
+                    -pos.y - half_tile,
+                    0.0,
+                )),
+                ..default()
+            },
+            LifeIcon,
+        ));
+    }
+}
+
+/// The demo game is played under a "GAME  OVER" banner, as in the arcade.
+pub fn spawn_game_over_text(mut commands: Commands) {
+    commands.spawn((
+        ArcadeTextBundle::new("GAME  OVER", TilePos { x: 9, y: 17 }, Color::RED),
+        LevelEntity,
+    ));
+}
@@ -1,51 +1,498 @@
 use ai::blinky::{blinky_ai, Blinky};
-use ai::Ghost;
-use bevy::{prelude::*, window::PresentMode};
+use ai::profile::CasualReaction;
+use ai::{
+    end_frightened, frighten_ghosts, return_home, FrightenedTimer, Ghost, GhostColor, GhostMode,
+    GhostTarget,
+};
+use attract::{
+    demo_steer, despawn_with, end_demo, roll_call, start_attract, start_demo, start_game_on_input,
+    AttractEntity,
+};
+use audio::{
+    play_chomp, play_death, play_intro, synthesise_sounds, update_background_loop, ChompState,
+    GameSound, SynthSound,
+};
+use std::time::Duration;
+
+use bevy::{
+    app::{MainScheduleOrder, RunFixedUpdateLoop, ScheduleRunnerPlugin},
+    asset::ChangeWatcher,
+    audio::AddAudioSource,
+    ecs::schedule::{ExecutorKind, ScheduleLabel},
+    ecs::system::SystemParam,
+    input::{InputPlugin, InputSystem},
+    prelude::*,
+    time::TimeUpdateStrategy,
+    window::{PresentMode, ReceivedCharacter},
+};
+use bot::{read_bot_moves, send_bot_observation, BotLink};
+use camera::{fit_camera, spawn_camera, toggle_camera_layout, CameraLayout};
 use common::Direction;
-use components::{AnimationIndices, AnimationTimer, Movable, Player, Position};
-use map::MapType;
-use map::{TilePos, MAP};
-use player::{check_collision, move_player, steer};
-use visuals::{animate_sprite, draw_movable, sprite_index_for_wall_type};
+use components::{
+    AnimationTimer, Level, LevelClear, LevelEntity, Movable, NormalAnimation, Pellet, Player,
+    Position, Score, SpriteSheets,
+};
+use config::GameConfig;
+use console::{console_input, draw_console, game_running, run_console_command, Console};
+use debug::{draw_debug_overlay, hide_debug_labels, toggle_debug_overlay, DebugOverlay};
+use editor::{
+    draw_editor_overlay, editor_keys, end_play_test, open_editor_from_config, open_editor_on_input,
+    paint_maze, refresh_editor_maze, start_editor, update_editor_text, EditorEntity, PlayTest,
+};
+use events::{GhostEaten, PacmanDied, PelletEaten};
+use fruit::{eat_fruit, expire_fruit};
+use game_state::{in_game, GameState};
+use hud::{
+    hide_ready_text, reset_score, spawn_game_over_text, spawn_hud, spawn_player_text,
+    spawn_ready_text, update_high_score, update_life_icons, update_score_text, LifeIcon,
+};
+use map::{Map, MapType, TilePos};
+use mazes::{Maze, MazePalette, MazeSet};
+use movement::pixel::{move_characters_pixels, PixelPosition, SpeedFrame, FRAME_SECS};
+use movement::{move_characters, EatingPause, MovementModel, MoverKind, NextDirection};
+use nav::{use_nav_graph, NavGraph};
+use net::{NetLink, NetRole};
+use party::{
+    steer_ghosts, Controls, PartyMode, PlayerIndex, RequestedDirection, PARTY_CONTROLS,
+    SECOND_PACMAN_TINT,
+};
+use player::{check_collision, eat_pellets, steer};
+use players::{
+    between_turns, end_game, reset_players, restore_pellets, save_turn, GameOver, NextTurn,
+    Players, SwitchPlayer,
+};
+use save::{quick_save_keys, resume_from_config, resume_saved_game, ResumeGame, SaveFile};
+use spectate::{publish_game_state, SpectatorServer};
+use sprites::{
+    apply_sprite_manifest, reload_sprite_manifest, SpriteManifest, SpriteManifestHandle,
+    SpriteManifestLoader, SPRITE_MANIFEST_PATH,
+};
+use terminal::{draw_terminal, enter_terminal, read_terminal_input, TerminalScreen};
+use text::render_text;
+use visuals::{animate_sprite, draw_movable, flash_maze, ghost_mode_visuals, tint_maze};
 
 mod ai;
+mod attract;
+mod audio;
+mod bot;
+mod camera;
 mod common;
 mod components;
+mod config;
+mod console;
+mod debug;
+mod editor;
+mod events;
+mod fruit;
+mod game_state;
+mod hud;
 mod map;
 mod math;
+mod mazes;
+mod movement;
+mod nav;
+mod net;
+mod party;
 mod player;
+mod players;
+mod save;
+mod snapshot;
+mod spectate;
+mod sprites;
+mod terminal;
+mod text;
 mod visuals;
 
 fn main() {
-    App::new()
-        .add_plugins(
-            DefaultPlugins
-                .set(ImagePlugin::default_nearest())
-                .set(WindowPlugin {
-                    primary_window: Some(Window {
-                        title: "Pacman-RS".into(),
-                        resolution: (3000., 1600.).into(),
-                        present_mode: PresentMode::AutoVsync,
-                        ..default()
-                    }),
-                    ..default()
-                }),
+    let args = std::env::args().collect::<Vec<String>>();
+    if args.get(1).map(String::as_str) == Some("validate") {
+        let valid = map::validate::run_validate_command(&args[2..], mazes::builtin_layouts());
+        std::process::exit(if valid { 0 } else { 1 });
+    }
+    let config = GameConfig::load();
+    if args.get(1).map(String::as_str) == Some("generate") {
+        match map::generate::generate(&config.maze_params()) {
+            Ok(layout) => {
+                for row in layout {
+                    println!("{row}");
+                }
+            }
+            Err(problems) => {
+                eprintln!("Can't generate a valid maze:");
+                for problem in problems {
+                    eprintln!("  {problem}");
+                }
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let link = NetRole::from_config(&config).map(|role| {
+        NetLink::open(role).unwrap_or_else(|err| {
+            eprintln!("Can't open the network connection: {err}");
+            std::process::exit(1);
+        })
+    });
+    if config.bot && config.tui {
+        eprintln!(
+            "A bot plays over the standard input and output, which --tui draws on. \
+             Run it with --headless to play without a window."
+        );
+        std::process::exit(1);
+    }
+    if config.lockstep && !config.bot {
+        eprintln!("--lockstep waits for a bot: start one with --bot");
+        std::process::exit(1);
+    }
+    if config.headless && config.tui {
+        eprintln!("--headless draws nothing, --tui draws in the terminal: pick one");
+        std::process::exit(1);
+    }
+    let spectators = config.spectate.map(|port| {
+        SpectatorServer::open(port).unwrap_or_else(|err| {
+            eprintln!("Can't listen for spectators on port {port}: {err}");
+            std::process::exit(1);
+        })
+    });
+
+    let bot = config.bot.then_some(config.lockstep);
+    let mut app = App::new();
+    if config.headless {
+        add_headless_frontend(&mut app);
+    } else if config.tui {
+        add_terminal_frontend(&mut app);
+    } else {
+        add_window_frontend(&mut app, &config);
+    }
+
+    snapshot::register_snapshot_types(&mut app);
+    app.add_state::<GameState>()
+        .init_resource::<Score>()
+        .init_resource::<FrightenedTimer>()
+        .insert_resource(Level(config.level))
+        .insert_resource(Players::new(1, &config))
+        .init_resource::<Map>()
+        .init_resource::<MazePalette>()
+        .init_resource::<CasualReaction>()
+        .insert_resource(config.ai_dice())
+        .init_resource::<SpeedFrame>()
+        .init_resource::<Console>()
+        .insert_resource(NavGraph::new(&Map::default()))
+        .insert_resource(config.maze_set())
+        .insert_resource(config.ai)
+        .insert_resource(config.movement)
+        .insert_resource(config.party)
+        .insert_resource(FixedTime::new_from_secs(FRAME_SECS))
+        .insert_resource(SaveFile(config.save_file.clone()))
+        .insert_resource(config)
+        .add_event::<PelletEaten>()
+        .add_event::<GhostEaten>()
+        .add_event::<PacmanDied>()
+        .add_systems(Startup, (spawn_hud, resume_from_config))
+        .add_systems(
+            PreUpdate,
+            (console_input, run_console_command)
+                .chain()
+                .after(InputSystem),
+        )
+        .add_systems(
+            PreUpdate,
+            resume_saved_game
+                .run_if(in_state(GameState::Playing).and_then(resource_exists::<ResumeGame>())),
+        )
+        .add_systems(OnEnter(GameState::Attract), start_attract)
+        .add_systems(OnExit(GameState::Attract), despawn_with::<AttractEntity>)
+        .add_systems(
+            OnEnter(GameState::Demo),
+            (
+                reset_score,
+                (reset_level, spawn_level, apply_deferred, start_demo).chain(),
+                spawn_game_over_text,
+            ),
+        )
+        .add_systems(OnExit(GameState::Demo), despawn_with::<LevelEntity>)
+        .add_systems(
+            OnExit(GameState::Playing),
+            (
+                despawn_with::<LevelEntity>,
+                despawn_with::<GameSound>,
+                despawn_with::<LifeIcon>,
+                reset_players,
+            ),
+        )
+        .add_systems(
+            OnEnter(GameState::Playing),
+            (
+                reset_score,
+                (reset_level, spawn_level).chain(),
+                spawn_ready_text,
+            ),
         )
-        // set the global default background color
-        .insert_resource(ClearColor(Color::BLACK))
-        .add_systems(Startup, setup)
         .add_systems(
             Update,
+            (
+                roll_call.run_if(in_state(GameState::Attract)),
+                (demo_steer, end_demo)
+                    .run_if(in_state(GameState::Demo))
+                    .run_if(game_running),
+                (
+                    steer.before(move_characters),
+                    update_high_score.after(eat_pellets),
+                    hide_ready_text,
+                    clear_level,
+                    end_play_test,
+                    handle_death.after(eat_pellets),
+                    update_life_icons,
+                )
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(game_running),
+                (
+                    save_turn,
+                    despawn_with::<LevelEntity>,
+                    apply_deferred,
+                    start_turn,
+                    apply_deferred,
+                    restore_pellets,
+                    spawn_ready_text,
+                    spawn_player_text,
+                )
+                    .chain()
+                    .run_if(resource_exists::<SwitchPlayer>())
+                    .run_if(game_running),
+                (
+                    spawn_game_over_text.run_if(resource_added::<GameOver>()),
+                    end_game,
+                )
+                    .run_if(resource_exists::<GameOver>())
+                    .run_if(game_running),
+                (flash_maze, next_level.run_if(level_flashed))
+                    .chain()
+                    .run_if(resource_exists::<LevelClear>())
+                    .run_if(game_running),
+                start_game_on_input.run_if(
+                    in_state(GameState::Attract)
+                        .or_else(in_state(GameState::Demo))
+                        .and_then(not(resource_exists::<NetLink>())),
+                ),
+            ),
+        )
+        .add_systems(
+            Update,
+            // In a set order, for games over the network to play out the
+            // same on both machines.
             (
                 animate_sprite,
-                move_player,
+                move_characters.run_if(resource_equals(MovementModel::Tiles)),
                 draw_movable,
-                steer,
                 check_collision,
+                eat_pellets,
+                eat_fruit,
+                expire_fruit,
                 blinky_ai,
+                steer_ghosts,
+                frighten_ghosts,
+                end_frightened,
+                return_home,
+                ghost_mode_visuals,
+            )
+                .chain()
+                .run_if(
+                    in_game
+                        .and_then(not(resource_exists::<LevelClear>()))
+                        .and_then(not(between_turns))
+                        .and_then(game_running),
+                ),
+        )
+        .add_systems(
+            FixedUpdate,
+            move_characters_pixels.run_if(
+                in_game
+                    .and_then(not(resource_exists::<LevelClear>()))
+                    .and_then(not(between_turns))
+                    .and_then(resource_equals(MovementModel::Pixels))
+                    .and_then(game_running),
             ),
         )
-        .run();
+        .add_systems(Update, tint_maze.after(flash_maze))
+        .add_systems(Update, quick_save_keys)
+        .add_systems(Update, update_score_text)
+        .add_systems(Update, draw_console);
+    if let Some(link) = link {
+        add_network(&mut app, link);
+    }
+    if let Some(lockstep) = bot {
+        app.insert_resource(BotLink::start(lockstep))
+            .add_systems(
+                PreUpdate,
+                read_bot_moves.after(InputSystem).before(console_input),
+            )
+            .add_systems(Last, send_bot_observation);
+    }
+    if let Some(spectators) = spectators {
+        app.insert_resource(spectators)
+            .add_systems(Last, publish_game_state);
+    }
+    app.run();
+}
+
+/// Draws the game in a window, with sound, the maze editor and the debug
+/// overlay.
+fn add_window_frontend(app: &mut App, config: &GameConfig) {
+    app.add_plugins(
+        DefaultPlugins
+            .set(ImagePlugin::default_nearest())
+            // For the sprite manifest to be picked up as it is saved.
+            .set(AssetPlugin {
+                watch_for_changes: ChangeWatcher::with_delay(Duration::from_millis(200)),
+                ..default()
+            })
+            .set(WindowPlugin {
+                primary_window: Some(Window {
+                    title: "Pacman-RS".into(),
+                    resolution: (config.window_width, config.window_height).into(),
+                    present_mode: PresentMode::AutoVsync,
+                    ..default()
+                }),
+                ..default()
+            }),
+    )
+    // set the global default background color
+    .insert_resource(ClearColor(Color::BLACK))
+    .init_resource::<CameraLayout>()
+    .init_resource::<ChompState>()
+    .init_resource::<DebugOverlay>()
+    .add_audio_source::<SynthSound>()
+    .add_asset::<SpriteManifest>()
+    .init_asset_loader::<SpriteManifestLoader>()
+    .add_systems(
+        Startup,
+        (
+            spawn_camera,
+            setup,
+            synthesise_sounds,
+            open_editor_from_config,
+        ),
+    )
+    .add_systems(OnEnter(GameState::Editor), start_editor)
+    .add_systems(OnExit(GameState::Editor), despawn_with::<EditorEntity>)
+    .add_systems(OnEnter(GameState::Playing), play_intro)
+    .add_systems(
+        Update,
+        (
+            (play_chomp, play_death, update_background_loop).run_if(in_state(GameState::Playing)),
+            open_editor_on_input
+                .after(start_game_on_input)
+                .run_if(in_state(GameState::Attract)),
+            (
+                editor_keys,
+                paint_maze,
+                refresh_editor_maze,
+                update_editor_text,
+                draw_editor_overlay,
+            )
+                .chain()
+                .run_if(in_state(GameState::Editor)),
+        ),
+    )
+    .add_systems(Update, render_text.after(update_score_text))
+    .add_systems(Update, (toggle_camera_layout, fit_camera).chain())
+    .add_systems(
+        Update,
+        (
+            reload_sprite_manifest,
+            apply_sprite_manifest.run_if(resource_changed::<SpriteSheets>()),
+        )
+            .chain(),
+    )
+    .add_systems(
+        Update,
+        (
+            toggle_debug_overlay,
+            draw_debug_overlay.run_if(in_game),
+            hide_debug_labels.run_if(not(in_game)),
+            // Labels must exist before being rendered in the same frame.
+            apply_deferred,
+        )
+            .chain()
+            .before(render_text),
+    );
+}
+
+/// Runs the game without drawing it, for bots to play as fast as they can,
+/// every frame playing a tick.
+fn add_headless_frontend(app: &mut App) {
+    add_minimal_frontend(app, Duration::ZERO);
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+        FRAME_SECS,
+    )));
+}
+
+/// Runs the game a frame at a time, waiting at least [wait] between frames,
+/// without a window.
+fn add_minimal_frontend(app: &mut App, wait: Duration) {
+    app.add_plugins((
+        MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(wait)),
+        InputPlugin,
+    ))
+    .init_resource::<SpriteSheets>()
+    .add_event::<ReceivedCharacter>();
+}
+
+/// Draws the game in the terminal it was started from, see [terminal].
+fn add_terminal_frontend(app: &mut App) {
+    add_minimal_frontend(app, Duration::from_secs_f32(FRAME_SECS));
+    app.init_resource::<TerminalScreen>()
+        .add_systems(Startup, enter_terminal)
+        .add_systems(
+            PreUpdate,
+            read_terminal_input.after(InputSystem).before(console_input),
+        )
+        .add_systems(Last, draw_terminal);
+}
+
+/// Plays against another machine, see [net].
+fn add_network(app: &mut App, link: NetLink) {
+    app.insert_resource(link)
+        .add_systems(Startup, (net::settings_digest, net::spawn_net_status))
+        .add_systems(
+            Update,
+            // Once the game is over the link is gone, local play taking over.
+            net::connect.run_if(
+                resource_exists::<NetLink>().and_then(not(resource_exists::<net::Lockstep>())),
+            ),
+        );
+
+    // The game is played by [net::run_simulation] in place of the usual
+    // schedules, a tick at a time.
+    app.add_schedule(net::Simulation, Schedule::new())
+        .add_systems(net::Simulation, net::run_simulation);
+    let mut order = app.world.resource_mut::<MainScheduleOrder>();
+    order.insert_after(PreUpdate, net::Simulation);
+    let played_by_simulation: [Box<dyn ScheduleLabel>; 3] = [
+        Box::new(StateTransition),
+        Box::new(RunFixedUpdateLoop),
+        Box::new(Update),
+    ];
+    order
+        .labels
+        .retain(|label| !played_by_simulation.contains(label));
+
+    // Systems running in the same order on both machines keep their games
+    // the same.
+    single_threaded(app, Update);
+    single_threaded(app, FixedUpdate);
+    single_threaded(app, StateTransition);
+    for state in GameState::variants() {
+        single_threaded(app, OnEnter(state));
+        single_threaded(app, OnExit(state));
+    }
+}
+
+fn single_threaded(app: &mut App, label: impl ScheduleLabel) {
+    app.edit_schedule(label, |schedule| {
+        schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+    });
 }
 
 fn setup(
@@ -53,56 +500,225 @@ fn setup(
     asset_server: Res<AssetServer>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
 ) {
-    let mut camera = Camera2dBundle::default();
-    camera.projection.scale = 0.5;
-    camera.transform.translation = Vec3::new(70.0, -150.0, camera.transform.translation.z);
-    commands.spawn(camera);
+    // Drawn with the built-in manifest until the file is loaded.
+    let manifest = SpriteManifest::default();
+    commands.insert_resource(SpriteManifestHandle(
+        asset_server.load(SPRITE_MANIFEST_PATH),
+    ));
+    commands.insert_resource(SpriteSheets {
+        characters: texture_atlases.add(manifest.characters.atlas(&asset_server)),
+        maze: texture_atlases.add(manifest.maze.atlas(&asset_server)),
+        font: texture_atlases.add(manifest.font.atlas(&asset_server)),
+        manifest,
+    });
+}
+
+fn reset_level(mut commands: Commands, mut level: ResMut<Level>, config: Res<GameConfig>) {
+    *level = Level(config.level);
+    commands.remove_resource::<LevelClear>();
+}
+
+fn spawn_level(mut commands: Commands, loader: LevelLoader, level: Res<Level>) {
+    loader.load(&mut commands, level.0);
+}
 
-    spawn_characters(&mut commands, &asset_server, &mut texture_atlases);
-    spawn_map(&mut commands, &asset_server, &mut texture_atlases);
+/// What it takes to load a level, see [load_level].
+#[derive(SystemParam)]
+pub struct LevelLoader<'w> {
+    sprite_sheets: Res<'w, SpriteSheets>,
+    config: Res<'w, GameConfig>,
+    maze_set: Res<'w, MazeSet>,
+    play_test: Option<Res<'w, PlayTest>>,
+    players: Res<'w, Players>,
+}
+
+impl LevelLoader<'_> {
+    /// Loads [level] in the maze it is played in, the one being edited when
+    /// play-testing.
+    pub fn load(&self, commands: &mut Commands, level: u32) {
+        let maze = match self.play_test.as_deref() {
+            Some(PlayTest(map)) => Maze {
+                map: map.clone(),
+                palette: MazePalette::CLASSIC,
+            },
+            None => self.maze_set.maze_for_level(level),
+        };
+        load_level(
+            commands,
+            &self.sprite_sheets,
+            &self.config,
+            self.players.party_mode(),
+            maze,
+        );
+    }
+}
+
+fn load_level(
+    commands: &mut Commands,
+    sprite_sheets: &SpriteSheets,
+    config: &GameConfig,
+    party: PartyMode,
+    maze: Maze,
+) {
+    commands.insert_resource(FrightenedTimer::default());
+    let map = maze.map.clone();
+    commands.add(move |world: &mut World| use_nav_graph(world, &map));
+    commands.insert_resource(maze.palette);
+    spawn_characters(commands, sprite_sheets, config, party, &maze.map);
+    spawn_map(commands, sprite_sheets, &maze.map);
+    commands.insert_resource(maze.map);
+}
+
+/// Starts flashing the maze once Pac-Man has cleared every pellet.
+fn clear_level(
+    mut commands: Commands,
+    palette: Res<MazePalette>,
+    clear: Option<Res<LevelClear>>,
+    pellet_query: Query<(), With<Pellet>>,
+) {
+    if clear.is_none() && pellet_query.is_empty() {
+        commands.insert_resource(LevelClear::new(*palette));
+    }
+}
+
+/// Whether the maze is done flashing, once the level got cleared.
+fn level_flashed(clear: Option<Res<LevelClear>>) -> bool {
+    clear.is_some_and(|clear| clear.timer.finished())
+}
+
+/// Moves on to the next level, and maybe the next maze, once the maze is
+/// done flashing.
+fn next_level(
+    mut commands: Commands,
+    mut level: ResMut<Level>,
+    loader: LevelLoader,
+    level_query: Query<Entity, With<LevelEntity>>,
+) {
+    commands.remove_resource::<LevelClear>();
+
+    for entity in level_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    level.0 += 1;
+    loader.load(&mut commands, level.0);
+}
+
+/// Takes a life after Pac-Man got caught, then puts the characters back at
+/// their starting tiles, hands over to the other player or ends the game.
+#[allow(clippy::type_complexity)]
+fn handle_death(
+    mut commands: Commands,
+    mut died_events: EventReader<PacmanDied>,
+    mut players: ResMut<Players>,
+    sprite_sheets: Res<SpriteSheets>,
+    config: Res<GameConfig>,
+    map: Res<Map>,
+    character_query: Query<Entity, Or<(With<Player>, With<Ghost>)>>,
+) {
+    if died_events.iter().count() == 0 {
+        return;
+    }
+
+    for entity in character_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.insert_resource(FrightenedTimer::default());
+    match players.lose_life() {
+        NextTurn::Again => spawn_characters(
+            &mut commands,
+            &sprite_sheets,
+            &config,
+            players.party_mode(),
+            &map,
+        ),
+        NextTurn::Switch(player) => commands.insert_resource(SwitchPlayer(player)),
+        NextTurn::GameOver => commands.insert_resource(GameOver::default()),
+    }
+}
+
+/// Brings back the level of the player taking over, see [save_turn].
+fn start_turn(
+    mut commands: Commands,
+    players: Res<Players>,
+    mut score: ResMut<Score>,
+    mut level: ResMut<Level>,
+    loader: LevelLoader,
+) {
+    let turn = players
+        .turn(players.current())
+        .expect("the player at the controls has a turn");
+    score.current = turn.score;
+    level.0 = turn.level;
+    loader.load(&mut commands, level.0);
 }
 
 fn spawn_characters(
     commands: &mut Commands,
-    asset_server: &Res<AssetServer>,
-    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
+    sprite_sheets: &SpriteSheets,
+    config: &GameConfig,
+    party: PartyMode,
+    map: &Map,
 ) {
-    let sprite_handle = asset_server.load("sprites/pacman_character_sheet_2.png");
-    let atlas = TextureAtlas::from_grid(sprite_handle, Vec2::new(16.0, 16.0), 15, 15, None, None);
-    let texture_atlas_handle = texture_atlases.add(atlas);
+    let texture_atlas_handle = &sprite_sheets.characters;
 
     // Pacman
-    let pacman_animation_indices = AnimationIndices::new(
-        vec![24, 1, 0, 1],
-        vec![24, 16, 15, 16],
-        vec![24, 31, 30, 31],
-        vec![24, 46, 45, 46],
-    );
-    let pacman_start_tile = TilePos { x: 13, y: 17 };
-    commands.spawn((
-        Position(pacman_start_tile.clone()),
-        Movable::new(pacman_start_tile, 0.0, 11.5, Direction::Up),
-        SpriteSheetBundle {
-            texture_atlas: texture_atlas_handle.clone(),
-            sprite: TextureAtlasSprite::new(
-                pacman_animation_indices.sprite_indices_right
-                    [pacman_animation_indices.current_index],
-            ),
-            ..default()
-        },
-        pacman_animation_indices,
-        Player,
-        AnimationTimer(Timer::from_seconds(1.0 / 16.0, TimerMode::Repeating)),
-    ));
+    let pacman_start_tile = config
+        .pacman_start
+        .clone()
+        .unwrap_or_else(|| map.pacman_start.clone());
+    match party {
+        PartyMode::Arcade => {
+            commands.spawn((
+                pacman(sprite_sheets, config, &pacman_start_tile, Color::WHITE),
+                Controls::Any,
+            ));
+        }
+        PartyMode::Coop => {
+            commands.spawn((
+                pacman(sprite_sheets, config, &pacman_start_tile, Color::WHITE),
+                PARTY_CONTROLS[0],
+            ));
+
+            // The second Pac-Man starts next to the first one when there is
+            // room for him.
+            let next_tile = TilePos {
+                x: pacman_start_tile.x + 1,
+                y: pacman_start_tile.y,
+            };
+            let second_start_tile = match map.get_at(&next_tile) {
+                MapType::Open(_) => next_tile,
+                _ => pacman_start_tile.clone(),
+            };
+            commands.spawn((
+                pacman(
+                    sprite_sheets,
+                    config,
+                    &second_start_tile,
+                    SECOND_PACMAN_TINT,
+                ),
+                PARTY_CONTROLS[1],
+                PlayerIndex(1),
+            ));
+        }
+        PartyMode::Versus => {
+            commands.spawn((
+                pacman(sprite_sheets, config, &pacman_start_tile, Color::WHITE),
+                PARTY_CONTROLS[0],
+            ));
+        }
+    }
 
     // Blinky
-    let blinky_animation_indices =
-        AnimationIndices::new(vec![60, 61], vec![62, 63], vec![64, 65], vec![66, 67]);
-    let blinky_start_tile = TilePos { x: 13, y: 11 };
-    commands.spawn((
+    let blinky_animation_indices = sprite_sheets.manifest.blinky.indices();
+    let blinky_start_tile = config
+        .ghost_start
+        .clone()
+        .unwrap_or_else(|| map.ghost_start.clone());
+    let mut blinky = commands.spawn((
         Position(blinky_start_tile.clone()),
+        PixelPosition::from_tile(&blinky_start_tile),
         Movable {
-            base_speed: 7.0,
+            base_speed: config.ghost_speed,
             direction: Direction::Up,
             target_tile: blinky_start_tile,
             progress: 0.0,
@@ -116,51 +732,93 @@ fn spawn_characters(
             ..default()
         },
         Ghost,
+        GhostMode::default(),
+        GhostTarget::default(),
+        MoverKind::Ghost,
+        NextDirection::default(),
+        GhostColor(Color::RED),
         Blinky,
+        NormalAnimation(blinky_animation_indices.clone()),
         blinky_animation_indices,
-        AnimationTimer(Timer::from_seconds(1.0 / 8.0, TimerMode::Repeating)),
+        AnimationTimer(Timer::from_seconds(
+            sprite_sheets.manifest.ghost_frame_secs,
+            TimerMode::Repeating,
+        )),
+        LevelEntity,
     ));
+    if party == PartyMode::Versus {
+        blinky.insert((
+            PARTY_CONTROLS[1],
+            PlayerIndex(1),
+            RequestedDirection::default(),
+        ));
+    }
 }
 
-fn spawn_map(
-    commands: &mut Commands,
-    asset_server: &Res<AssetServer>,
-    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
-) {
-    let maze_sprite_handle = asset_server.load("sprites/pacman_maze.png");
-    let maze_atlas = TextureAtlas::from_grid(
-        maze_sprite_handle,
-        Vec2::new(8.0, 8.0),
-        16,
-        14,
-        Some(Vec2::new(1.0, 1.0)),
-        Some(Vec2::new(225.0, 27.0)),
-    );
-    let texture_maze_atlas_handle = texture_atlases.add(maze_atlas);
+/// Pac-Man at [start], tinted with [color], for whoever gets the [Controls].
+fn pacman(
+    sprite_sheets: &SpriteSheets,
+    config: &GameConfig,
+    start: &TilePos,
+    color: Color,
+) -> impl Bundle {
+    let pacman_animation_indices = sprite_sheets.manifest.pacman.indices();
+    (
+        Position(start.clone()),
+        PixelPosition::from_tile(start),
+        Movable::new(start.clone(), 0.0, config.pacman_speed, Direction::Up),
+        SpriteSheetBundle {
+            texture_atlas: sprite_sheets.characters.clone(),
+            sprite: TextureAtlasSprite {
+                color,
+                ..TextureAtlasSprite::new(
+                    pacman_animation_indices.sprite_indices_right
+                        [pacman_animation_indices.current_index],
+                )
+            },
+            ..default()
+        },
+        pacman_animation_indices,
+        Player,
+        MoverKind::Pacman,
+        NextDirection::default(),
+        EatingPause::default(),
+        AnimationTimer(Timer::from_seconds(
+            sprite_sheets.manifest.pacman_frame_secs,
+            TimerMode::Repeating,
+        )),
+        LevelEntity,
+    )
+}
+
+fn spawn_map(commands: &mut Commands, sprite_sheets: &SpriteSheets, map: &Map) {
+    let texture_maze_atlas_handle = &sprite_sheets.maze;
 
     // Spawn maze
-    MAP.iter().enumerate().for_each(|(row_num, row)| {
+    map.rows().enumerate().for_each(|(row_num, row)| {
         row.iter().enumerate().for_each(|(col_num, tile)| {
-            let sprite_index = if let MapType::Wall(wall_type) = tile {
-                sprite_index_for_wall_type(wall_type)
-            } else {
-                44
-            };
+            let sprite = sprite_sheets.manifest.tile_sprite(tile);
 
             let tile_pos = TilePos {
                 x: col_num as i32,
                 y: row_num as i32,
             };
             let pos = tile_pos.to_display_pos();
-            commands.spawn((
+            let mut tile_entity = commands.spawn((
                 Position(tile_pos),
+                tile.clone(),
                 SpriteSheetBundle {
                     texture_atlas: texture_maze_atlas_handle.clone(),
-                    sprite: TextureAtlasSprite::new(sprite_index),
+                    sprite,
                     transform: Transform::from_translation(Vec3::new(pos.x, -pos.y, -1.0)),
                     ..default()
                 },
+                LevelEntity,
             ));
+
+            if let Some(pellet) = Pellet::for_tile(tile) {
+                tile_entity.insert(pellet);
+            }
         })
     })
 }
@@ -1,144 +1,12 @@
-use bevy::prelude::*;
-
 use crate::common::Direction;
 
-#[derive(Component, Clone, Debug, PartialEq)]
-pub enum MapType {
-    Wall(WallType),
-    Open(OpenContent),
-    GhostOnlyBarrier,
-}
-
-#[derive(PartialEq, Clone, Debug)]
-pub enum OpenContent {
-    None,
-    Food,
-    GhostEater,
-}
-
-#[derive(PartialEq, Clone, Debug)]
-pub enum WallType {
-    Straight(Direction),
-    DoubleStraight(Direction),
-    DoubleCorner(Corner),
-    VerticalLineInnerCorner(Corner),
-    OuterCorner(Corner),
-    InnerCorner(Corner),
-    NestCorner(Corner),
-    Inner,
-    HorizontalLineInnerCornerTopRight,
-    HorizontalLineInnerCornerTopLeft,
-}
-
-#[derive(PartialEq, Clone, Debug)]
-pub enum Corner {
-    TopRight,
-    TopLeft,
-    BottomRight,
-    BottomLeft,
-}
-
-const MAP_WIDTH: usize = 28;
-const MAP_HEIGHT: usize = 31;
-
-#[derive(Deref, DerefMut)]
-pub struct Map([[MapType; MAP_WIDTH]; MAP_HEIGHT]);
-
-impl Map {
-    const FORBIDDEN_TILES: [TilePos; 11] = [
-        TilePos { x: 11, y: 13 },
-        TilePos { x: 11, y: 14 },
-        TilePos { x: 11, y: 15 },
-        TilePos { x: 12, y: 15 },
-        TilePos { x: 13, y: 15 },
-        TilePos { x: 14, y: 15 },
-        TilePos { x: 15, y: 15 },
-        TilePos { x: 16, y: 15 },
-        TilePos { x: 16, y: 14 },
-        TilePos { x: 16, y: 13 },
-        TilePos { x: 15, y: 13 },
-    ];
-
-    /// Returns wether the tile at [pos] is a wall or not.
-    /// If the position is outside of map tiles, returns true
-    pub fn is_wall(&self, tile_pos: &TilePos) -> bool {
-        let x = tile_pos.x as usize;
-        let y = tile_pos.y as usize;
-        if x >= MAP_WIDTH || y >= MAP_HEIGHT {
-            return true;
-        }
-
-        if let MapType::Wall(_) = self.0[y][x] {
-            true
-        } else {
-            false
-        }
-    }
-
-    pub fn get_at(&self, tile_pos: &TilePos) -> MapType {
-        if tile_pos.x < 0 || tile_pos.y < 0 {
-            panic!("Tile ({}, {}) out of bounds", tile_pos.x, tile_pos.y);
-        }
-
-        let x = tile_pos.x as usize;
-        let y = tile_pos.y as usize;
-
-        if x >= MAP_WIDTH || y >= MAP_HEIGHT {
-            panic!("Tile ({}, {}) out of bounds", tile_pos.x, tile_pos.y);
-        }
-
-        self[y][x].clone()
-    }
-
-    pub fn get_empty_neighbours(&self, pos: &TilePos) -> Vec<(TilePos, Direction)> {
-        let directions = vec![
-            Direction::Up,
-            Direction::Down,
-            Direction::Right,
-            Direction::Left,
-        ];
-
-        directions
-            .into_iter()
-            .map(|dir| (pos.translate(&dir), dir))
-            .filter(|(p, _)| !MAP.is_wall(p))
-            .filter(|(p, _)| !Map::FORBIDDEN_TILES.contains(p))
-            .collect()
-    }
-}
-
-const TILE_SIZE: i32 = 8;
-
-#[derive(Clone, Debug, PartialEq)]
-pub struct TilePos {
-    pub x: i32,
-    pub y: i32,
-}
-
-impl TilePos {
-    pub fn to_display_pos(&self) -> Vec2 {
-        Vec2::new((self.x * TILE_SIZE) as f32, (self.y * TILE_SIZE) as f32)
-    }
-
-    pub fn translate(&self, dir: &Direction) -> Self {
-        let (translate_x, translate_y) = match dir {
-            Direction::Up => (0, -1),
-            Direction::Right => (1, 0),
-            Direction::Down => (0, 1),
-            Direction::Left => (-1, 0),
-        };
-
-        let new_x = (self.x as i32 + translate_x).max(0).min(MAP_WIDTH as i32);
-        let new_y = (self.y as i32 + translate_y).max(0).min(MAP_HEIGHT as i32);
-        Self { x: new_x, y: new_y }
-    }
+use super::{Corner, MapType, OpenContent, WallType};
 
-    pub fn dist_to(&self, other: &TilePos) -> f32 {
-        ((self.x - other.x).abs() + (self.y - other.y).abs()) as f32 / 2.0
-    }
-}
+pub const CLASSIC_WIDTH: usize = 28;
+pub const CLASSIC_HEIGHT: usize = 31;
 
-pub const MAP: Map = Map([
+/// The arcade Pac-Man maze.
+pub const CLASSIC_TILES: [[MapType; CLASSIC_WIDTH]; CLASSIC_HEIGHT] = [
     // 0 (Upper wall)
     [
         MapType::Wall(WallType::DoubleCorner(Corner::TopLeft)),
@@ -1100,4 +968,4 @@ pub const MAP: Map = Map([
         MapType::Wall(WallType::DoubleStraight(Direction::Down)),
         MapType::Wall(WallType::DoubleCorner(Corner::BottomRight)),
     ],
-]);
+];
@@ -0,0 +1,490 @@
+//! Random mazes in the style of the arcade ones: left-right symmetric, one
+//! tile wide corridors between walls at least two tiles thick, no dead ends,
+//! the ghost house in the middle and tunnels off the sides.
+//!
+//! Corridors are laid along a lattice of rows and columns three to five tiles
+//! apart, which keeps every wall drawable. All the lattice segments start
+//! open and random ones are walled off, as long as that leaves no dead end
+//! and every corridor connected.
+
+use std::collections::HashSet;
+
+use super::layout::{
+    EMPTY, FOOD, GHOST_EATER, GHOST_ONLY_BARRIER, GHOST_START, PACMAN_START, WALL,
+};
+use super::validate::{validate, Problem};
+
+pub const MIN_WIDTH: usize = 20;
+pub const MIN_HEIGHT: usize = 17;
+/// Past this size, finding the way between every two tiles of the maze, as
+/// [crate::nav::NavGraph] does, takes too long to load a level.
+pub const MAX_WIDTH: usize = 64;
+pub const MAX_HEIGHT: usize = 64;
+
+const GHOST_HOUSE_WIDTH: i32 = 8;
+const GHOST_HOUSE_HEIGHT: i32 = 5;
+/// How many tiles apart neighbouring corridors can be.
+const MIN_GAP: i32 = 3;
+const MAX_GAP: i32 = 5;
+/// The chance of walling off each lattice segment that can be.
+const WALL_OFF_CHANCE: f32 = 0.6;
+/// Retries before giving up, should generating ever go wrong.
+const MAX_ATTEMPTS: usize = 100;
+
+/// What to generate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MazeParams {
+    /// Rounded up to an even number, the maze being mirrored in its middle,
+    /// and kept between [MIN_WIDTH] and [MAX_WIDTH].
+    pub width: usize,
+    /// Between [MIN_HEIGHT] and [MAX_HEIGHT].
+    pub height: usize,
+    /// The same seed always gives the same maze.
+    pub seed: u64,
+}
+
+impl Default for MazeParams {
+    /// The size of the arcade mazes.
+    fn default() -> Self {
+        Self {
+            width: 28,
+            height: 31,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Edge {
+    /// Along row [1], from column [0] to the next one.
+    Horizontal(usize, usize),
+    /// Along column [0], from row [1] to the next one.
+    Vertical(usize, usize),
+}
+
+struct Lattice {
+    width: i32,
+    height: i32,
+    /// Corridor columns, symmetric around the middle of the maze.
+    xs: Vec<i32>,
+    ys: Vec<i32>,
+    /// Top left corner of the ghost house.
+    house: (i32, i32),
+    open: HashSet<Edge>,
+    tunnel_rows: Vec<usize>,
+}
+
+/// Splits [length] into gaps between [MIN_GAP] and [MAX_GAP] tiles, returning
+/// the positions between them after [start].
+fn split(rng: &mut fastrand::Rng, start: i32, length: i32) -> Vec<i32> {
+    let mut positions = Vec::new();
+    let mut position = start;
+    let mut remaining = length;
+    while remaining > MAX_GAP {
+        // Never leave less than a whole gap behind.
+        let max = (remaining - MIN_GAP).min(MAX_GAP);
+        let gap = rng.i32(MIN_GAP..=max);
+        position += gap;
+        remaining -= gap;
+        positions.push(position);
+    }
+    positions.push(start + length);
+    positions
+}
+
+impl Lattice {
+    fn new(rng: &mut fastrand::Rng, width: i32, height: i32) -> Self {
+        let house = (
+            width / 2 - GHOST_HOUSE_WIDTH / 2,
+            (height - GHOST_HOUSE_HEIGHT) / 2 - 1,
+        );
+        let (ring_left, ring_top) = (house.0 - 1, house.1 - 1);
+        let ring_bottom = house.1 + GHOST_HOUSE_HEIGHT;
+
+        // Columns up to the one left of the ghost house, maybe one more above
+        // and below it, then the same mirrored.
+        let mut left = vec![1];
+        left.extend(split(rng, 1, ring_left - 1));
+        if rng.bool() {
+            left.push(width / 2 - 2);
+        }
+        let mut xs = left.clone();
+        xs.extend(left.iter().rev().map(|x| width - 1 - x));
+
+        // Rows down to the one above the ghost house, maybe one halfway down
+        // its sides, then down to the bottom of the maze.
+        let mut ys = vec![1];
+        ys.extend(split(rng, 1, ring_top - 1));
+        if rng.bool() {
+            ys.push(ring_top + 3);
+        }
+        ys.push(ring_bottom);
+        ys.extend(split(rng, ring_bottom, height - 2 - ring_bottom));
+
+        let mut lattice = Self {
+            width,
+            height,
+            xs,
+            ys,
+            house,
+            open: HashSet::new(),
+            tunnel_rows: Vec::new(),
+        };
+        lattice.open = lattice
+            .edges()
+            .filter(|edge| !lattice.crosses_house(edge))
+            .collect();
+
+        // One or two tunnels, away from the ghost house.
+        let candidates = (0..lattice.ys.len())
+            .filter(|j| !(ring_top..=ring_bottom).contains(&lattice.ys[*j]))
+            .collect::<Vec<usize>>();
+        for _ in 0..rng.usize(1..=2) {
+            let row = candidates[rng.usize(..candidates.len())];
+            if !lattice.tunnel_rows.contains(&row) {
+                lattice.tunnel_rows.push(row);
+            }
+        }
+        lattice
+    }
+
+    fn edges(&self) -> impl Iterator<Item = Edge> + '_ {
+        let horizontal = (0..self.xs.len() - 1)
+            .flat_map(|i| (0..self.ys.len()).map(move |j| Edge::Horizontal(i, j)));
+        let vertical = (0..self.xs.len())
+            .flat_map(|i| (0..self.ys.len() - 1).map(move |j| Edge::Vertical(i, j)));
+        horizontal.chain(vertical)
+    }
+
+    fn mirror(&self, edge: &Edge) -> Edge {
+        let columns = self.xs.len();
+        match *edge {
+            Edge::Horizontal(i, j) => Edge::Horizontal(columns - 2 - i, j),
+            Edge::Vertical(i, j) => Edge::Vertical(columns - 1 - i, j),
+        }
+    }
+
+    fn ends(&self, edge: &Edge) -> [(usize, usize); 2] {
+        match *edge {
+            Edge::Horizontal(i, j) => [(i, j), (i + 1, j)],
+            Edge::Vertical(i, j) => [(i, j), (i, j + 1)],
+        }
+    }
+
+    fn tiles(&self, edge: &Edge) -> Vec<(i32, i32)> {
+        let [(i0, j0), (i1, j1)] = self.ends(edge);
+        let (x0, y0, x1, y1) = (self.xs[i0], self.ys[j0], self.xs[i1], self.ys[j1]);
+        (x0..=x1)
+            .flat_map(|x| (y0..=y1).map(move |y| (x, y)))
+            .collect()
+    }
+
+    fn in_house(&self, (x, y): (i32, i32)) -> bool {
+        let (house_x, house_y) = self.house;
+        (house_x..house_x + GHOST_HOUSE_WIDTH).contains(&x)
+            && (house_y..house_y + GHOST_HOUSE_HEIGHT).contains(&y)
+    }
+
+    /// The corridor running around the ghost house.
+    fn on_ring(&self, (x, y): (i32, i32)) -> bool {
+        let (left, top) = (self.house.0 - 1, self.house.1 - 1);
+        let (right, bottom) = (
+            self.house.0 + GHOST_HOUSE_WIDTH,
+            self.house.1 + GHOST_HOUSE_HEIGHT,
+        );
+        let across = (left..=right).contains(&x) && (y == top || y == bottom);
+        let down = (top..=bottom).contains(&y) && (x == left || x == right);
+        across || down
+    }
+
+    fn crosses_house(&self, edge: &Edge) -> bool {
+        self.tiles(edge).into_iter().any(|tile| self.in_house(tile))
+    }
+
+    fn degree(&self, open: &HashSet<Edge>, (i, j): (usize, usize)) -> usize {
+        let at_edge = i == 0 || i == self.xs.len() - 1;
+        let tunnel = usize::from(at_edge && self.tunnel_rows.contains(&j));
+        let mut edges = Vec::new();
+        if i > 0 {
+            edges.push(Edge::Horizontal(i - 1, j));
+        }
+        if j > 0 {
+            edges.push(Edge::Vertical(i, j - 1));
+        }
+        edges.push(Edge::Horizontal(i, j));
+        edges.push(Edge::Vertical(i, j));
+        tunnel + edges.iter().filter(|edge| open.contains(edge)).count()
+    }
+
+    /// Whether every corridor can be reached from every other, tunnels
+    /// included.
+    fn is_connected(&self, open: &HashSet<Edge>) -> bool {
+        let nodes = open
+            .iter()
+            .flat_map(|edge| self.ends(edge))
+            .collect::<HashSet<(usize, usize)>>();
+        let Some(start) = nodes.iter().next() else {
+            return false;
+        };
+
+        let last_column = self.xs.len() - 1;
+        let mut reached = HashSet::from([*start]);
+        let mut stack = vec![*start];
+        while let Some((i, j)) = stack.pop() {
+            let mut next = open
+                .iter()
+                .filter_map(|edge| match self.ends(edge) {
+                    [a, b] if a == (i, j) => Some(b),
+                    [a, b] if b == (i, j) => Some(a),
+                    _ => None,
+                })
+                .collect::<Vec<(usize, usize)>>();
+            if self.tunnel_rows.contains(&j) && (i == 0 || i == last_column) {
+                next.push((last_column - i, j));
+            }
+            for node in next {
+                if reached.insert(node) {
+                    stack.push(node);
+                }
+            }
+        }
+        reached.len() == nodes.len()
+    }
+
+    /// Walls off random segments, keeping the maze symmetric.
+    fn carve(&mut self, rng: &mut fastrand::Rng) {
+        let mut candidates = self
+            .open
+            .iter()
+            .filter(|edge| {
+                let tiles = self.tiles(edge);
+                !tiles.iter().all(|tile| self.on_ring(*tile))
+                    && tiles[0].0 <= self.width - 1 - tiles[tiles.len() - 1].0
+            })
+            .copied()
+            .collect::<Vec<Edge>>();
+        // Sorting first, as iterating over a HashSet isn't reproducible.
+        candidates.sort_by_key(|edge| match *edge {
+            Edge::Horizontal(i, j) => (0, i, j),
+            Edge::Vertical(i, j) => (1, i, j),
+        });
+        rng.shuffle(&mut candidates);
+
+        for edge in candidates {
+            if rng.f32() >= WALL_OFF_CHANCE {
+                continue;
+            }
+
+            let mut open = self.open.clone();
+            open.remove(&edge);
+            open.remove(&self.mirror(&edge));
+            let affected = self
+                .ends(&edge)
+                .into_iter()
+                .chain(self.ends(&self.mirror(&edge)));
+            let dead_end = affected
+                .into_iter()
+                .any(|node| self.degree(&open, node) == 1);
+            if !dead_end && self.is_connected(&open) {
+                self.open = open;
+            }
+        }
+    }
+
+    fn render(&self) -> Vec<Vec<char>> {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let mut rows = vec![vec![WALL; width]; height];
+        let mut set = |(x, y): (i32, i32), tile: char| rows[y as usize][x as usize] = tile;
+
+        for edge in self.open.iter() {
+            for tile in self.tiles(edge) {
+                set(tile, if self.on_ring(tile) { EMPTY } else { FOOD });
+            }
+        }
+        for j in self.tunnel_rows.iter() {
+            set((0, self.ys[*j]), EMPTY);
+            set((self.width - 1, self.ys[*j]), EMPTY);
+        }
+
+        let (house_x, house_y) = self.house;
+        for y in 0..GHOST_HOUSE_HEIGHT {
+            for x in 0..GHOST_HOUSE_WIDTH {
+                let border = x == 0 || y == 0 || x == GHOST_HOUSE_WIDTH - 1;
+                let bottom = y == GHOST_HOUSE_HEIGHT - 1;
+                let door = y == 0 && (x == 3 || x == 4);
+                let tile = match () {
+                    _ if door => GHOST_ONLY_BARRIER,
+                    _ if border || bottom => WALL,
+                    _ => EMPTY,
+                };
+                set((house_x + x, house_y + y), tile);
+            }
+        }
+        set((self.width / 2 - 1, house_y - 1), GHOST_START);
+        set(
+            (self.width / 2 - 1, house_y + GHOST_HOUSE_HEIGHT),
+            PACMAN_START,
+        );
+        rows
+    }
+}
+
+/// Puts power pellets on the corridors closest to the four corners.
+fn place_power_pellets(rows: &mut [Vec<char>]) {
+    let (width, height) = (rows[0].len() as i32, rows.len() as i32);
+    for corner_y in [height / 10, height - height / 4] {
+        let closest = (0..height)
+            .flat_map(|y| (0..width / 2).map(move |x| (x, y)))
+            .filter(|(x, y)| rows[*y as usize][*x as usize] == FOOD)
+            .min_by_key(|(x, y)| x * x + (y - corner_y) * (y - corner_y));
+        if let Some((x, y)) = closest {
+            rows[y as usize][x as usize] = GHOST_EATER;
+            rows[y as usize][(width - 1 - x) as usize] = GHOST_EATER;
+        }
+    }
+}
+
+/// A random maze drawn as text, see [super::Map::from_layout], or the
+/// problems of the last one tried should none of them come out valid.
+pub fn generate(params: &MazeParams) -> Result<Vec<String>, Vec<Problem>> {
+    let width = (params.width.clamp(MIN_WIDTH, MAX_WIDTH) as i32 + 1) / 2 * 2;
+    let height = params.height.clamp(MIN_HEIGHT, MAX_HEIGHT) as i32;
+    let mut rng = fastrand::Rng::with_seed(params.seed);
+
+    let mut problems = Vec::new();
+    for _ in 0..MAX_ATTEMPTS {
+        let mut lattice = Lattice::new(&mut rng, width, height);
+        lattice.carve(&mut rng);
+        let mut rows = lattice.render();
+        place_power_pellets(&mut rows);
+
+        let layout = rows
+            .iter()
+            .map(|row| row.iter().collect())
+            .collect::<Vec<String>>();
+        problems = validate(&layout.iter().map(String::as_str).collect::<Vec<&str>>());
+        if problems.is_empty() {
+            return Ok(layout);
+        }
+    }
+    Err(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ai::{blinky::ghost_moves, GhostMode},
+        common::Direction,
+        components::{Movable, Position},
+        map::Map,
+        nav::NavGraph,
+    };
+
+    /// The sizes played in, from the smallest to a large one, with a few
+    /// seeds each.
+    fn params() -> impl Iterator<Item = MazeParams> {
+        [(20, 17), (28, 31), (30, 25), (40, 35)]
+            .into_iter()
+            .flat_map(|(width, height)| {
+                (0..4).map(move |seed| MazeParams {
+                    width,
+                    height,
+                    seed,
+                })
+            })
+    }
+
+    fn map(params: &MazeParams) -> Map {
+        let layout = generate(params).unwrap();
+        Map::from_layout(&layout.iter().map(String::as_str).collect::<Vec<&str>>()).unwrap()
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_maze() {
+        let params = MazeParams::default();
+        assert_eq!(generate(&params).unwrap(), generate(&params).unwrap());
+        assert_ne!(
+            generate(&params).unwrap(),
+            generate(&MazeParams { seed: 1, ..params }).unwrap()
+        );
+    }
+
+    #[test]
+    fn generates_the_size_asked_for() {
+        let layout = generate(&MazeParams {
+            width: 31,
+            height: 25,
+            seed: 0,
+        })
+        .unwrap();
+        assert_eq!(layout.len(), 25);
+        assert!(layout.iter().all(|row| row.chars().count() == 32));
+
+        let layout = generate(&MazeParams {
+            width: 1,
+            height: 1,
+            seed: 0,
+        })
+        .unwrap();
+        assert_eq!(layout.len(), MIN_HEIGHT);
+        assert!(layout.iter().all(|row| row.chars().count() == MIN_WIDTH));
+
+        let layout = generate(&MazeParams {
+            width: 1000,
+            height: 1000,
+            seed: 0,
+        })
+        .unwrap();
+        assert_eq!(layout.len(), MAX_HEIGHT);
+        assert!(layout.iter().all(|row| row.chars().count() == MAX_WIDTH));
+    }
+
+    #[test]
+    fn generated_mazes_are_valid() {
+        for params in params() {
+            let layout = generate(&params).unwrap();
+            let layout = layout.iter().map(String::as_str).collect::<Vec<&str>>();
+            assert_eq!(validate(&layout), vec![], "{params:?}");
+        }
+    }
+
+    #[test]
+    fn ghosts_always_have_somewhere_to_go() {
+        for params in params() {
+            let map = map(&params);
+            let nav = NavGraph::new(&map);
+
+            let start = Movable::new(map.ghost_start.clone(), 0.0, 0.0, Direction::Up);
+            let mut steps = vec![(Position(map.ghost_start.clone()), start)];
+            for corridor in nav.corridors() {
+                let walked = std::iter::once(&corridor.from).chain(&corridor.tiles);
+                for (from, to) in walked.clone().zip(walked.skip(1)) {
+                    let direction = nav.next_direction(from, to).unwrap();
+                    let movable = Movable::new(to.clone(), 0.0, 0.0, direction);
+                    steps.push((Position(to.clone()), movable));
+                }
+            }
+
+            for (position, movable) in steps {
+                for mode in [GhostMode::Chase, GhostMode::Frightened] {
+                    assert!(
+                        !ghost_moves(&map, &position, &movable, &mode).is_empty(),
+                        "{params:?}: a {mode:?} ghost at {:?} heading {:?} is stuck",
+                        position.0,
+                        movable.direction
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn the_ghosts_start_outside_their_house() {
+        for params in params() {
+            let map = map(&params);
+            assert!(!map.ghost_house.is_empty(), "{params:?}");
+            assert!(!map.is_ghost_house(&map.ghost_start), "{params:?}");
+        }
+    }
+}
@@ -0,0 +1,531 @@
+//! Mazes drawn as text, one character per tile. Wall tiles only need to be
+//! marked as walls: which wall sprite each of them needs is worked out from
+//! the tiles around it.
+
+use std::{collections::HashSet, fmt};
+
+use crate::common::Direction;
+
+use super::{enclosed_tiles, Corner, Map, MapType, OpenContent, TilePos, WallType};
+
+pub const WALL: char = '#';
+pub const FOOD: char = '.';
+pub const GHOST_EATER: char = 'o';
+pub const EMPTY: char = ' ';
+pub const GHOST_ONLY_BARRIER: char = '-';
+/// An empty tile where Pac-Man starts, see [Map::to_layout].
+pub const PACMAN_START: char = 'P';
+/// An empty tile where the ghosts start, see [Map::to_layout].
+pub const GHOST_START: char = 'G';
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
+
+const CORNERS: [Corner; 4] = [
+    Corner::TopRight,
+    Corner::TopLeft,
+    Corner::BottomRight,
+    Corner::BottomLeft,
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayoutError {
+    Empty,
+    RaggedRow { row: usize, width: usize },
+    UnknownTile { pos: TilePos, tile: char },
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutError::Empty => write!(f, "the layout has no tiles"),
+            LayoutError::RaggedRow { row, width } => {
+                write!(f, "row {row} isn't {width} tiles long like the first one")
+            }
+            LayoutError::UnknownTile { pos, tile } => {
+                write!(f, "unknown tile '{tile}' at ({}, {})", pos.x, pos.y)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LayoutError {}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Cell {
+    Wall,
+    Open(char),
+    Door,
+}
+
+/// The outline of a wall tile, before picking between the single and double
+/// line sprites.
+enum Shape {
+    /// A straight edge, the wall lying towards the direction.
+    Straight(Direction),
+    /// The wall bends around an open corner, lying towards the corner.
+    Outer(Corner),
+    /// Only the tile at the opposite diagonal is open.
+    Inner(Corner),
+    /// Surrounded by walls.
+    Filled,
+    /// A wall only one tile thick, which no sprite can draw.
+    Unsupported,
+}
+
+fn offset(dir: &Direction) -> (i32, i32) {
+    match dir {
+        Direction::Up => (0, -1),
+        Direction::Right => (1, 0),
+        Direction::Down => (0, 1),
+        Direction::Left => (-1, 0),
+    }
+}
+
+fn corner_offset(corner: &Corner) -> (i32, i32) {
+    match corner {
+        Corner::TopRight => (1, -1),
+        Corner::TopLeft => (-1, -1),
+        Corner::BottomRight => (1, 1),
+        Corner::BottomLeft => (-1, 1),
+    }
+}
+
+fn opposite_corner(corner: &Corner) -> Corner {
+    match corner {
+        Corner::TopRight => Corner::BottomLeft,
+        Corner::TopLeft => Corner::BottomRight,
+        Corner::BottomRight => Corner::TopLeft,
+        Corner::BottomLeft => Corner::TopRight,
+    }
+}
+
+struct Grid {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+    /// Open tiles that can only be reached through the ghost house door.
+    interior: HashSet<(i32, i32)>,
+}
+
+impl Grid {
+    fn at(&self, x: i32, y: i32) -> Cell {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return Cell::Wall;
+        }
+        self.cells[y as usize * self.width + x as usize]
+    }
+
+    fn find_interior(&mut self) {
+        self.interior = enclosed_tiles(self.width, self.height, |x, y| {
+            matches!(self.at(x, y), Cell::Open(_))
+        })
+        .into_iter()
+        .map(|pos| (pos.x, pos.y))
+        .collect();
+    }
+
+    /// Ghost house walls are drawn around its inside rather than along the
+    /// corridors.
+    fn is_nest(&self, x: i32, y: i32) -> bool {
+        (-1..=1).any(|dx| {
+            (-1..=1).any(|dy| {
+                self.interior.contains(&(x + dx, y + dy)) || self.at(x + dx, y + dy) == Cell::Door
+            })
+        })
+    }
+
+    fn is_solid(&self, x: i32, y: i32, nest: bool) -> bool {
+        match self.at(x, y) {
+            Cell::Wall => true,
+            Cell::Door => nest,
+            Cell::Open(_) => nest && self.interior.contains(&(x, y)),
+        }
+    }
+
+    fn shape(&self, x: i32, y: i32) -> Shape {
+        let nest = self.is_nest(x, y);
+        let open = DIRECTIONS
+            .iter()
+            .filter(|dir| {
+                let (dx, dy) = offset(dir);
+                !self.is_solid(x + dx, y + dy, nest)
+            })
+            .cloned()
+            .collect::<Vec<Direction>>();
+
+        match open.as_slice() {
+            [] => {
+                let open_corners = CORNERS
+                    .iter()
+                    .filter(|corner| {
+                        let (dx, dy) = corner_offset(corner);
+                        !self.is_solid(x + dx, y + dy, nest)
+                    })
+                    .collect::<Vec<&Corner>>();
+                match open_corners.as_slice() {
+                    [] => Shape::Filled,
+                    [corner] => Shape::Inner(opposite_corner(corner)),
+                    _ => Shape::Unsupported,
+                }
+            }
+            [dir] => Shape::Straight(dir.opposite()),
+            [a, b] if *a != b.opposite() => {
+                let vertical = if open.contains(&Direction::Up) {
+                    Direction::Down
+                } else {
+                    Direction::Up
+                };
+                let horizontal = if open.contains(&Direction::Left) {
+                    Direction::Right
+                } else {
+                    Direction::Left
+                };
+                Shape::Outer(match (vertical, horizontal) {
+                    (Direction::Up, Direction::Right) => Corner::TopRight,
+                    (Direction::Up, _) => Corner::TopLeft,
+                    (_, Direction::Right) => Corner::BottomRight,
+                    _ => Corner::BottomLeft,
+                })
+            }
+            _ => Shape::Unsupported,
+        }
+    }
+
+    /// The maze border is drawn with double lines: the tiles on the edge of
+    /// the map and those reached from them through filled wall tiles.
+    /// Walls jutting out of the border keep a single line.
+    fn double_line_tiles(&self, shapes: &[Option<Shape>]) -> HashSet<(i32, i32)> {
+        let (width, height) = (self.width as i32, self.height as i32);
+        let shape_at = |x: i32, y: i32| {
+            if x < 0 || y < 0 || x >= width || y >= height {
+                return None;
+            }
+            shapes[(y * width + x) as usize].as_ref()
+        };
+
+        let mut double = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|(x, y)| *x == 0 || *y == 0 || *x == width - 1 || *y == height - 1)
+            .filter(|(x, y)| shape_at(*x, *y).is_some())
+            .collect::<HashSet<(i32, i32)>>();
+
+        let mut stack = double
+            .iter()
+            .filter(|(x, y)| matches!(shape_at(*x, *y), Some(Shape::Filled)))
+            .copied()
+            .collect::<Vec<(i32, i32)>>();
+        while let Some((x, y)) = stack.pop() {
+            for (dx, dy) in (-1..=1).flat_map(|dx| (-1..=1).map(move |dy| (dx, dy))) {
+                let next = (x + dx, y + dy);
+                let Some(shape) = shape_at(next.0, next.1) else {
+                    continue;
+                };
+                if double.insert(next) && matches!(shape, Shape::Filled) {
+                    stack.push(next);
+                }
+            }
+        }
+        double
+    }
+
+    fn wall_type(
+        &self,
+        x: i32,
+        y: i32,
+        shape: &Shape,
+        double: &HashSet<(i32, i32)>,
+        shapes: &[Option<Shape>],
+    ) -> WallType {
+        if self.is_nest(x, y) {
+            return match shape {
+                Shape::Straight(dir) => WallType::DoubleStraight(dir.clone()),
+                Shape::Outer(corner) => WallType::NestCorner(corner.clone()),
+                Shape::Inner(corner) => WallType::DoubleCorner(corner.clone()),
+                Shape::Filled | Shape::Unsupported => WallType::Inner,
+            };
+        }
+
+        let is_double = double.contains(&(x, y));
+        match shape {
+            Shape::Straight(dir) if is_double => WallType::DoubleStraight(dir.clone()),
+            Shape::Straight(dir) => WallType::Straight(dir.clone()),
+            Shape::Outer(corner) => WallType::OuterCorner(corner.clone()),
+            Shape::Inner(corner) if !is_double => WallType::InnerCorner(corner.clone()),
+            Shape::Inner(corner) => {
+                // Where a single line wall juts out of the border, the border
+                // line bends into it.
+                let single_neighbour = DIRECTIONS.iter().find(|dir| {
+                    let (dx, dy) = offset(dir);
+                    let (nx, ny) = (x + dx, y + dy);
+                    let inside = nx >= 0
+                        && ny >= 0
+                        && (nx as usize) < self.width
+                        && (ny as usize) < self.height;
+                    inside
+                        && shapes[ny as usize * self.width + nx as usize].is_some()
+                        && !double.contains(&(nx, ny))
+                });
+                match (single_neighbour, corner) {
+                    (None, _) => WallType::DoubleCorner(corner.clone()),
+                    (Some(Direction::Left | Direction::Right), _) => {
+                        WallType::VerticalLineInnerCorner(corner.clone())
+                    }
+                    (Some(_), Corner::TopRight) => WallType::HorizontalLineInnerCornerTopRight,
+                    (Some(_), Corner::TopLeft) => WallType::HorizontalLineInnerCornerTopLeft,
+                    (Some(_), Corner::BottomRight) => {
+                        WallType::HorizontalLineInnerCornerBottomRight
+                    }
+                    (Some(_), Corner::BottomLeft) => WallType::HorizontalLineInnerCornerBottomLeft,
+                }
+            }
+            Shape::Filled | Shape::Unsupported => WallType::Inner,
+        }
+    }
+}
+
+fn parse(layout: &[&str]) -> Result<Grid, LayoutError> {
+    let width = layout.first().map_or(0, |row| row.chars().count());
+    if width == 0 {
+        return Err(LayoutError::Empty);
+    }
+
+    let mut cells = Vec::with_capacity(width * layout.len());
+    for (y, row) in layout.iter().enumerate() {
+        if row.chars().count() != width {
+            return Err(LayoutError::RaggedRow { row: y, width });
+        }
+
+        for (x, tile) in row.chars().enumerate() {
+            cells.push(match tile {
+                WALL => Cell::Wall,
+                GHOST_ONLY_BARRIER => Cell::Door,
+                FOOD | GHOST_EATER | EMPTY | PACMAN_START | GHOST_START => Cell::Open(tile),
+                _ => {
+                    let pos = TilePos {
+                        x: x as i32,
+                        y: y as i32,
+                    };
+                    return Err(LayoutError::UnknownTile { pos, tile });
+                }
+            });
+        }
+    }
+
+    let mut grid = Grid {
+        width,
+        height: layout.len(),
+        cells,
+        interior: HashSet::new(),
+    };
+    grid.find_interior();
+    Ok(grid)
+}
+
+/// Wall tiles that no wall sprite can draw, such as walls a single tile thick.
+pub fn unsupported_walls(layout: &[&str]) -> Result<Vec<TilePos>, LayoutError> {
+    let grid = parse(layout)?;
+    let width = grid.width as i32;
+    Ok((0..grid.cells.len() as i32)
+        .map(|i| (i % width, i / width))
+        .filter(|(x, y)| grid.at(*x, *y) == Cell::Wall)
+        .filter(|(x, y)| matches!(grid.shape(*x, *y), Shape::Unsupported))
+        .map(|(x, y)| TilePos { x, y })
+        .collect())
+}
+
+impl Map {
+    /// Reads a maze drawn with [WALL], [FOOD], [GHOST_EATER], [EMPTY] and
+    /// [GHOST_ONLY_BARRIER] tiles, one row per line. The characters start at
+    /// the [PACMAN_START] and [GHOST_START] tiles, or where [Map::new] puts
+    /// them when there are none.
+    pub fn from_layout(layout: &[&str]) -> Result<Self, LayoutError> {
+        let grid = parse(layout)?;
+        let width = grid.width as i32;
+        let positions = (0..grid.cells.len() as i32).map(|i| (i % width, i / width));
+
+        let shapes = positions
+            .clone()
+            .map(|(x, y)| (grid.at(x, y) == Cell::Wall).then(|| grid.shape(x, y)))
+            .collect::<Vec<Option<Shape>>>();
+        let double = grid.double_line_tiles(&shapes);
+
+        let tiles = positions
+            .map(
+                |(x, y)| match (grid.at(x, y), &shapes[(y * width + x) as usize]) {
+                    (Cell::Wall, Some(shape)) => {
+                        MapType::Wall(grid.wall_type(x, y, shape, &double, &shapes))
+                    }
+                    (Cell::Door, _) => MapType::GhostOnlyBarrier,
+                    (Cell::Open(FOOD), _) => MapType::Open(OpenContent::Food),
+                    (Cell::Open(GHOST_EATER), _) => MapType::Open(OpenContent::GhostEater),
+                    _ => MapType::Open(OpenContent::None),
+                },
+            )
+            .collect();
+
+        let mut map = Map::new(grid.width, tiles);
+        let find = |start: char| {
+            (0..grid.cells.len())
+                .find(|i| grid.cells[*i] == Cell::Open(start))
+                .map(|i| TilePos {
+                    x: (i % grid.width) as i32,
+                    y: (i / grid.width) as i32,
+                })
+        };
+        if let Some(pos) = find(PACMAN_START) {
+            map.pacman_start = pos;
+        }
+        if let Some(pos) = find(GHOST_START) {
+            map.ghost_start = pos;
+        }
+        Ok(map)
+    }
+
+    /// Draws the maze back as text, as read by [Map::from_layout]. The
+    /// starts are always marked, so a pellet under one of them is read back
+    /// as an empty tile.
+    pub fn to_layout(&self) -> Vec<String> {
+        self.rows()
+            .enumerate()
+            .map(|(y, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(x, tile)| {
+                        let pos = TilePos {
+                            x: x as i32,
+                            y: y as i32,
+                        };
+                        match tile {
+                            MapType::Wall(_) => WALL,
+                            MapType::GhostOnlyBarrier => GHOST_ONLY_BARRIER,
+                            MapType::Open(_) if pos == self.pacman_start => PACMAN_START,
+                            MapType::Open(_) if pos == self.ghost_start => GHOST_START,
+                            MapType::Open(OpenContent::Food) => FOOD,
+                            MapType::Open(OpenContent::GhostEater) => GHOST_EATER,
+                            MapType::Open(OpenContent::None) => EMPTY,
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draws_the_classic_walls_as_the_arcade_does() {
+        let classic = Map::classic();
+        let layout = classic.to_layout();
+        let map =
+            Map::from_layout(&layout.iter().map(String::as_str).collect::<Vec<&str>>()).unwrap();
+        assert_eq!(
+            map.rows().collect::<Vec<_>>(),
+            classic.rows().collect::<Vec<_>>()
+        );
+        assert_eq!(map.ghost_house, classic.ghost_house);
+        assert_eq!(map.to_layout(), layout);
+    }
+
+    /// A ghost house under a corridor, with Pac-Man's below it.
+    const HOUSE: [&str; 7] = [
+        "#########",
+        "#.......#",
+        "#.##-##.#",
+        "#.#   #.#",
+        "#.#####.#",
+        "#...o...#",
+        "#########",
+    ];
+
+    #[test]
+    fn reads_each_tile() {
+        let map = Map::from_layout(&["#.o", "- #"]).unwrap();
+        let tiles = map.rows().flatten().cloned().collect::<Vec<MapType>>();
+        assert!(matches!(tiles[0], MapType::Wall(_)));
+        assert_eq!(tiles[1], MapType::Open(OpenContent::Food));
+        assert_eq!(tiles[2], MapType::Open(OpenContent::GhostEater));
+        assert_eq!(tiles[3], MapType::GhostOnlyBarrier);
+        assert_eq!(tiles[4], MapType::Open(OpenContent::None));
+        assert!(matches!(tiles[5], MapType::Wall(_)));
+    }
+
+    #[test]
+    fn rejects_unreadable_layouts() {
+        assert_eq!(Map::from_layout(&[]), Err(LayoutError::Empty));
+        assert_eq!(Map::from_layout(&[""]), Err(LayoutError::Empty));
+        assert_eq!(
+            Map::from_layout(&["###", "#.", "###"]),
+            Err(LayoutError::RaggedRow { row: 1, width: 3 })
+        );
+        assert_eq!(
+            Map::from_layout(&["###", "#x#", "###"]),
+            Err(LayoutError::UnknownTile {
+                pos: TilePos { x: 1, y: 1 },
+                tile: 'x'
+            })
+        );
+    }
+
+    #[test]
+    fn finds_the_ghost_house_and_starts() {
+        let map = Map::from_layout(&HOUSE).unwrap();
+        assert_eq!(
+            map.ghost_house,
+            (3..6)
+                .map(|x| TilePos { x, y: 3 })
+                .collect::<Vec<TilePos>>()
+        );
+        assert_eq!(map.ghost_start, TilePos { x: 4, y: 1 });
+        assert_eq!(map.pacman_start, TilePos { x: 4, y: 5 });
+    }
+
+    #[test]
+    fn starts_on_the_marked_tiles() {
+        let mut layout = HOUSE.map(String::from);
+        layout[1].replace_range(2..3, "G");
+        layout[5].replace_range(6..7, "P");
+        let layout = layout.iter().map(String::as_str).collect::<Vec<&str>>();
+
+        let map = Map::from_layout(&layout).unwrap();
+        assert_eq!(map.ghost_start, TilePos { x: 2, y: 1 });
+        assert_eq!(map.pacman_start, TilePos { x: 6, y: 5 });
+        assert_eq!(map.to_layout(), layout);
+    }
+
+    #[test]
+    fn marks_starts_on_pellets() {
+        let mut map = Map::from_layout(&HOUSE).unwrap();
+        map.pacman_start = TilePos { x: 4, y: 5 };
+        map.ghost_start = TilePos { x: 1, y: 1 };
+        let layout = map.to_layout();
+        assert_eq!(&layout[1][..2], "#G");
+        assert_eq!(&layout[5][..5], "#...P");
+
+        let read =
+            Map::from_layout(&layout.iter().map(String::as_str).collect::<Vec<&str>>()).unwrap();
+        assert_eq!(read.pacman_start, map.pacman_start);
+        assert_eq!(read.ghost_start, map.ghost_start);
+        // The pellets under the starts are gone.
+        let empty = MapType::Open(OpenContent::None);
+        assert_eq!(read.get_at(&read.pacman_start), empty);
+        assert_eq!(read.get_at(&read.ghost_start), empty);
+        assert_eq!(read.pellet_count(), map.pellet_count() - 2);
+    }
+
+    #[test]
+    fn finds_walls_too_thin_to_draw() {
+        assert_eq!(unsupported_walls(&HOUSE), Ok(vec![]));
+        assert_eq!(
+            unsupported_walls(&["#####", "#...#", "#.#.#", "#...#", "#####"]),
+            Ok(vec![TilePos { x: 2, y: 2 }])
+        );
+    }
+}
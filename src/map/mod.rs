@@ -0,0 +1,352 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::common::Direction;
+
+#[derive(Component, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+pub enum MapType {
+    Wall(WallType),
+    Open(OpenContent),
+    GhostOnlyBarrier,
+}
+
+/// An empty corridor.
+impl Default for MapType {
+    fn default() -> Self {
+        MapType::Open(OpenContent::None)
+    }
+}
+
+#[derive(PartialEq, Clone, Debug, Reflect)]
+pub enum OpenContent {
+    None,
+    Food,
+    GhostEater,
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Debug, Reflect, Deserialize)]
+pub enum WallType {
+    Straight(Direction),
+    DoubleStraight(Direction),
+    DoubleCorner(Corner),
+    VerticalLineInnerCorner(Corner),
+    OuterCorner(Corner),
+    InnerCorner(Corner),
+    NestCorner(Corner),
+    Inner,
+    HorizontalLineInnerCornerTopRight,
+    HorizontalLineInnerCornerTopLeft,
+    HorizontalLineInnerCornerBottomRight,
+    HorizontalLineInnerCornerBottomLeft,
+}
+
+impl WallType {
+    /// Every wall, each way round.
+    pub fn all() -> Vec<WallType> {
+        let directions = [
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+            Direction::Left,
+        ];
+        let corners = [
+            Corner::TopRight,
+            Corner::TopLeft,
+            Corner::BottomRight,
+            Corner::BottomLeft,
+        ];
+        let cornered: [fn(Corner) -> WallType; 5] = [
+            WallType::DoubleCorner,
+            WallType::VerticalLineInnerCorner,
+            WallType::OuterCorner,
+            WallType::InnerCorner,
+            WallType::NestCorner,
+        ];
+
+        let mut walls = Vec::new();
+        for dir in directions {
+            walls.push(WallType::Straight(dir.clone()));
+            walls.push(WallType::DoubleStraight(dir));
+        }
+        for wall in cornered {
+            walls.extend(corners.iter().cloned().map(wall));
+        }
+        walls.extend([
+            WallType::Inner,
+            WallType::HorizontalLineInnerCornerTopRight,
+            WallType::HorizontalLineInnerCornerTopLeft,
+            WallType::HorizontalLineInnerCornerBottomRight,
+            WallType::HorizontalLineInnerCornerBottomLeft,
+        ]);
+        walls
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Debug, Reflect, Deserialize)]
+pub enum Corner {
+    TopRight,
+    TopLeft,
+    BottomRight,
+    BottomLeft,
+}
+
+mod classic;
+pub mod generate;
+pub mod layout;
+pub mod validate;
+
+use classic::{CLASSIC_HEIGHT, CLASSIC_TILES, CLASSIC_WIDTH};
+
+/// How many tiles of a tunnel lie between the edge of the maze and its exit.
+const TUNNEL_LENGTH: i32 = 6;
+
+/// The maze currently being played.
+#[derive(Resource, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub struct Map {
+    width: usize,
+    height: usize,
+    tiles: Vec<MapType>,
+    /// Intersections where chasing ghosts are not allowed to turn up.
+    pub no_up_tiles: Vec<TilePos>,
+    /// The open tiles inside the ghost house, only reached through its door,
+    /// which the characters keep out of.
+    pub ghost_house: Vec<TilePos>,
+    /// Where Pac-Man starts.
+    pub pacman_start: TilePos,
+    /// Where the ghosts start, above the ghost house door. Eaten ghosts head
+    /// back there.
+    pub ghost_start: TilePos,
+}
+
+impl Default for Map {
+    fn default() -> Self {
+        Self::classic()
+    }
+}
+
+impl Map {
+    /// Builds a map from its rows, which must all be [width] tiles long.
+    /// The ghosts start just above the ghost house door and Pac-Man on the
+    /// first corridor below the house, as in the arcade maze.
+    pub fn new(width: usize, tiles: Vec<MapType>) -> Self {
+        assert!(
+            width > 0 && tiles.len().is_multiple_of(width),
+            "Map tiles don't make up whole rows"
+        );
+        let height = tiles.len() / width;
+        let is_open =
+            |x: i32, y: i32| matches!(tiles[y as usize * width + x as usize], MapType::Open(_));
+        let ghost_house = enclosed_tiles(width, height, is_open);
+
+        let position = |i: usize| TilePos {
+            x: (i % width) as i32,
+            y: (i / width) as i32,
+        };
+        let first_open = (0..tiles.len())
+            .map(position)
+            .find(|pos| is_open(pos.x, pos.y))
+            .unwrap_or_default();
+        let door = (0..tiles.len())
+            .find(|i| tiles[*i] == MapType::GhostOnlyBarrier)
+            .map(position);
+        let ghost_start = door
+            .as_ref()
+            .map(|door| door.translate(&Direction::Up))
+            .filter(|pos| pos.y >= 0 && is_open(pos.x, pos.y))
+            .unwrap_or_else(|| first_open.clone());
+        let pacman_start = door
+            .and_then(|door| {
+                (door.y + 1..height as i32)
+                    .map(|y| TilePos { x: door.x, y })
+                    .find(|pos| is_open(pos.x, pos.y) && !ghost_house.contains(pos))
+            })
+            .unwrap_or(first_open);
+
+        Self {
+            width,
+            height,
+            tiles,
+            no_up_tiles: Vec::new(),
+            ghost_house,
+            pacman_start,
+            ghost_start,
+        }
+    }
+
+    /// The arcade Pac-Man maze.
+    pub fn classic() -> Self {
+        let mut map = Self::new(CLASSIC_WIDTH, CLASSIC_TILES.concat());
+        debug_assert_eq!(map.height, CLASSIC_HEIGHT);
+        // Above the ghost house and above Pac-Man's arcade starting position.
+        map.no_up_tiles = vec![
+            TilePos { x: 12, y: 11 },
+            TilePos { x: 15, y: 11 },
+            TilePos { x: 12, y: 23 },
+            TilePos { x: 15, y: 23 },
+        ];
+        map
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// How many pellets the maze starts with.
+    pub fn pellet_count(&self) -> usize {
+        self.tiles
+            .iter()
+            .filter(|tile| {
+                matches!(
+                    tile,
+                    MapType::Open(OpenContent::Food | OpenContent::GhostEater)
+                )
+            })
+            .count()
+    }
+
+    /// The rows of the map, from top to bottom.
+    pub fn rows(&self) -> impl Iterator<Item = &[MapType]> {
+        self.tiles.chunks(self.width)
+    }
+
+    pub fn contains(&self, tile_pos: &TilePos) -> bool {
+        tile_pos.x >= 0
+            && tile_pos.y >= 0
+            && (tile_pos.x as usize) < self.width
+            && (tile_pos.y as usize) < self.height
+    }
+
+    /// Returns wether the tile at [pos] is a wall or not.
+    /// If the position is outside of map tiles, returns true
+    pub fn is_wall(&self, tile_pos: &TilePos) -> bool {
+        if !self.contains(tile_pos) {
+            return true;
+        }
+
+        matches!(self.get_at(tile_pos), MapType::Wall(_))
+    }
+
+    /// Tunnels are the stretches of open rows leading off the edges of the
+    /// maze, where ghosts slow down.
+    pub fn is_tunnel(&self, tile_pos: &TilePos) -> bool {
+        let on_edge_row = |x: i32| !self.is_wall(&TilePos { x, y: tile_pos.y });
+        let width = self.width as i32;
+        on_edge_row(0)
+            && on_edge_row(width - 1)
+            && (tile_pos.x < TUNNEL_LENGTH || tile_pos.x >= width - TUNNEL_LENGTH)
+    }
+
+    pub fn get_at(&self, tile_pos: &TilePos) -> MapType {
+        if !self.contains(tile_pos) {
+            panic!("Tile ({}, {}) out of bounds", tile_pos.x, tile_pos.y);
+        }
+
+        self.tiles[tile_pos.y as usize * self.width + tile_pos.x as usize].clone()
+    }
+
+    /// The tile next to [pos] in [dir]. Leaving the maze through a tunnel
+    /// comes back in on the other side.
+    pub fn neighbour(&self, pos: &TilePos, dir: &Direction) -> TilePos {
+        let next = pos.translate(dir);
+        TilePos {
+            x: next.x.rem_euclid(self.width as i32),
+            y: next.y.rem_euclid(self.height as i32),
+        }
+    }
+
+    /// The size of the map in pixels.
+    pub fn pixel_size(&self) -> IVec2 {
+        IVec2::new(self.width as i32, self.height as i32) * TILE_SIZE
+    }
+
+    pub fn get_empty_neighbours<'a>(
+        &'a self,
+        pos: &'a TilePos,
+    ) -> impl Iterator<Item = (TilePos, Direction)> + 'a {
+        [
+            Direction::Up,
+            Direction::Down,
+            Direction::Right,
+            Direction::Left,
+        ]
+        .into_iter()
+        .map(|dir| (self.neighbour(pos, &dir), dir))
+        .filter(|(p, _)| !self.is_wall(p))
+        .filter(|(p, _)| !self.is_ghost_house(p))
+    }
+
+    pub fn is_ghost_house(&self, tile_pos: &TilePos) -> bool {
+        self.ghost_house.contains(tile_pos)
+    }
+}
+
+/// The tiles of a [width] by [height] maze that are [open] but walled off from
+/// its corridors, which are those reached from its first open tile, counting
+/// tunnels.
+fn enclosed_tiles(width: usize, height: usize, open: impl Fn(i32, i32) -> bool) -> Vec<TilePos> {
+    let (width, height) = (width as i32, height as i32);
+    let tiles = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)));
+    let Some(start) = tiles.clone().find(|(x, y)| open(*x, *y)) else {
+        return Vec::new();
+    };
+
+    let mut reached = HashSet::from([start]);
+    let mut stack = vec![start];
+    while let Some((x, y)) = stack.pop() {
+        for (dx, dy) in [(0, -1), (1, 0), (0, 1), (-1, 0)] {
+            let next = ((x + dx).rem_euclid(width), (y + dy).rem_euclid(height));
+            if open(next.0, next.1) && reached.insert(next) {
+                stack.push(next);
+            }
+        }
+    }
+
+    tiles
+        .filter(|(x, y)| open(*x, *y) && !reached.contains(&(*x, *y)))
+        .map(|(x, y)| TilePos { x, y })
+        .collect()
+}
+
+pub const TILE_SIZE: i32 = 8;
+
+#[derive(Clone, Debug, Default, PartialEq, Reflect, Deserialize)]
+pub struct TilePos {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl TilePos {
+    pub fn to_display_pos(&self) -> Vec2 {
+        Vec2::new((self.x * TILE_SIZE) as f32, (self.y * TILE_SIZE) as f32)
+    }
+
+    pub fn translate(&self, dir: &Direction) -> Self {
+        let (translate_x, translate_y) = match dir {
+            Direction::Up => (0, -1),
+            Direction::Right => (1, 0),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+        };
+
+        Self {
+            x: self.x + translate_x,
+            y: self.y + translate_y,
+        }
+    }
+
+    /// Squared euclidean distance, as used by the arcade ghosts to compare
+    /// candidate tiles.
+    pub fn dist_sq_to(&self, other: &TilePos) -> i32 {
+        let dx = self.x - other.x;
+        let dy = self.y - other.y;
+        dx * dx + dy * dy
+    }
+}
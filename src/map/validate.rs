@@ -0,0 +1,392 @@
+//! Checks that a maze can be played: the game assumes things such as every
+//! corridor leading somewhere, which a hand drawn maze may not respect.
+
+use std::{collections::HashSet, fmt};
+
+use crate::{
+    ai::{blinky::ghost_moves, GhostMode},
+    common::Direction,
+    components::{Movable, Position},
+};
+
+use super::{
+    layout::{unsupported_walls, LayoutError},
+    Map, MapType, OpenContent, TilePos,
+};
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    /// The layout can't be read at all.
+    Layout(LayoutError),
+    /// Neither Pac-Man nor the ghosts can get there.
+    Unreachable(TilePos),
+    /// A pellet only the ghosts can get to, the level can never be cleared.
+    UnreachablePellet(TilePos),
+    /// A tile where a ghost, which never turns back, is left with nowhere to
+    /// go, such as a corridor ending in a wall.
+    DeadEnd(TilePos),
+    /// Open on one edge of the maze but not on the opposite one.
+    UnpairedTunnel(TilePos),
+    /// No door, or nothing behind it.
+    NoGhostHouse,
+    /// The ghosts start away from the ghost house door.
+    GhostStartAwayFromDoor(TilePos),
+    PacmanStartNotOpen(TilePos),
+    GhostStartNotOpen(TilePos),
+    /// A wall no wall sprite can draw, such as one a single tile thick.
+    UnsupportedWall(TilePos),
+}
+
+impl Problem {
+    /// The tile the problem is about, if any.
+    pub fn pos(&self) -> Option<&TilePos> {
+        match self {
+            Problem::Layout(LayoutError::UnknownTile { pos, .. }) => Some(pos),
+            Problem::Layout(_) | Problem::NoGhostHouse => None,
+            Problem::Unreachable(pos)
+            | Problem::UnreachablePellet(pos)
+            | Problem::DeadEnd(pos)
+            | Problem::UnpairedTunnel(pos)
+            | Problem::GhostStartAwayFromDoor(pos)
+            | Problem::PacmanStartNotOpen(pos)
+            | Problem::GhostStartNotOpen(pos)
+            | Problem::UnsupportedWall(pos) => Some(pos),
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let at = |pos: &TilePos| format!("({}, {})", pos.x, pos.y);
+        match self {
+            Problem::Layout(err) => write!(f, "{err}"),
+            Problem::Unreachable(pos) => write!(f, "tile {} can't be reached", at(pos)),
+            Problem::UnreachablePellet(pos) => {
+                write!(f, "Pac-Man can't reach the pellet at {}", at(pos))
+            }
+            Problem::DeadEnd(pos) => write!(f, "dead end at {}", at(pos)),
+            Problem::UnpairedTunnel(pos) => write!(
+                f,
+                "tunnel at {} has no exit on the other side of the maze",
+                at(pos)
+            ),
+            Problem::NoGhostHouse => write!(f, "no ghost house door with room behind it"),
+            Problem::GhostStartAwayFromDoor(pos) => write!(
+                f,
+                "the ghosts start at {}, away from the ghost house door",
+                at(pos)
+            ),
+            Problem::PacmanStartNotOpen(pos) => {
+                write!(f, "Pac-Man starts at {}, which isn't open", at(pos))
+            }
+            Problem::GhostStartNotOpen(pos) => {
+                write!(f, "the ghosts start at {}, which isn't open", at(pos))
+            }
+            Problem::UnsupportedWall(pos) => {
+                write!(f, "the wall at {} can't be drawn", at(pos))
+            }
+        }
+    }
+}
+
+fn is_open(map: &Map, pos: &TilePos) -> bool {
+    matches!(map.get_at(pos), MapType::Open(_))
+}
+
+/// The tiles reachable from [start] through open tiles, and through the
+/// ghost house door if [through_door].
+fn reachable(map: &Map, start: &TilePos, through_door: bool) -> HashSet<(i32, i32)> {
+    let walkable = |pos: &TilePos| match map.get_at(pos) {
+        MapType::Open(_) => true,
+        MapType::GhostOnlyBarrier => through_door,
+        MapType::Wall(_) => false,
+    };
+
+    let mut reached = HashSet::new();
+    if map.is_wall(start) || !walkable(start) {
+        return reached;
+    }
+
+    reached.insert((start.x, start.y));
+    let mut stack = vec![start.clone()];
+    while let Some(pos) = stack.pop() {
+        for dir in DIRECTIONS.iter() {
+            let next = map.neighbour(&pos, dir);
+            if walkable(&next) && reached.insert((next.x, next.y)) {
+                stack.push(next);
+            }
+        }
+    }
+    reached
+}
+
+/// Whether a ghost at [pos] can be left without a move, going by the same
+/// [ghost_moves] as the game: coming in from any side, or leaving its start
+/// heading up, whether chasing or frightened.
+fn ghost_stuck_at(map: &Map, pos: &TilePos) -> bool {
+    let mut headings = DIRECTIONS
+        .iter()
+        .filter(|dir| !map.is_wall(&map.neighbour(pos, &dir.opposite())))
+        .cloned()
+        .collect::<Vec<Direction>>();
+    if *pos == map.ghost_start {
+        headings.push(Direction::Up);
+    }
+
+    headings.into_iter().any(|direction| {
+        let movable = Movable::new(pos.clone(), 0.0, 0.0, direction);
+        [GhostMode::Chase, GhostMode::Frightened]
+            .iter()
+            .any(|mode| ghost_moves(map, &Position(pos.clone()), &movable, mode).is_empty())
+    })
+}
+
+/// Everything wrong with the maze drawn as [layout], see
+/// [Map::from_layout].
+pub fn validate(layout: &[&str]) -> Vec<Problem> {
+    let map = match Map::from_layout(layout) {
+        Ok(map) => map,
+        Err(err) => return vec![Problem::Layout(err)],
+    };
+    let mut problems = Vec::new();
+
+    let (width, height) = (map.width() as i32, map.height() as i32);
+    let in_bounds = |pos: &TilePos| pos.x >= 0 && pos.y >= 0 && pos.x < width && pos.y < height;
+
+    if !in_bounds(&map.pacman_start) || !is_open(&map, &map.pacman_start) {
+        problems.push(Problem::PacmanStartNotOpen(map.pacman_start.clone()));
+    }
+    if !in_bounds(&map.ghost_start) || !is_open(&map, &map.ghost_start) {
+        problems.push(Problem::GhostStartNotOpen(map.ghost_start.clone()));
+    }
+
+    let by_pacman = if in_bounds(&map.pacman_start) {
+        reachable(&map, &map.pacman_start, false)
+    } else {
+        HashSet::new()
+    };
+    let by_ghosts = if in_bounds(&map.ghost_start) {
+        reachable(&map, &map.ghost_start, true)
+    } else {
+        HashSet::new()
+    };
+
+    let tiles = (0..height).flat_map(|y| (0..width).map(move |x| TilePos { x, y }));
+    let doors = tiles
+        .clone()
+        .filter(|pos| map.get_at(pos) == MapType::GhostOnlyBarrier)
+        .collect::<Vec<TilePos>>();
+
+    for pos in tiles.clone().filter(|pos| is_open(&map, pos)) {
+        if !by_pacman.contains(&(pos.x, pos.y)) && !by_ghosts.contains(&(pos.x, pos.y)) {
+            problems.push(Problem::Unreachable(pos));
+            continue;
+        }
+
+        let pellet = matches!(
+            map.get_at(&pos),
+            MapType::Open(OpenContent::Food | OpenContent::GhostEater)
+        );
+        if pellet && !by_pacman.contains(&(pos.x, pos.y)) {
+            problems.push(Problem::UnreachablePellet(pos.clone()));
+        }
+
+        if !map.is_ghost_house(&pos) && ghost_stuck_at(&map, &pos) {
+            problems.push(Problem::DeadEnd(pos.clone()));
+        }
+
+        let opposite = TilePos {
+            x: if pos.x == 0 || pos.x == width - 1 {
+                width - 1 - pos.x
+            } else {
+                pos.x
+            },
+            y: if pos.y == 0 || pos.y == height - 1 {
+                height - 1 - pos.y
+            } else {
+                pos.y
+            },
+        };
+        if opposite != pos && !is_open(&map, &opposite) {
+            problems.push(Problem::UnpairedTunnel(pos));
+        }
+    }
+
+    if doors.is_empty() || map.ghost_house.is_empty() {
+        problems.push(Problem::NoGhostHouse);
+    } else if in_bounds(&map.ghost_start)
+        && !doors.iter().any(|door| {
+            DIRECTIONS
+                .iter()
+                .any(|dir| map.neighbour(&map.ghost_start, dir) == *door)
+        })
+    {
+        problems.push(Problem::GhostStartAwayFromDoor(map.ghost_start.clone()));
+    }
+
+    problems.extend(
+        unsupported_walls(layout)
+            .unwrap_or_default()
+            .into_iter()
+            .map(Problem::UnsupportedWall),
+    );
+    problems
+}
+
+/// The `validate [<file>...]` command: checks the given maze files, or the
+/// built in mazes when there are none. Returns whether they are all fine.
+pub fn run_validate_command(paths: &[String], builtin: Vec<(String, Vec<String>)>) -> bool {
+    let mazes = if paths.is_empty() {
+        builtin
+    } else {
+        let mut mazes = Vec::new();
+        for path in paths {
+            match std::fs::read_to_string(path) {
+                Ok(text) => mazes.push((path.clone(), text.lines().map(String::from).collect())),
+                Err(err) => {
+                    eprintln!("{path}: {err}");
+                    return false;
+                }
+            }
+        }
+        mazes
+    };
+
+    let mut all_valid = true;
+    for (name, layout) in mazes {
+        let problems = validate(&layout.iter().map(String::as_str).collect::<Vec<&str>>());
+        if problems.is_empty() {
+            println!("{name}: ok");
+            continue;
+        }
+
+        all_valid = false;
+        for problem in problems {
+            println!("{name}: {problem}");
+        }
+    }
+    all_valid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A small maze with nothing wrong with it, for the tests to break.
+    const MAZE: [&str; 13] = [
+        "##############",
+        "#o..........o#",
+        "#.##.####.##.#",
+        "#.##.####.##.#",
+        "#............#",
+        "####.##-##.###",
+        "####.#   #.###",
+        "####.#####.###",
+        "#............#",
+        "#.##.####.##.#",
+        "#.##.####.##.#",
+        "#o..........o#",
+        "##############",
+    ];
+
+    /// The problems of [MAZE] with the tile at ([x], [y]) replaced by [tile].
+    fn problems_with(x: usize, y: usize, tile: char) -> Vec<Problem> {
+        let mut layout = MAZE.map(String::from);
+        layout[y].replace_range(x..x + 1, &tile.to_string());
+        validate(&layout.iter().map(String::as_str).collect::<Vec<&str>>())
+    }
+
+    #[test]
+    fn accepts_a_playable_maze() {
+        assert_eq!(validate(&MAZE), vec![]);
+    }
+
+    #[test]
+    fn accepts_the_builtin_mazes() {
+        for (name, layout) in crate::mazes::builtin_layouts() {
+            let layout = layout.iter().map(String::as_str).collect::<Vec<&str>>();
+            assert_eq!(validate(&layout), vec![], "{name}");
+        }
+    }
+
+    #[test]
+    fn reports_unreadable_layouts() {
+        assert_eq!(
+            validate(&["###", "##"]),
+            vec![Problem::Layout(LayoutError::RaggedRow { row: 1, width: 3 })]
+        );
+    }
+
+    #[test]
+    fn reports_unreachable_tiles() {
+        // Walled off inside the block left of the ghost house.
+        let problems = problems_with(2, 6, ' ');
+        assert!(problems.contains(&Problem::Unreachable(TilePos { x: 2, y: 6 })));
+    }
+
+    #[test]
+    fn reports_pellets_inside_the_ghost_house() {
+        let problems = problems_with(6, 6, '.');
+        assert_eq!(
+            problems,
+            vec![Problem::UnreachablePellet(TilePos { x: 6, y: 6 })]
+        );
+    }
+
+    #[test]
+    fn reports_dead_ends() {
+        let problems = problems_with(12, 5, ' ');
+        assert!(problems.contains(&Problem::DeadEnd(TilePos { x: 12, y: 5 })));
+    }
+
+    #[test]
+    fn reports_corners_where_chasing_ghosts_cant_turn_up() {
+        let mut map = Map::from_layout(&MAZE).unwrap();
+        let corner = TilePos { x: 1, y: 11 };
+        assert!(!ghost_stuck_at(&map, &corner));
+
+        map.no_up_tiles.push(corner.clone());
+        assert!(ghost_stuck_at(&map, &corner));
+    }
+
+    #[test]
+    fn reports_unpaired_tunnels() {
+        let problems = problems_with(0, 4, ' ');
+        assert!(problems.contains(&Problem::UnpairedTunnel(TilePos { x: 0, y: 4 })));
+    }
+
+    #[test]
+    fn reports_a_missing_ghost_house() {
+        let problems = problems_with(7, 5, '#');
+        assert!(problems.contains(&Problem::NoGhostHouse));
+    }
+
+    #[test]
+    fn reports_ghosts_starting_away_from_the_door() {
+        let problems = problems_with(1, 1, 'G');
+        assert_eq!(
+            problems,
+            vec![Problem::GhostStartAwayFromDoor(TilePos { x: 1, y: 1 })]
+        );
+    }
+
+    #[test]
+    fn reports_starts_on_walls() {
+        let problems = validate(&["####", "####"]);
+        assert!(problems.contains(&Problem::PacmanStartNotOpen(TilePos { x: 0, y: 0 })));
+        assert!(problems.contains(&Problem::GhostStartNotOpen(TilePos { x: 0, y: 0 })));
+    }
+
+    #[test]
+    fn reports_walls_too_thin_to_draw() {
+        let problems = problems_with(6, 8, '#');
+        assert!(problems.contains(&Problem::UnsupportedWall(TilePos { x: 6, y: 8 })));
+    }
+}
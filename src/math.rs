@@ -14,5 +14,5 @@ pub fn lerp(a: f32, b: f32, interpolation: f32) -> f32 {
     let diff = b - a;
     let progress = diff * interpolation;
 
-    return a + progress;
+    a + progress
 }
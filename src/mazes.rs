@@ -0,0 +1,293 @@
+use bevy::prelude::*;
+
+use crate::map::{
+    generate::{generate, MazeParams},
+    Map,
+};
+
+/// The colours the maze currently being played is drawn in.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub struct MazePalette {
+    pub walls: Color,
+    pub pellets: Color,
+    pub door: Color,
+}
+
+impl MazePalette {
+    pub const CLASSIC: MazePalette = MazePalette {
+        walls: Color::rgb(0.13, 0.13, 1.0),
+        pellets: Color::rgb(0.98, 0.73, 0.69),
+        door: Color::rgb(1.0, 0.72, 1.0),
+    };
+}
+
+impl Default for MazePalette {
+    fn default() -> Self {
+        Self::CLASSIC
+    }
+}
+
+/// A maze and the colours it is drawn in.
+pub struct Maze {
+    pub map: Map,
+    pub palette: MazePalette,
+}
+
+/// Which mazes the levels are played in.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MazeSet {
+    /// The arcade Pac-Man maze on every level.
+    #[default]
+    Classic,
+    /// The four Ms. Pac-Man mazes, changing as the levels go by.
+    MsPacman,
+    /// A new random maze on every level, the same ones for the same seed.
+    Generated(MazeParams),
+}
+
+impl MazeSet {
+    /// The maze set called [name], generated mazes being of the arcade size
+    /// until given [MazeParams].
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "classic" => Some(MazeSet::Classic),
+            "ms-pacman" => Some(MazeSet::MsPacman),
+            "generated" => Some(MazeSet::Generated(MazeParams::default())),
+            _ => None,
+        }
+    }
+
+    /// The maze [level] is played in, levels counting from 1.
+    pub fn maze_for_level(&self, level: u32) -> Maze {
+        match self {
+            MazeSet::Classic => Maze {
+                map: Map::classic(),
+                palette: MazePalette::CLASSIC,
+            },
+            MazeSet::MsPacman => {
+                let (layout, palette) = &MS_PACMAN_MAZES[ms_pacman_maze_index(level)];
+                Maze {
+                    map: Map::from_layout(layout).expect("Ms. Pac-Man mazes are valid layouts"),
+                    palette: *palette,
+                }
+            }
+            MazeSet::Generated(params) => {
+                let params = MazeParams {
+                    seed: params.seed.wrapping_add(level as u64),
+                    ..*params
+                };
+                let layout = match generate(&params) {
+                    Ok(layout) => layout,
+                    Err(problems) => {
+                        eprintln!(
+                            "Can't generate a maze for level {level}, playing the classic one:"
+                        );
+                        for problem in problems {
+                            eprintln!("  {problem}");
+                        }
+                        return MazeSet::Classic.maze_for_level(level);
+                    }
+                };
+                let rows = layout.iter().map(String::as_str).collect::<Vec<&str>>();
+                let (_, palette) = MS_PACMAN_MAZES[level as usize % MS_PACMAN_MAZES.len()];
+                Maze {
+                    map: Map::from_layout(&rows).expect("generated mazes are validated"),
+                    palette,
+                }
+            }
+        }
+    }
+}
+
+/// The layouts of every maze shipped with the game, by name.
+pub fn builtin_layouts() -> Vec<(String, Vec<String>)> {
+    let ms_pacman = MS_PACMAN_MAZES.iter().enumerate().map(|(i, (layout, _))| {
+        let rows = layout.iter().map(|row| row.to_string()).collect();
+        (format!("ms-pacman-{}", i + 1), rows)
+    });
+    std::iter::once(("classic".to_string(), Map::classic().to_layout()))
+        .chain(ms_pacman)
+        .collect()
+}
+
+/// Ms. Pac-Man plays her first maze for 2 levels, the second for 3, then
+/// alternates between the last two every 4 levels.
+fn ms_pacman_maze_index(level: u32) -> usize {
+    match level {
+        0..=2 => 0,
+        3..=5 => 1,
+        level => 2 + ((level - 6) / 4 % 2) as usize,
+    }
+}
+
+const MS_PACMAN_MAZES: [(&[&str], MazePalette); 4] = [
+    (
+        &MAZE_1,
+        MazePalette {
+            walls: Color::rgb(1.0, 0.72, 0.68),
+            pellets: Color::rgb(0.87, 0.87, 1.0),
+            door: Color::rgb(1.0, 0.72, 1.0),
+        },
+    ),
+    (
+        &MAZE_2,
+        MazePalette {
+            walls: Color::rgb(0.28, 0.72, 1.0),
+            pellets: Color::rgb(1.0, 1.0, 0.0),
+            door: Color::rgb(1.0, 0.72, 1.0),
+        },
+    ),
+    (
+        &MAZE_3,
+        MazePalette {
+            walls: Color::rgb(0.87, 0.59, 0.32),
+            pellets: Color::rgb(1.0, 0.0, 0.0),
+            door: Color::rgb(1.0, 0.72, 1.0),
+        },
+    ),
+    (
+        &MAZE_4,
+        MazePalette {
+            walls: Color::rgb(0.13, 0.13, 0.87),
+            pellets: Color::rgb(0.87, 0.87, 1.0),
+            door: Color::rgb(1.0, 0.72, 1.0),
+        },
+    ),
+];
+
+const MAZE_1: [&str; 31] = [
+    "############################",
+    "#......##..........##......#",
+    "#.####.##.########.##.####.#",
+    "#o####.##.########.##.####o#",
+    "#..........................#",
+    "###.#####.##.##.##.#####.###",
+    "###.#####.##.##.##.#####.###",
+    "###..........##..........###",
+    "....####################....",
+    "###.####################.###",
+    "###.##....########....##.###",
+    "###....##          ##....###",
+    "######.## ###--### ##.######",
+    "######.## #      # ##.######",
+    "###....## #      # ##....###",
+    "###.##### #      # #####.###",
+    "###.##### ######## #####.###",
+    ".........          .........",
+    "###.##.##############.##.###",
+    "###.##.##############.##.###",
+    "#..........................#",
+    "#.####.##.########.##.####.#",
+    "#.####.##.########.##.####.#",
+    "#o.....##....  ....##.....o#",
+    "###.#####.##.##.##.#####.###",
+    "###.#####.##.##.##.#####.###",
+    "#..........................#",
+    "#.####.#####.##.#####.####.#",
+    "#.####.#####.##.#####.####.#",
+    "#..........................#",
+    "############################",
+];
+
+const MAZE_2: [&str; 31] = [
+    "############################",
+    ".......##..........##.......",
+    "######.##.########.##.######",
+    "######.##.########.##.######",
+    "#o........................o#",
+    "#.##########.##.##########.#",
+    "#.##########.##.##########.#",
+    "#.##########.##.##########.#",
+    "#.........##....##.........#",
+    "######.##.########.##.######",
+    "######.##.########.##.######",
+    "###....##          ##....###",
+    "###.##### ###--### #####.###",
+    "###.##### #      # #####.###",
+    "###....## #      # ##....###",
+    "###.##.## #      # ##.##.###",
+    "###.##.## ######## ##.##.###",
+    "###......          ......###",
+    "#########.########.#########",
+    "#########.########.#########",
+    "....##................##....",
+    "###.##.##############.##.###",
+    "###.##.##############.##.###",
+    "#......##....  ....##......#",
+    "#.####.##.########.##.####.#",
+    "#.####.##.########.##.####.#",
+    "#o........................o#",
+    "#.##########.##.##########.#",
+    "#.##########.##.##########.#",
+    "#..........................#",
+    "############################",
+];
+
+const MAZE_3: [&str; 31] = [
+    "############################",
+    "#.........##....##.........#",
+    "#o#######.##.##.##.#######o#",
+    "#.#######.##.##.##.#######.#",
+    "#..........................#",
+    "###.##.##############.##.###",
+    "###.##.##############.##.###",
+    "###.##................##.###",
+    "....#####.########.#####....",
+    "###.#####.########.#####.###",
+    "###.#####.########.#####.###",
+    "###......          ......###",
+    "######.## ###--### ##.######",
+    "######.## #      # ##.######",
+    "###....## #      # ##....###",
+    "###.##### #      # #####.###",
+    "###.##### ######## #####.###",
+    "###......          ......###",
+    "###.##.##############.##.###",
+    "###.##.##############.##.###",
+    "#..........................#",
+    "#.####.##.########.##.####.#",
+    "#.####.##.########.##.####.#",
+    "#......##....  ....##......#",
+    "###.########.##.########.###",
+    "###.########.##.########.###",
+    "#o........................o#",
+    "#.####.#####.##.#####.####.#",
+    "#.####.#####.##.#####.####.#",
+    "#..........................#",
+    "############################",
+];
+
+const MAZE_4: [&str; 31] = [
+    "############################",
+    "#......##..........##......#",
+    "#.####.##.########.##.####.#",
+    "#o####.##.########.##.####o#",
+    "#..........................#",
+    "#.####.##.##.##.##.##.####.#",
+    "#.####.##.##.##.##.##.####.#",
+    "#......##..........##......#",
+    "######.##############.######",
+    "######.##############.######",
+    "######.##############.######",
+    ".........          .........",
+    "######### ###--### #########",
+    "######### #      # #########",
+    ".......## #      # ##.......",
+    "######.## #      # ##.######",
+    "######.## ######## ##.######",
+    "###......          ......###",
+    "###.##.##############.##.###",
+    "###.##.##############.##.###",
+    "#..........................#",
+    "#.#######.########.#######.#",
+    "#o#######.########.#######o#",
+    "#......##....  ....##......#",
+    "###.##.#####.##.#####.##.###",
+    "###.##.#####.##.#####.##.###",
+    "#.........##....##.........#",
+    "#.#######.##.##.##.#######.#",
+    "#.#######.##.##.##.#######.#",
+    "#..........................#",
+    "############################",
+];
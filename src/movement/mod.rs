@@ -0,0 +1,172 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::ai::GhostMode;
+use crate::common::Direction;
+use crate::components::{Movable, Position};
+use crate::map::{Map, MapType, TilePos};
+
+pub mod pixel;
+
+/// Ghosts crawl through the tunnels.
+const TUNNEL_SPEED_MULTIPLIER: f32 = 0.5;
+
+/// How characters get from one tile to the next.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MovementModel {
+    /// Smoothly, a fraction of a tile every frame.
+    #[default]
+    Tiles,
+    /// A whole number of pixels every arcade frame, as the arcade does.
+    Pixels,
+}
+
+impl MovementModel {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "tiles" => Some(MovementModel::Tiles),
+            "pixels" => Some(MovementModel::Pixels),
+            _ => None,
+        }
+    }
+}
+
+/// Which tiles a moving character may enter and how it picks its way.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub enum MoverKind {
+    /// Keeps going straight until told to turn, can turn around at any time
+    /// and is kept out of the ghost house.
+    #[default]
+    Pacman,
+    /// Only ever follows the direction planned by its AI, waiting at the tile
+    /// when there is none, and can go through the ghost house door.
+    Ghost,
+}
+
+impl MoverKind {
+    pub fn can_enter(&self, map: &Map, pos: &TilePos) -> bool {
+        if map.is_wall(pos) {
+            return false;
+        }
+
+        match self {
+            MoverKind::Pacman => map.get_at(pos) != MapType::GhostOnlyBarrier,
+            MoverKind::Ghost => true,
+        }
+    }
+}
+
+/// The direction to take when reaching the next tile, provided by whatever
+/// steers the character: the keyboard or the demo for Pac-Man, the AI for the
+/// ghosts.
+#[derive(Component, Default, Debug, Reflect)]
+#[reflect(Component)]
+pub struct NextDirection(pub Option<Direction>);
+
+/// Pac-Man stops for a moment on every pellet he eats, [0] seconds left.
+#[derive(Component, Default, Debug, Reflect)]
+#[reflect(Component)]
+pub struct EatingPause(pub f32);
+
+/// How fast [movable] currently goes, in tiles per second.
+fn speed(
+    map: &Map,
+    movable: &Movable,
+    kind: &MoverKind,
+    position: &Position,
+    mode: Option<&GhostMode>,
+) -> f32 {
+    let mut speed = movable.base_speed;
+    if let Some(mode) = mode {
+        speed *= mode.speed_multiplier();
+    }
+    if *kind == MoverKind::Ghost && map.is_tunnel(position) {
+        speed *= TUNNEL_SPEED_MULTIPLIER;
+    }
+    speed
+}
+
+/// The way out of the tile the character stands on, if any.
+fn next_direction(
+    map: &Map,
+    kind: &MoverKind,
+    position: &Position,
+    movable: &Movable,
+    next: &mut NextDirection,
+) -> Option<Direction> {
+    if let Some(dir) = next.0.take() {
+        if kind.can_enter(map, &map.neighbour(position, &dir)) {
+            return Some(dir);
+        }
+        // Pac-Man keeps wanting to turn until he can.
+        if *kind == MoverKind::Pacman {
+            next.0 = Some(dir);
+        }
+    }
+
+    match kind {
+        MoverKind::Pacman if kind.can_enter(map, &map.neighbour(position, &movable.direction)) => {
+            Some(movable.direction.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Moves every character along, tile by tile, however far its speed takes it
+/// this frame, asking for the next direction whenever it reaches a tile.
+#[allow(clippy::type_complexity)]
+pub fn move_characters(
+    time: Res<Time>,
+    map: Res<Map>,
+    mut query: Query<(
+        &mut Position,
+        &mut Movable,
+        &mut NextDirection,
+        &MoverKind,
+        Option<&GhostMode>,
+        Option<&mut EatingPause>,
+    )>,
+) {
+    for (mut position, mut movable, mut next, kind, mode, pause) in &mut query {
+        let mut delta = time.delta().as_secs_f32();
+        if let Some(mut pause) = pause {
+            let paused = pause.0.min(delta);
+            pause.0 -= paused;
+            delta -= paused;
+        }
+
+        // Pac-Man may turn around without waiting to reach the next tile.
+        let reversed = Some(movable.direction.opposite());
+        if *kind == MoverKind::Pacman && next.0 == reversed && movable.target_tile != position.0 {
+            let previous_tile = std::mem::replace(&mut position.0, movable.target_tile.clone());
+            movable.target_tile = previous_tile;
+            movable.progress = 1.0 - movable.progress;
+            movable.direction = movable.direction.opposite();
+            next.0 = None;
+        }
+
+        let mut distance = speed(&map, &movable, kind, &position, mode) * delta;
+        loop {
+            if movable.target_tile == position.0 {
+                let Some(dir) = next_direction(&map, kind, &position, &movable, &mut next) else {
+                    movable.progress = 0.0;
+                    break;
+                };
+                movable.target_tile = map.neighbour(&position, &dir);
+                movable.direction = dir;
+            }
+
+            let remaining = 1.0 - movable.progress;
+            if distance < remaining {
+                movable.progress += distance;
+                break;
+            }
+
+            distance -= remaining;
+            movable.progress = 0.0;
+            position.0 = movable.target_tile.clone();
+        }
+    }
+}
@@ -0,0 +1,389 @@
+use bevy::prelude::*;
+
+use crate::ai::{FrightenedTimer, GhostMode};
+use crate::common::Direction;
+use crate::components::{Level, Movable, Position};
+use crate::map::{Map, TilePos, TILE_SIZE};
+
+use super::{next_direction, EatingPause, MoverKind, NextDirection};
+
+/// The arcade runs its game logic at 60 frames per second.
+pub const FRAME_SECS: f32 = 1.0 / 60.0;
+
+/// How many frames a speed bitmask covers.
+const PATTERN_FRAMES: u32 = 32;
+
+/// Where a character is, in pixels, relative to the centre of the top left
+/// tile.
+#[derive(Component, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct PixelPosition(pub IVec2);
+
+impl PixelPosition {
+    pub fn from_tile(tile: &TilePos) -> Self {
+        Self(IVec2::new(tile.x, tile.y) * TILE_SIZE)
+    }
+
+    /// The tile the character is in, each tile spanning from half a tile
+    /// before its centre to just before half a tile after it.
+    pub fn tile(&self) -> TilePos {
+        let half_tile = TILE_SIZE / 2;
+        TilePos {
+            x: (self.0.x + half_tile).div_euclid(TILE_SIZE),
+            y: (self.0.y + half_tile).div_euclid(TILE_SIZE),
+        }
+    }
+
+    /// How far the character is from the centre of its tile.
+    pub fn offset(&self) -> IVec2 {
+        let tile = self.tile();
+        self.0 - IVec2::new(tile.x, tile.y) * TILE_SIZE
+    }
+}
+
+/// Which frames of [PATTERN_FRAMES] a character moves on, the way the
+/// arcade's speed bitmasks say: one pixel on the frames whose bit is set in
+/// [single] and one more on those set in [extra], the first frame being the
+/// highest bit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpeedBitmask {
+    single: u32,
+    extra: u32,
+}
+
+impl SpeedBitmask {
+    const fn new(single: u32, extra: u32) -> Self {
+        Self { single, extra }
+    }
+
+    /// How many pixels to move on [frame].
+    pub fn pixels_on(&self, frame: u32) -> u32 {
+        let bit = 1 << (PATTERN_FRAMES - 1 - frame % PATTERN_FRAMES);
+        u32::from(self.single & bit != 0) + u32::from(self.extra & bit != 0)
+    }
+
+    /// The pattern the arcade moves a character of [kind] by at [level].
+    /// Pac-Man speeds up while the ghosts are [frightened], and ghosts slow
+    /// down in the tunnels.
+    pub fn for_level(
+        level: u32,
+        kind: &MoverKind,
+        mode: Option<&GhostMode>,
+        in_tunnel: bool,
+        frightened: bool,
+    ) -> Self {
+        let speeds = match level {
+            0 | 1 => &SPEED_TABLE[0],
+            2..=4 => &SPEED_TABLE[1],
+            5..=20 => &SPEED_TABLE[2],
+            _ => &SPEED_TABLE[3],
+        };
+        match (kind, mode) {
+            (MoverKind::Pacman, _) if frightened => speeds.pacman_frightened,
+            (MoverKind::Pacman, _) => speeds.pacman,
+            (MoverKind::Ghost, Some(GhostMode::Eaten)) => EYES_SPEED,
+            (MoverKind::Ghost, _) if in_tunnel => speeds.ghost_tunnel,
+            (MoverKind::Ghost, Some(GhostMode::Frightened)) => speeds.ghost_frightened,
+            (MoverKind::Ghost, _) => speeds.ghost,
+        }
+    }
+}
+
+// The arcade's speeds, in percent of full speed: 40 pixels every 32 frames.
+const PERCENT_40: SpeedBitmask = SpeedBitmask::new(0x55555555, 0);
+const PERCENT_45: SpeedBitmask = SpeedBitmask::new(0x55AB55AB, 0);
+const PERCENT_50: SpeedBitmask = SpeedBitmask::new(0x5B5B5B5B, 0);
+const PERCENT_55: SpeedBitmask = SpeedBitmask::new(0x6DB76DB7, 0);
+const PERCENT_60: SpeedBitmask = SpeedBitmask::new(0x77777777, 0);
+const PERCENT_75: SpeedBitmask = SpeedBitmask::new(0x7FFF7FFF, 0);
+const PERCENT_80: SpeedBitmask = SpeedBitmask::new(0xFFFFFFFF, 0);
+const PERCENT_85: SpeedBitmask = SpeedBitmask::new(0xFFFFFFFF, 0x00010001);
+const PERCENT_90: SpeedBitmask = SpeedBitmask::new(0xFFFFFFFF, 0x01010101);
+const PERCENT_95: SpeedBitmask = SpeedBitmask::new(0xFFFFFFFF, 0x04210421);
+const PERCENT_100: SpeedBitmask = SpeedBitmask::new(0xFFFFFFFF, 0x11111111);
+
+/// Eaten ghosts' eyes rush home at two pixels every frame, whatever the
+/// level.
+const EYES_SPEED: SpeedBitmask = SpeedBitmask::new(0xFFFFFFFF, 0xFFFFFFFF);
+
+/// How fast the characters go over a stretch of levels.
+struct LevelSpeeds {
+    pacman: SpeedBitmask,
+    /// While the ghosts are frightened.
+    pacman_frightened: SpeedBitmask,
+    ghost: SpeedBitmask,
+    ghost_frightened: SpeedBitmask,
+    ghost_tunnel: SpeedBitmask,
+}
+
+/// The arcade's speed table, for level 1, levels 2 to 4, 5 to 20 and 21 on.
+/// The arcade no longer frightens the ghosts from level 21, where they keep
+/// the speeds of the levels before.
+const SPEED_TABLE: [LevelSpeeds; 4] = [
+    LevelSpeeds {
+        pacman: PERCENT_80,
+        pacman_frightened: PERCENT_90,
+        ghost: PERCENT_75,
+        ghost_frightened: PERCENT_50,
+        ghost_tunnel: PERCENT_40,
+    },
+    LevelSpeeds {
+        pacman: PERCENT_90,
+        pacman_frightened: PERCENT_95,
+        ghost: PERCENT_85,
+        ghost_frightened: PERCENT_55,
+        ghost_tunnel: PERCENT_45,
+    },
+    LevelSpeeds {
+        pacman: PERCENT_100,
+        pacman_frightened: PERCENT_100,
+        ghost: PERCENT_95,
+        ghost_frightened: PERCENT_60,
+        ghost_tunnel: PERCENT_50,
+    },
+    LevelSpeeds {
+        pacman: PERCENT_90,
+        pacman_frightened: PERCENT_90,
+        ghost: PERCENT_95,
+        ghost_frightened: PERCENT_60,
+        ghost_tunnel: PERCENT_50,
+    },
+];
+
+fn direction_vec(dir: &Direction) -> IVec2 {
+    match dir {
+        Direction::Up => IVec2::new(0, -1),
+        Direction::Right => IVec2::new(1, 0),
+        Direction::Down => IVec2::new(0, 1),
+        Direction::Left => IVec2::new(-1, 0),
+    }
+}
+
+/// Moves the character a single pixel, choosing its way at tile centres.
+fn step(
+    map: &Map,
+    pixel: &mut PixelPosition,
+    movable: &mut Movable,
+    next: &mut NextDirection,
+    kind: &MoverKind,
+) {
+    let tile = pixel.tile();
+
+    // Pac-Man turns as soon as the tile he is in opens the way he wants to
+    // go, cutting the corner rather than waiting to be centred.
+    if *kind == MoverKind::Pacman {
+        if let Some(dir) = next.0.clone() {
+            let reverses = dir == movable.direction.opposite();
+            if reverses || kind.can_enter(map, &map.neighbour(&tile, &dir)) {
+                movable.direction = dir;
+                next.0 = None;
+            }
+        }
+    }
+
+    let offset = pixel.offset();
+    if offset == IVec2::ZERO {
+        match next_direction(map, kind, &Position(tile), movable, next) {
+            Some(dir) => movable.direction = dir,
+            None => return,
+        }
+    }
+
+    let forward = direction_vec(&movable.direction);
+    let across = offset - forward * offset.dot(forward);
+    pixel.0 += forward - across.signum();
+
+    // Leaving the maze through a tunnel comes back in on the other side.
+    let half_tile = TILE_SIZE / 2;
+    pixel.0 = (pixel.0 + half_tile).rem_euclid(map.pixel_size()) - half_tile;
+}
+
+/// Keeps [Position] and [Movable] describing the tile the character last
+/// left and the one it is heading to, as the rest of the game expects.
+fn sync_tiles(map: &Map, pixel: &PixelPosition, position: &mut Position, movable: &mut Movable) {
+    let tile = pixel.tile();
+    let along = pixel.offset().dot(direction_vec(&movable.direction));
+
+    let (from, to, progress) = if pixel.offset() == IVec2::ZERO {
+        (tile.clone(), tile, 0.0)
+    } else if along > 0 {
+        let to = map.neighbour(&tile, &movable.direction);
+        (tile, to, along as f32 / TILE_SIZE as f32)
+    } else {
+        let from = map.neighbour(&tile, &movable.direction.opposite());
+        (from, tile, (TILE_SIZE + along) as f32 / TILE_SIZE as f32)
+    };
+
+    if position.0 != from {
+        position.0 = from;
+    }
+    movable.target_tile = to;
+    movable.progress = progress;
+}
+
+/// The 60 Hz frames counted so far, telling the speed bitmasks where they
+/// are.
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct SpeedFrame(u32);
+
+/// Moves every character a whole number of pixels per 60 Hz frame, following
+/// the arcade's speed bitmask for the level, as an alternative to
+/// [super::move_characters]. The speeds of the [crate::config::GameConfig]
+/// are left out.
+#[allow(clippy::type_complexity)]
+pub fn move_characters_pixels(
+    mut frame: ResMut<SpeedFrame>,
+    map: Res<Map>,
+    level: Res<Level>,
+    frightened_timer: Res<FrightenedTimer>,
+    mut query: Query<(
+        &mut PixelPosition,
+        &mut Position,
+        &mut Movable,
+        &mut NextDirection,
+        &MoverKind,
+        Option<&GhostMode>,
+        Option<&mut EatingPause>,
+    )>,
+) {
+    frame.0 = frame.0.wrapping_add(1);
+    let frightened = !frightened_timer.paused() && !frightened_timer.finished();
+
+    for (mut pixel, mut position, mut movable, mut next, kind, mode, pause) in &mut query {
+        // Eating pellets costs Pac-Man whole frames.
+        if let Some(mut pause) = pause {
+            if pause.0 > FRAME_SECS / 2.0 {
+                pause.0 -= FRAME_SECS;
+                continue;
+            }
+            pause.0 = 0.0;
+        }
+
+        let in_tunnel = map.is_tunnel(&position);
+        let steps =
+            SpeedBitmask::for_level(level.0, kind, mode, in_tunnel, frightened).pixels_on(frame.0);
+        for _ in 0..steps {
+            step(&map, &mut pixel, &mut movable, &mut next, kind);
+        }
+
+        sync_tiles(&map, &pixel, &mut position, &mut movable);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// How many pixels [mask] moves a character over the first [frames].
+    fn pixels_in(mask: SpeedBitmask, frames: u32) -> u32 {
+        (0..frames).map(|frame| mask.pixels_on(frame)).sum()
+    }
+
+    #[test]
+    fn speeds_follow_the_arcade_table() {
+        // Pac-Man, frightened Pac-Man, ghosts, frightened ghosts, ghosts in
+        // the tunnels.
+        let table = [
+            (1, [80, 90, 75, 50, 40]),
+            (2, [90, 95, 85, 55, 45]),
+            (4, [90, 95, 85, 55, 45]),
+            (5, [100, 100, 95, 60, 50]),
+            (20, [100, 100, 95, 60, 50]),
+            (21, [90, 90, 95, 60, 50]),
+        ];
+        let (pacman, ghost) = (MoverKind::Pacman, MoverKind::Ghost);
+        let frightened = Some(&GhostMode::Frightened);
+        for (level, percents) in table {
+            let masks = [
+                SpeedBitmask::for_level(level, &pacman, None, false, false),
+                SpeedBitmask::for_level(level, &pacman, None, false, true),
+                SpeedBitmask::for_level(level, &ghost, Some(&GhostMode::Chase), false, false),
+                SpeedBitmask::for_level(level, &ghost, frightened, false, true),
+                SpeedBitmask::for_level(level, &ghost, Some(&GhostMode::Chase), true, false),
+            ];
+            for (mask, percent) in masks.into_iter().zip(percents) {
+                // Full speed is 40 pixels every 32 frames, spread evenly
+                // enough for half of it to be covered in 16.
+                assert_eq!(pixels_in(mask, 32), percent * 40 / 100, "level {level}");
+                assert_eq!(pixels_in(mask, 16), percent * 20 / 100, "level {level}");
+            }
+        }
+        let eyes = SpeedBitmask::for_level(1, &ghost, Some(&GhostMode::Eaten), true, false);
+        assert_eq!(pixels_in(eyes, 32), 64);
+    }
+
+    #[test]
+    fn tiles_span_half_a_tile_each_way() {
+        let tile = TilePos { x: 3, y: 5 };
+        let centre = PixelPosition::from_tile(&tile);
+        assert_eq!(centre.tile(), tile);
+        let at = |dx, dy| PixelPosition(centre.0 + IVec2::new(dx, dy)).tile();
+        assert_eq!(at(3, 3), tile);
+        assert_eq!(at(-4, -4), tile);
+        assert_eq!(at(4, 0), TilePos { x: 4, y: 5 });
+        assert_eq!(at(0, -5), TilePos { x: 3, y: 4 });
+        // Off the left edge, on the way through a tunnel.
+        assert_eq!(
+            PixelPosition(IVec2::new(-5, 0)).tile(),
+            TilePos { x: -1, y: 0 }
+        );
+    }
+
+    #[test]
+    fn pacman_cuts_corners() {
+        let map = Map::from_layout(&["#####", "##.##", "#...#", "##.##", "#####"]).unwrap();
+        let centre = PixelPosition::from_tile(&TilePos { x: 2, y: 2 });
+        let mut pixel = PixelPosition(centre.0 - IVec2::new(2, 0));
+        let mut movable = Movable::new(TilePos { x: 2, y: 2 }, 0.75, 1.0, Direction::Right);
+        let mut next = NextDirection(Some(Direction::Up));
+
+        // He turns up two pixels early, moving diagonally back into line.
+        step(
+            &map,
+            &mut pixel,
+            &mut movable,
+            &mut next,
+            &MoverKind::Pacman,
+        );
+        assert_eq!(movable.direction, Direction::Up);
+        assert_eq!(next.0, None);
+        assert_eq!(pixel.0, centre.0 + IVec2::new(-1, -1));
+        step(
+            &map,
+            &mut pixel,
+            &mut movable,
+            &mut next,
+            &MoverKind::Pacman,
+        );
+        assert_eq!(pixel.0, centre.0 + IVec2::new(0, -2));
+        step(
+            &map,
+            &mut pixel,
+            &mut movable,
+            &mut next,
+            &MoverKind::Pacman,
+        );
+        assert_eq!(pixel.0, centre.0 + IVec2::new(0, -3));
+    }
+
+    #[test]
+    fn walls_keep_pacman_from_turning_early() {
+        let map = Map::from_layout(&["#####", "#...#", "#####"]).unwrap();
+        let centre = PixelPosition::from_tile(&TilePos { x: 2, y: 1 });
+        let mut pixel = PixelPosition(centre.0 - IVec2::new(2, 0));
+        let mut movable = Movable::new(TilePos { x: 2, y: 1 }, 0.75, 1.0, Direction::Right);
+        let mut next = NextDirection(Some(Direction::Up));
+
+        step(
+            &map,
+            &mut pixel,
+            &mut movable,
+            &mut next,
+            &MoverKind::Pacman,
+        );
+        assert_eq!(movable.direction, Direction::Right);
+        assert_eq!(next.0, Some(Direction::Up));
+        assert_eq!(pixel.0, centre.0 - IVec2::new(1, 0));
+    }
+}
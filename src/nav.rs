@@ -0,0 +1,351 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::common::Direction;
+use crate::map::{Map, MapType, TilePos};
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Left,
+    Direction::Down,
+    Direction::Right,
+];
+
+const UNREACHABLE: u16 = u16::MAX;
+/// How many graphs of mazes played before are kept, see [use_nav_graph].
+const CACHED_GRAPHS: usize = 4;
+
+/// A corridor between two junctions of the maze.
+#[derive(Clone, Debug)]
+pub struct Corridor {
+    pub from: TilePos,
+    /// The tiles of the corridor, excluding [from] and ending with the
+    /// junction it leads to.
+    pub tiles: Vec<TilePos>,
+}
+
+/// Precomputed navigation data for a maze: the walkable tiles and their
+/// neighbours (including tunnels wrapping around the maze edges), the
+/// junctions joined by corridors, and the shortest distance between any two
+/// walkable tiles.
+#[derive(Resource)]
+pub struct NavGraph {
+    /// The maze the graph is of.
+    map: Map,
+    width: usize,
+    height: usize,
+    tiles: Vec<TilePos>,
+    /// Index into [tiles] of each map tile, None when it can't be walked on.
+    tile_indices: Vec<Option<usize>>,
+    neighbours: Vec<Vec<(usize, Direction)>>,
+    /// Shortest distances in tiles, row [a] column [b] at [a * tiles.len() + b].
+    distances: Vec<u16>,
+    junctions: Vec<TilePos>,
+    corridors: Vec<Corridor>,
+}
+
+impl NavGraph {
+    pub fn new(map: &Map) -> Self {
+        let (width, height) = (map.width(), map.height());
+        let mut tiles = Vec::new();
+        let mut tile_indices = vec![None; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                let pos = TilePos {
+                    x: x as i32,
+                    y: y as i32,
+                };
+                if is_walkable(map, &pos) {
+                    tile_indices[y * width + x] = Some(tiles.len());
+                    tiles.push(pos);
+                }
+            }
+        }
+
+        let neighbours = tiles
+            .iter()
+            .map(|pos| {
+                DIRECTIONS
+                    .iter()
+                    .filter_map(|dir| {
+                        let next = map.neighbour(pos, dir);
+                        tile_indices[next.y as usize * width + next.x as usize]
+                            .map(|i| (i, dir.clone()))
+                    })
+                    .collect::<Vec<(usize, Direction)>>()
+            })
+            .collect::<Vec<_>>();
+
+        let distances = all_pairs_distances(&neighbours);
+
+        let mut graph = Self {
+            map: map.clone(),
+            width,
+            height,
+            tiles,
+            tile_indices,
+            neighbours,
+            distances,
+            junctions: Vec::new(),
+            corridors: Vec::new(),
+        };
+        graph.build_corridors();
+        graph
+    }
+
+    fn index_of(&self, pos: &TilePos) -> Option<usize> {
+        if pos.x < 0 || pos.y < 0 || pos.x as usize >= self.width || pos.y as usize >= self.height {
+            return None;
+        }
+        self.tile_indices[pos.y as usize * self.width + pos.x as usize]
+    }
+
+    /// Junctions are the tiles where a choice can be made (three or more
+    /// ways out) and dead ends.
+    fn is_junction(&self, index: usize) -> bool {
+        self.neighbours[index].len() != 2
+    }
+
+    fn build_corridors(&mut self) {
+        for start in 0..self.tiles.len() {
+            if !self.is_junction(start) {
+                continue;
+            }
+            self.junctions.push(self.tiles[start].clone());
+
+            for (first, _) in self.neighbours[start].clone() {
+                let mut previous = start;
+                let mut current = first;
+                let mut tiles = vec![self.tiles[current].clone()];
+
+                while !self.is_junction(current) && current != start {
+                    let next = self.neighbours[current]
+                        .iter()
+                        .map(|(i, _)| *i)
+                        .find(|i| *i != previous)
+                        .expect("Corridor tiles have two neighbours");
+                    previous = current;
+                    current = next;
+                    tiles.push(self.tiles[current].clone());
+                }
+
+                self.corridors.push(Corridor {
+                    from: self.tiles[start].clone(),
+                    tiles,
+                });
+            }
+        }
+    }
+
+    pub fn junctions(&self) -> &[TilePos] {
+        &self.junctions
+    }
+
+    /// Every corridor, once for each direction it can be walked in.
+    pub fn corridors(&self) -> &[Corridor] {
+        &self.corridors
+    }
+
+    /// The length of the shortest path between two tiles, None if either
+    /// can't be walked on or they aren't connected.
+    pub fn distance(&self, from: &TilePos, to: &TilePos) -> Option<u32> {
+        let from = self.index_of(from)?;
+        let to = self.index_of(to)?;
+        match self.distances[from * self.tiles.len() + to] {
+            UNREACHABLE => None,
+            distance => Some(distance as u32),
+        }
+    }
+
+    /// The first junction reached when leaving [from] towards [direction] and
+    /// following the corridor, None if [direction] leads into a wall.
+    pub fn junction_ahead(&self, from: &TilePos, direction: &Direction) -> Option<TilePos> {
+        let start = self.index_of(from)?;
+        let (mut current, _) = self.neighbours[start]
+            .iter()
+            .find(|(_, dir)| dir == direction)?
+            .clone();
+        let mut previous = start;
+
+        while !self.is_junction(current) && current != start {
+            let next = self.neighbours[current]
+                .iter()
+                .map(|(i, _)| *i)
+                .find(|i| *i != previous)
+                .expect("Corridor tiles have two neighbours");
+            previous = current;
+            current = next;
+        }
+        Some(self.tiles[current].clone())
+    }
+
+    /// The first step of a shortest path from [from] to [to].
+    /// Equally short paths are told apart by the arcade direction priority.
+    pub fn next_direction(&self, from: &TilePos, to: &TilePos) -> Option<Direction> {
+        let from_index = self.index_of(from)?;
+        let to_index = self.index_of(to)?;
+        let remaining = self.distances[from_index * self.tiles.len() + to_index];
+        if remaining == UNREACHABLE || remaining == 0 {
+            return None;
+        }
+
+        self.neighbours[from_index]
+            .iter()
+            .filter(|(i, _)| self.distances[i * self.tiles.len() + to_index] == remaining - 1)
+            .map(|(_, dir)| dir.clone())
+            .min_by_key(|dir| dir.arcade_priority())
+    }
+
+    /// A shortest path from [from] to [to], excluding [from] and including
+    /// [to]. Returns None when there is no way between the two tiles.
+    pub fn path(&self, from: &TilePos, to: &TilePos) -> Option<Vec<TilePos>> {
+        self.distance(from, to)?;
+
+        let mut path = Vec::new();
+        let mut current = from.clone();
+        while current != *to {
+            let dir = self.next_direction(&current, to)?;
+            let next = current.translate(&dir);
+            current = TilePos {
+                x: next.x.rem_euclid(self.width as i32),
+                y: next.y.rem_euclid(self.height as i32),
+            };
+            path.push(current.clone());
+        }
+        Some(path)
+    }
+}
+
+/// The graphs of the mazes played before the current one, the most recently
+/// played last.
+#[derive(Resource, Default)]
+pub struct NavGraphCache(Vec<NavGraph>);
+
+/// Makes [NavGraph] the graph of [map]. Graphs take a while to build on big
+/// mazes, and are kept for levels and turns going back to a maze played
+/// shortly before.
+pub fn use_nav_graph(world: &mut World, map: &Map) {
+    if world
+        .get_resource::<NavGraph>()
+        .is_some_and(|nav| nav.map == *map)
+    {
+        return;
+    }
+
+    let previous = world.remove_resource::<NavGraph>();
+    let mut cache = world.get_resource_or_insert_with(NavGraphCache::default);
+    let graph = match cache.0.iter().position(|nav| nav.map == *map) {
+        Some(index) => cache.0.remove(index),
+        None => NavGraph::new(map),
+    };
+    cache.0.extend(previous);
+    if cache.0.len() > CACHED_GRAPHS {
+        cache.0.remove(0);
+    }
+    world.insert_resource(graph);
+}
+
+fn is_walkable(map: &Map, pos: &TilePos) -> bool {
+    !map.is_wall(pos) && map.get_at(pos) != MapType::GhostOnlyBarrier && !map.is_ghost_house(pos)
+}
+
+fn all_pairs_distances(neighbours: &[Vec<(usize, Direction)>]) -> Vec<u16> {
+    let count = neighbours.len();
+    let mut distances = vec![UNREACHABLE; count * count];
+    let mut queue = VecDeque::new();
+
+    for source in 0..count {
+        let row = &mut distances[source * count..(source + 1) * count];
+        row[source] = 0;
+        queue.push_back(source);
+
+        while let Some(current) = queue.pop_front() {
+            for (next, _) in &neighbours[current] {
+                if row[*next] == UNREACHABLE {
+                    row[*next] = row[current] + 1;
+                    queue.push_back(*next);
+                }
+            }
+        }
+    }
+
+    distances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The row of the classic maze's tunnel.
+    const TUNNEL_ROW: i32 = 14;
+
+    fn tile(x: i32, y: i32) -> TilePos {
+        TilePos { x, y }
+    }
+
+    #[test]
+    fn tunnels_wrap_around_the_maze() {
+        let nav = NavGraph::new(&Map::classic());
+        let (left, right) = (tile(0, TUNNEL_ROW), tile(27, TUNNEL_ROW));
+        assert_eq!(nav.distance(&left, &right), Some(1));
+        assert_eq!(nav.next_direction(&left, &right), Some(Direction::Left));
+        assert_eq!(
+            nav.path(&tile(2, TUNNEL_ROW), &tile(25, TUNNEL_ROW)),
+            Some(vec![
+                tile(1, TUNNEL_ROW),
+                left,
+                right,
+                tile(26, TUNNEL_ROW),
+                tile(25, TUNNEL_ROW),
+            ])
+        );
+    }
+
+    #[test]
+    fn walls_and_the_ghost_house_are_unreachable() {
+        let map = Map::classic();
+        let nav = NavGraph::new(&map);
+        let start = map.pacman_start.clone();
+        for unreachable in [tile(0, 0), map.ghost_house[0].clone(), tile(-1, 40)] {
+            assert_eq!(nav.distance(&start, &unreachable), None, "{unreachable:?}");
+            assert_eq!(nav.path(&start, &unreachable), None, "{unreachable:?}");
+            assert_eq!(nav.next_direction(&unreachable, &start), None);
+        }
+    }
+
+    #[test]
+    fn paths_are_as_long_as_the_distance() {
+        let nav = NavGraph::new(&Map::classic());
+        for from in nav.junctions() {
+            for to in nav.junctions() {
+                let path = nav.path(from, to).unwrap();
+                assert_eq!(
+                    Some(path.len() as u32),
+                    nav.distance(from, to),
+                    "{from:?} to {to:?}"
+                );
+                assert_eq!(path.last().unwrap_or(from), to);
+            }
+        }
+    }
+
+    #[test]
+    fn graphs_are_kept_for_the_mazes_played_before() {
+        let classic = Map::classic();
+        let mut other = Map::classic();
+        other.no_up_tiles.clear();
+
+        let mut world = World::new();
+        use_nav_graph(&mut world, &classic);
+        use_nav_graph(&mut world, &other);
+        assert!(world.resource::<NavGraph>().map == other);
+        assert_eq!(world.resource::<NavGraphCache>().0.len(), 1);
+
+        use_nav_graph(&mut world, &classic);
+        assert!(world.resource::<NavGraph>().map == classic);
+        let cache = &world.resource::<NavGraphCache>().0;
+        assert_eq!(cache.len(), 1);
+        assert!(cache[0].map == other);
+    }
+}
@@ -0,0 +1,775 @@
+//! Networked play over UDP. Both machines run the whole game and only trade
+//! the moves of their own player, one per tick of the simulation, so that
+//! both simulate exactly the same game in lockstep. A tick whose move from
+//! the other machine is late is played on a guess that nothing was pressed,
+//! the game being put back and played again from there when the move turns
+//! out to be something else.
+//!
+//! One machine hosts with `--host [PORT]`, the other joins it with
+//! `--join HOST[:PORT]`. Both need the same build, frontend and game
+//! settings, the [PartyMode] being the host's.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::io::{self, ErrorKind};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+use bevy::app::RunFixedUpdateLoop;
+use bevy::ecs::schedule::ScheduleLabel;
+use bevy::prelude::*;
+
+use crate::ai::profile::{AiDice, AiProfile, CasualReaction};
+use crate::common::Direction;
+use crate::components::LevelClear;
+use crate::config::GameConfig;
+use crate::events::GameEvents;
+use crate::game_state::GameState;
+use crate::map::TilePos;
+use crate::mazes::MazeSet;
+use crate::movement::pixel::{SpeedFrame, FRAME_SECS};
+use crate::movement::MovementModel;
+use crate::party::{Controls, PartyMode, PARTY_CONTROLS};
+use crate::players::Players;
+use crate::snapshot::Snapshot;
+use crate::text::{ArcadeText, ArcadeTextBundle};
+
+pub const DEFAULT_PORT: u16 = 7777;
+
+/// Ticks between a key being pressed and the tick it is played on, giving it
+/// time to reach the other machine.
+const INPUT_DELAY: usize = 2;
+/// How many ticks the game may be played ahead of the other machine's moves
+/// before waiting for them.
+const MAX_GUESSED_TICKS: usize = 8;
+/// Moves not yet acknowledged are sent again with every packet, up to this
+/// many.
+const MAX_INPUTS_PER_PACKET: usize = 64;
+const HELLO_INTERVAL: Duration = Duration::from_millis(500);
+/// Nothing heard from the other machine for this long ends the game.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// No key pressed during a tick.
+const NO_INPUT: u8 = 0;
+
+/// How this machine takes part in a networked game.
+#[derive(Clone, Debug)]
+pub enum NetRole {
+    /// Waits for another machine on the port.
+    Host(u16),
+    /// Joins the machine hosting at the address.
+    Join(SocketAddr),
+}
+
+impl NetRole {
+    /// Hosts or joins a game as the [GameConfig] says, hosting coming first.
+    pub fn from_config(config: &GameConfig) -> Option<Self> {
+        if let Some(port) = config.host {
+            return Some(NetRole::Host(port));
+        }
+
+        let address = config.join.as_ref()?;
+        let with_port = if address.contains(':') {
+            address.clone()
+        } else {
+            format!("{address}:{DEFAULT_PORT}")
+        };
+        match with_port.to_socket_addrs().map(|mut addrs| addrs.next()) {
+            Ok(Some(address)) => Some(NetRole::Join(address)),
+            _ => {
+                eprintln!("Can't find the host '{address}'");
+                None
+            }
+        }
+    }
+}
+
+/// The packets exchanged by the two machines.
+#[derive(Debug, PartialEq)]
+enum Message {
+    /// A machine asking to join, with its [settings_digest].
+    Hello { settings: u64 },
+    /// The host letting the machine in. It stays out when the settings
+    /// differ.
+    Welcome {
+        settings: u64,
+        party: PartyMode,
+        seed: u64,
+    },
+    /// The sender's moves from tick [first] on, and how many of the
+    /// receiver's moves it has.
+    Inputs {
+        ack: u32,
+        first: u32,
+        inputs: Vec<u8>,
+    },
+}
+
+impl Message {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        match self {
+            Message::Hello { settings } => {
+                bytes.push(0);
+                bytes.extend(settings.to_le_bytes());
+            }
+            Message::Welcome {
+                settings,
+                party,
+                seed,
+            } => {
+                bytes.push(1);
+                bytes.extend(settings.to_le_bytes());
+                bytes.push(encode_party(party));
+                bytes.extend(seed.to_le_bytes());
+            }
+            Message::Inputs { ack, first, inputs } => {
+                bytes.push(2);
+                bytes.extend(ack.to_le_bytes());
+                bytes.extend(first.to_le_bytes());
+                bytes.extend(inputs);
+            }
+        }
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let u64_at = |at: usize| Some(u64::from_le_bytes(bytes.get(at..at + 8)?.try_into().ok()?));
+        let u32_at = |at: usize| Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+        match bytes.first()? {
+            0 => Some(Message::Hello {
+                settings: u64_at(1)?,
+            }),
+            1 => Some(Message::Welcome {
+                settings: u64_at(1)?,
+                party: decode_party(*bytes.get(9)?)?,
+                seed: u64_at(10)?,
+            }),
+            2 => Some(Message::Inputs {
+                ack: u32_at(1)?,
+                first: u32_at(5)?,
+                inputs: bytes[9..].to_vec(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+fn encode_party(party: &PartyMode) -> u8 {
+    match party {
+        PartyMode::Arcade => 0,
+        PartyMode::Coop => 1,
+        PartyMode::Versus => 2,
+    }
+}
+
+fn decode_party(party: u8) -> Option<PartyMode> {
+    match party {
+        0 => Some(PartyMode::Arcade),
+        1 => Some(PartyMode::Coop),
+        2 => Some(PartyMode::Versus),
+        _ => None,
+    }
+}
+
+fn encode_direction(dir: Option<Direction>) -> u8 {
+    match dir {
+        None => NO_INPUT,
+        Some(Direction::Up) => 1,
+        Some(Direction::Right) => 2,
+        Some(Direction::Down) => 3,
+        Some(Direction::Left) => 4,
+    }
+}
+
+fn decode_direction(input: u8) -> Option<Direction> {
+    match input {
+        1 => Some(Direction::Up),
+        2 => Some(Direction::Right),
+        3 => Some(Direction::Down),
+        4 => Some(Direction::Left),
+        _ => None,
+    }
+}
+
+/// The socket talking to the other machine.
+#[derive(Resource)]
+pub struct NetLink {
+    socket: UdpSocket,
+    role: NetRole,
+    /// The other machine, once known.
+    peer: Option<SocketAddr>,
+    /// The [settings_digest] of this machine.
+    settings: u64,
+    /// What the host answered the other machine, sent again if it asks
+    /// again.
+    welcome: Option<Vec<u8>>,
+    last_hello: Option<Instant>,
+    last_heard: Instant,
+}
+
+impl NetLink {
+    pub fn open(role: NetRole) -> io::Result<Self> {
+        let (bind, peer) = match role {
+            NetRole::Host(port) => (SocketAddr::from(([0, 0, 0, 0], port)), None),
+            NetRole::Join(host) if host.is_ipv6() => (SocketAddr::from(([0; 16], 0)), Some(host)),
+            NetRole::Join(host) => (SocketAddr::from(([0, 0, 0, 0], 0)), Some(host)),
+        };
+        let socket = UdpSocket::bind(bind)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            role,
+            peer,
+            settings: 0,
+            welcome: None,
+            last_hello: None,
+            last_heard: Instant::now(),
+        })
+    }
+
+    fn send(&self, message: &Message) {
+        if let Some(peer) = self.peer {
+            // Packets get lost anyway, the next ones make up for it.
+            let _ = self.socket.send_to(&message.encode(), peer);
+        }
+    }
+
+    /// The next message waiting, with where it comes from.
+    fn receive(&self) -> Option<(Message, SocketAddr)> {
+        let mut buffer = [0; 512];
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((length, from)) => {
+                    if let Some(message) = Message::decode(&buffer[..length]) {
+                        return Some((message, from));
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return None,
+                // Such as the other machine not listening yet.
+                Err(_) => return None,
+            }
+        }
+    }
+}
+
+/// A hash of the settings both machines must agree on for their games to be
+/// the same.
+pub fn settings_digest(
+    mut link: ResMut<NetLink>,
+    maze_set: Res<MazeSet>,
+    profile: Res<AiProfile>,
+    movement: Res<MovementModel>,
+    config: Res<GameConfig>,
+) {
+    let mut hasher = DefaultHasher::new();
+    // The window and camera are the player's own business.
+    let rules = (
+        config.level,
+        config.lives,
+        config.god_mode,
+        config.pacman_speed,
+        config.ghost_speed,
+        &config.pacman_start,
+        &config.ghost_start,
+    );
+    format!("{:?}", (*maze_set, *profile, *movement, config.tui, rules)).hash(&mut hasher);
+    link.settings = hasher.finish();
+}
+
+/// Tells how connecting to the other machine goes.
+#[derive(Component)]
+pub struct NetStatusText;
+
+pub fn spawn_net_status(mut commands: Commands, link: Res<NetLink>) {
+    let status = match link.role {
+        NetRole::Host(_) => "WAITING FOR PLAYER 2".to_string(),
+        NetRole::Join(host) => format!("JOINING {host}"),
+    };
+    commands.spawn((
+        ArcadeTextBundle::new(status, TilePos { x: 2, y: 31 }, Color::WHITE),
+        NetStatusText,
+    ));
+}
+
+fn set_net_status(world: &mut World, status: &str) {
+    let mut query = world.query_filtered::<&mut ArcadeText, With<NetStatusText>>();
+    for mut text in query.iter_mut(world) {
+        text.text = status.to_string();
+    }
+}
+
+/// Meets the other machine, both starting the game once they have agreed on
+/// it.
+pub fn connect(
+    mut commands: Commands,
+    mut link: ResMut<NetLink>,
+    party: Res<PartyMode>,
+    config: Res<GameConfig>,
+    mut status_query: Query<&mut ArcadeText, With<NetStatusText>>,
+    mut events: GameEvents,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if matches!(link.role, NetRole::Join(_))
+        && link
+            .last_hello
+            .is_none_or(|last| last.elapsed() >= HELLO_INTERVAL)
+    {
+        link.send(&Message::Hello {
+            settings: link.settings,
+        });
+        link.last_hello = Some(Instant::now());
+    }
+
+    let mut started = None;
+    while let Some((message, from)) = link.receive() {
+        match (&link.role, message) {
+            (NetRole::Host(_), Message::Hello { settings }) => {
+                let seed = config.seed.unwrap_or_else(|| fastrand::u64(..));
+                let party = match *party {
+                    PartyMode::Arcade => PartyMode::Coop,
+                    party => party,
+                };
+                link.peer = Some(from);
+                link.send(&Message::Welcome {
+                    settings: link.settings,
+                    party,
+                    seed,
+                });
+                if settings == link.settings {
+                    link.welcome = Some(
+                        Message::Welcome {
+                            settings,
+                            party,
+                            seed,
+                        }
+                        .encode(),
+                    );
+                    started = Some((0, party, seed));
+                    break;
+                }
+                link.peer = None;
+            }
+            (
+                NetRole::Join(host),
+                Message::Welcome {
+                    settings,
+                    party,
+                    seed,
+                },
+            ) if from == *host => {
+                if settings == link.settings {
+                    started = Some((1, party, seed));
+                    break;
+                }
+                for mut text in &mut status_query {
+                    text.text = "SETTINGS DIFFER FROM HOST".to_string();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let Some((local_player, party, seed)) = started else {
+        return;
+    };
+    link.last_heard = Instant::now();
+    for mut text in &mut status_query {
+        text.text.clear();
+    }
+    // Both machines start from the same state, whatever their attract loop
+    // was up to.
+    commands.insert_resource(Players::party(party, &config));
+    commands.insert_resource(AiDice::new(seed));
+    commands.insert_resource(CasualReaction::default());
+    commands.insert_resource(SpeedFrame::default());
+    commands.remove_resource::<LevelClear>();
+    events.clear();
+    commands.insert_resource(Lockstep::new(local_player));
+    next_state.set(GameState::Playing);
+}
+
+/// Runs the gameplay in place of [StateTransition], [RunFixedUpdateLoop] and
+/// [Update] when playing over the network, see [run_simulation].
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Simulation;
+
+/// A networked game being played, one tick at a time.
+#[derive(Resource)]
+pub struct Lockstep {
+    local_player: usize,
+    /// The moves of both players, one per tick, as far as known. Those of
+    /// this machine are known [INPUT_DELAY] ticks ahead.
+    inputs: [Vec<u8>; 2],
+    /// The next tick to play.
+    tick: usize,
+    /// The game before each of the last ticks played on a guess of the other
+    /// machine's move, oldest first, with the state it was in.
+    guessed: VecDeque<(Snapshot, GameState)>,
+    /// A move made while the game waited on the other machine.
+    pending: u8,
+    /// How many of this machine's moves the other one has.
+    acknowledged: usize,
+    /// When the simulation started, its clock moving on a tick at a time.
+    clock: Instant,
+}
+
+impl Lockstep {
+    fn new(local_player: usize) -> Self {
+        let mut inputs = [Vec::new(), Vec::new()];
+        inputs[local_player] = vec![NO_INPUT; INPUT_DELAY];
+        Self {
+            local_player,
+            inputs,
+            tick: 0,
+            guessed: VecDeque::new(),
+            pending: NO_INPUT,
+            acknowledged: 0,
+            clock: Instant::now(),
+        }
+    }
+
+    fn remote_player(&self) -> usize {
+        1 - self.local_player
+    }
+
+    /// The first tick played on a guess, if any.
+    fn first_guessed(&self) -> usize {
+        self.tick - self.guessed.len()
+    }
+
+    /// Takes in the other machine's moves, returning the first tick that was
+    /// guessed wrong.
+    fn receive(&mut self, link: &mut NetLink) -> Option<usize> {
+        let remote = self.remote_player();
+        let known = self.inputs[remote].len();
+        while let Some((message, from)) = link.receive() {
+            if Some(from) != link.peer {
+                continue;
+            }
+            link.last_heard = Instant::now();
+            match message {
+                Message::Hello { .. } => {
+                    if let (Some(welcome), Some(peer)) = (&link.welcome, link.peer) {
+                        let _ = link.socket.send_to(welcome, peer);
+                    }
+                }
+                Message::Inputs { ack, first, inputs } => {
+                    self.acknowledged = self.acknowledged.max(ack as usize);
+                    let first = first as usize;
+                    let received = &mut self.inputs[remote];
+                    for (tick, input) in (first..).zip(inputs) {
+                        if tick == received.len() {
+                            received.push(input);
+                        }
+                    }
+                }
+                Message::Welcome { .. } => {}
+            }
+        }
+
+        let confirmed = self.inputs[remote].len().min(self.tick);
+        let first_guessed = self.first_guessed().max(known);
+        let wrong = (first_guessed..confirmed).find(|tick| self.inputs[remote][*tick] != NO_INPUT);
+        if wrong.is_none() {
+            let right = confirmed.saturating_sub(self.first_guessed());
+            self.guessed.drain(..right.min(self.guessed.len()));
+        }
+        wrong
+    }
+
+    /// Puts the game back to before [tick], returning the tick it was
+    /// played up to.
+    fn roll_back(&mut self, world: &mut World, tick: usize) -> usize {
+        let played = self.tick;
+        let guessed = std::mem::take(&mut self.guessed);
+        let (snapshot, state) = &guessed[tick - (played - guessed.len())];
+        snapshot.restore(world);
+        world.insert_resource(State::new(*state));
+        world.insert_resource(NextState::<GameState>(None));
+        self.tick = tick;
+        played
+    }
+
+    /// Sets the world up for the next tick: the keys of both players and a
+    /// frame's time.
+    fn prepare_tick(&mut self, world: &mut World) {
+        let tick = self.tick;
+        if tick >= self.inputs[self.remote_player()].len() {
+            let state = *world.resource::<State<GameState>>().get();
+            self.guessed.push_back((Snapshot::take(world), state));
+        }
+
+        let mut keys = Input::<KeyCode>::default();
+        for (inputs, controls) in self.inputs.iter().zip(PARTY_CONTROLS) {
+            let dir = inputs.get(tick).copied().and_then(decode_direction);
+            if let Some(key) = dir.and_then(|dir| controls.key(&dir)) {
+                keys.press(key);
+            }
+        }
+        world.insert_resource(keys);
+
+        let tick_length = Duration::from_secs_f32(FRAME_SECS);
+        let start = self.clock + tick_length * tick as u32;
+        // A clock's first update only starts it, the second one moving it
+        // on to the start of the tick.
+        let mut time = Time::new(self.clock);
+        time.update_with_instant(self.clock);
+        time.update_with_instant(start);
+        time.update_with_instant(start + tick_length);
+        world.insert_resource(time);
+        self.tick += 1;
+    }
+
+    fn can_play(&self) -> bool {
+        self.tick < self.inputs[self.remote_player()].len() + MAX_GUESSED_TICKS
+    }
+
+    /// Queues the move made on this machine, to be played [INPUT_DELAY]
+    /// ticks from now.
+    fn queue_input(&mut self) {
+        let input = std::mem::replace(&mut self.pending, NO_INPUT);
+        self.inputs[self.local_player].push(input);
+    }
+
+    fn send(&self, link: &NetLink) {
+        let inputs = &self.inputs[self.local_player];
+        let first = self.acknowledged.min(inputs.len());
+        let last = (first + MAX_INPUTS_PER_PACKET).min(inputs.len());
+        link.send(&Message::Inputs {
+            ack: self.inputs[self.remote_player()].len() as u32,
+            first: first as u32,
+            inputs: inputs[first..last].to_vec(),
+        });
+    }
+}
+
+/// Plays a tick of the game with the moves of both players.
+fn play_tick(world: &mut World) {
+    world.resource_scope(|world, mut lockstep: Mut<Lockstep>| lockstep.prepare_tick(world));
+    world.run_schedule(StateTransition);
+    world.run_schedule(FixedUpdate);
+    world.run_schedule(Update);
+}
+
+/// Plays the game a frame at a time as usual, or as many ticks of a
+/// networked game as the moves of both machines allow: none while waiting on
+/// the other machine, a couple to catch up with it, or the last few again
+/// after a wrong guess.
+pub fn run_simulation(world: &mut World) {
+    if !world.contains_resource::<Lockstep>() {
+        world.run_schedule(StateTransition);
+        world.run_schedule(RunFixedUpdateLoop);
+        world.run_schedule(Update);
+        return;
+    }
+
+    // The game is played with the moves of both players, leaving the
+    // keyboard and clock the way they are for the rest of the frame.
+    let keys = world.resource::<Input<KeyCode>>().clone();
+    let time = world.resource::<Time>().clone();
+    // The session's resources stay in the world while the game is played,
+    // for systems to tell a networked game.
+    let wrong = world.resource_scope(|world, mut link: Mut<NetLink>| {
+        world.resource_mut::<Lockstep>().receive(&mut link)
+    });
+    if let Some(tick) = wrong {
+        let played = world
+            .resource_scope(|world, mut lockstep: Mut<Lockstep>| lockstep.roll_back(world, tick));
+        while world.resource::<Lockstep>().tick < played {
+            play_tick(world);
+        }
+    }
+
+    let mut lockstep = world.resource_mut::<Lockstep>();
+    if let Some(dir) = Controls::Any.just_pressed(&keys) {
+        lockstep.pending = encode_direction(Some(dir));
+    }
+    let remote = lockstep.remote_player();
+    let behind = lockstep.inputs[remote].len() > lockstep.tick + INPUT_DELAY + 1;
+    for _ in 0..if behind { 2 } else { 1 } {
+        let mut lockstep = world.resource_mut::<Lockstep>();
+        if lockstep.can_play() {
+            lockstep.queue_input();
+            play_tick(world);
+        }
+    }
+
+    let lockstep = world.resource::<Lockstep>();
+    let link = world.resource::<NetLink>();
+    lockstep.send(link);
+    let lost = link.last_heard.elapsed() > TIMEOUT;
+    let guessing = !lockstep.guessed.is_empty();
+    world.insert_resource(keys);
+    world.insert_resource(time);
+
+    if lost {
+        world
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Attract);
+        set_net_status(world, "CONNECTION LOST");
+    } else if guessing || *world.resource::<State<GameState>>() == GameState::Playing {
+        // A game over on a guess may yet be taken back.
+        return;
+    }
+    // Either way, the game is over and the link goes with it.
+    world.remove_resource::<Lockstep>();
+    world.remove_resource::<NetLink>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{Score, SpriteSheets};
+    use crate::map::Map;
+    use crate::snapshot::register_snapshot_types;
+
+    #[test]
+    fn messages_read_back_as_sent() {
+        let mut messages = vec![
+            Message::Hello { settings: u64::MAX },
+            Message::Inputs {
+                ack: 3,
+                first: 1,
+                inputs: vec![NO_INPUT, 1, 2, 3, 4],
+            },
+            Message::Inputs {
+                ack: 0,
+                first: 0,
+                inputs: vec![],
+            },
+        ];
+        for party in [PartyMode::Arcade, PartyMode::Coop, PartyMode::Versus] {
+            messages.push(Message::Welcome {
+                settings: 42,
+                party,
+                seed: 7,
+            });
+        }
+
+        for message in messages {
+            assert_eq!(Message::decode(&message.encode()), Some(message));
+        }
+    }
+
+    #[test]
+    fn garbled_messages_are_dropped() {
+        let welcome = Message::Welcome {
+            settings: 42,
+            party: PartyMode::Versus,
+            seed: 7,
+        }
+        .encode();
+        let mut unknown_party = welcome.clone();
+        unknown_party[9] = 3;
+
+        assert_eq!(Message::decode(&[]), None);
+        assert_eq!(Message::decode(&[3]), None);
+        assert_eq!(Message::decode(&[0, 1, 2]), None);
+        assert_eq!(Message::decode(&welcome[..welcome.len() - 1]), None);
+        assert_eq!(Message::decode(&unknown_party), None);
+    }
+
+    /// A link and a socket standing for the other machine, talking over the
+    /// loopback.
+    fn loopback() -> (NetLink, UdpSocket) {
+        let remote = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut link = NetLink::open(NetRole::Join(remote.local_addr().unwrap())).unwrap();
+        link.socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        link.socket.set_nonblocking(true).unwrap();
+        (link, remote)
+    }
+
+    fn send_inputs(remote: &UdpSocket, link: &NetLink, ack: u32, first: u32, inputs: &[u8]) {
+        let message = Message::Inputs {
+            ack,
+            first,
+            inputs: inputs.to_vec(),
+        };
+        remote
+            .send_to(&message.encode(), link.socket.local_addr().unwrap())
+            .unwrap();
+        // Gives the packet time to come through.
+        std::thread::sleep(Duration::from_millis(20));
+    }
+
+    /// A world with as much of a game as a [Snapshot] needs, its score
+    /// telling the ticks apart.
+    fn world() -> World {
+        let mut app = App::new();
+        register_snapshot_types(&mut app);
+        app.insert_resource(Map::classic())
+            .init_resource::<SpriteSheets>()
+            .init_resource::<Score>()
+            .insert_resource(State::new(GameState::Playing));
+        std::mem::take(&mut app.world)
+    }
+
+    /// Plays [ticks] more ticks, scoring each one.
+    fn play(lockstep: &mut Lockstep, world: &mut World, ticks: usize) {
+        for _ in 0..ticks {
+            lockstep.queue_input();
+            lockstep.prepare_tick(world);
+            world.resource_mut::<Score>().current = lockstep.tick as u32 * 10;
+        }
+    }
+
+    #[test]
+    fn late_moves_are_guessed_then_played_again() {
+        let (mut link, remote) = loopback();
+        let mut world = world();
+        let mut lockstep = Lockstep::new(0);
+
+        // Nothing heard from the other machine yet, every tick is a guess.
+        play(&mut lockstep, &mut world, 4);
+        assert_eq!(lockstep.guessed.len(), 4);
+
+        // Its first moves are what was guessed, and they stop being guesses.
+        send_inputs(&remote, &link, 2, 0, &[NO_INPUT, NO_INPUT]);
+        assert_eq!(lockstep.receive(&mut link), None);
+        assert_eq!(lockstep.acknowledged, 2);
+        assert_eq!(lockstep.first_guessed(), 2);
+
+        // Moves past a gap wait for those before them, and strangers are
+        // ignored.
+        send_inputs(&remote, &link, 2, 3, &[1]);
+        let stranger = UdpSocket::bind("127.0.0.1:0").unwrap();
+        send_inputs(&stranger, &link, 9, 2, &[1, 1]);
+        assert_eq!(lockstep.receive(&mut link), None);
+        assert_eq!(lockstep.inputs[1].len(), 2);
+        assert_eq!(lockstep.acknowledged, 2);
+
+        // The rest comes again with the first ones, and tick 3 was guessed
+        // wrong: the game goes back to before it and plays it again.
+        send_inputs(&remote, &link, 4, 1, &[NO_INPUT, NO_INPUT, 3]);
+        assert_eq!(lockstep.receive(&mut link), Some(3));
+        assert_eq!(lockstep.inputs[1], vec![NO_INPUT, NO_INPUT, NO_INPUT, 3]);
+
+        assert_eq!(lockstep.roll_back(&mut world, 3), 4);
+        assert_eq!(lockstep.tick, 3);
+        assert_eq!(world.resource::<Score>().current, 30);
+
+        lockstep.prepare_tick(&mut world);
+        let down = PARTY_CONTROLS[1].key(&Direction::Down).unwrap();
+        assert!(world.resource::<Input<KeyCode>>().pressed(down));
+        assert!(lockstep.guessed.is_empty());
+    }
+
+    #[test]
+    fn right_guesses_are_not_played_again() {
+        let (mut link, remote) = loopback();
+        let mut world = world();
+        let mut lockstep = Lockstep::new(1);
+
+        play(&mut lockstep, &mut world, 3);
+        send_inputs(&remote, &link, 0, 0, &[NO_INPUT; 5]);
+        assert_eq!(lockstep.receive(&mut link), None);
+        assert!(lockstep.guessed.is_empty());
+
+        // Known ahead of time, the next ticks aren't guesses.
+        play(&mut lockstep, &mut world, 2);
+        assert!(lockstep.guessed.is_empty());
+        assert_eq!(world.resource::<Score>().current, 50);
+    }
+}
@@ -0,0 +1,158 @@
+//! Two players on the same maze at once: both as Pac-Man sharing the
+//! pellets, or one as Pac-Man and the other driving Blinky.
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::ai::{blinky::ghost_moves, Ghost, GhostMode};
+use crate::common::Direction;
+use crate::components::{Movable, Position};
+use crate::map::Map;
+use crate::movement::NextDirection;
+
+/// The second Pac-Man is tinted so that the players can tell who is who.
+pub const SECOND_PACMAN_TINT: Color = Color::rgb(0.6, 1.0, 1.0);
+
+/// How two players share the maze, see [crate::players::Players].
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PartyMode {
+    /// One player at a time, as in the arcade.
+    #[default]
+    Arcade,
+    /// Two Pac-Men eating the same pellets, each scoring for themself.
+    Coop,
+    /// The second player drives Blinky, scoring every time Pac-Man gets
+    /// caught.
+    Versus,
+}
+
+impl PartyMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "arcade" => Some(PartyMode::Arcade),
+            "coop" => Some(PartyMode::Coop),
+            "versus" => Some(PartyMode::Versus),
+            _ => None,
+        }
+    }
+}
+
+/// The player a character belongs to, from 0. Characters without one
+/// belong to the player at the controls.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct PlayerIndex(pub usize);
+
+const WASD_KEYS: &[(KeyCode, Direction)] = &[
+    (KeyCode::W, Direction::Up),
+    (KeyCode::A, Direction::Left),
+    (KeyCode::S, Direction::Down),
+    (KeyCode::D, Direction::Right),
+];
+const ARROW_KEYS: &[(KeyCode, Direction)] = &[
+    (KeyCode::Up, Direction::Up),
+    (KeyCode::Left, Direction::Left),
+    (KeyCode::Down, Direction::Down),
+    (KeyCode::Right, Direction::Right),
+];
+const ANY_KEYS: &[(KeyCode, Direction)] = &[
+    (KeyCode::W, Direction::Up),
+    (KeyCode::A, Direction::Left),
+    (KeyCode::S, Direction::Down),
+    (KeyCode::D, Direction::Right),
+    (KeyCode::Up, Direction::Up),
+    (KeyCode::Left, Direction::Left),
+    (KeyCode::Down, Direction::Down),
+    (KeyCode::Right, Direction::Right),
+];
+
+/// The keys steering a character.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub enum Controls {
+    Wasd,
+    Arrows,
+    /// For a lone player, who can use either.
+    #[default]
+    Any,
+}
+
+/// The keys of each player when two share the maze.
+pub const PARTY_CONTROLS: [Controls; 2] = [Controls::Wasd, Controls::Arrows];
+
+impl Controls {
+    fn keys(&self) -> &'static [(KeyCode, Direction)] {
+        match self {
+            Controls::Wasd => WASD_KEYS,
+            Controls::Arrows => ARROW_KEYS,
+            Controls::Any => ANY_KEYS,
+        }
+    }
+
+    /// The direction just asked for, if any.
+    pub fn just_pressed(&self, keyboard_input: &Input<KeyCode>) -> Option<Direction> {
+        self.keys()
+            .iter()
+            .find(|(key, _)| keyboard_input.just_pressed(*key))
+            .map(|(_, dir)| dir.clone())
+    }
+
+    /// The key asking for [dir].
+    pub fn key(&self, dir: &Direction) -> Option<KeyCode> {
+        self.keys()
+            .iter()
+            .find(|(_, key_dir)| key_dir == dir)
+            .map(|(key, _)| *key)
+    }
+}
+
+/// The way the player driving a ghost last asked for, taken as soon as the
+/// ghost is allowed to.
+#[derive(Component, Default, Debug, Reflect)]
+#[reflect(Component)]
+pub struct RequestedDirection(pub Option<Direction>);
+
+/// Drives the ghosts a player controls. Like the AI, the way out of the next
+/// tile is picked among those the ghost rules allow, so a player can't turn
+/// back or enter the ghost house: the direction asked for when possible,
+/// straight on otherwise, or the way the arcade ghosts prefer. Eaten ghosts
+/// are brought home by the AI.
+pub fn steer_ghosts(
+    keyboard_input: Res<Input<KeyCode>>,
+    map: Res<Map>,
+    mut query: Query<
+        (
+            &Movable,
+            &GhostMode,
+            &Controls,
+            &mut RequestedDirection,
+            &mut NextDirection,
+        ),
+        With<Ghost>,
+    >,
+) {
+    for (movable, mode, controls, mut requested, mut next) in &mut query {
+        if let Some(dir) = controls.just_pressed(&keyboard_input) {
+            requested.0 = Some(dir);
+        }
+        if *mode == GhostMode::Eaten {
+            continue;
+        }
+
+        let decision_pos = Position(movable.target_tile.clone());
+        let moves = ghost_moves(&map, &decision_pos, movable, mode);
+        let allowed = |dir: &Direction| moves.iter().any(|(_, allowed)| allowed == dir);
+        next.0 = requested
+            .0
+            .clone()
+            .filter(allowed)
+            .or_else(|| Some(movable.direction.clone()).filter(allowed))
+            .or_else(|| {
+                moves
+                    .iter()
+                    .map(|(_, dir)| dir.clone())
+                    .min_by_key(Direction::arcade_priority)
+            });
+    }
+}
@@ -1,57 +1,109 @@
 use bevy::prelude::*;
 
-use crate::ai::Ghost;
-use crate::common::Direction;
-use crate::components::{Movable, Player, Position};
-use crate::map::{MapType, MAP};
+use crate::ai::{Ghost, GhostMode};
+use crate::components::{Pellet, Player, Position, Score, SpriteSheets};
+use crate::config::GameConfig;
+use crate::events::{GhostEaten, PacmanDied, PelletEaten};
+use crate::movement::{EatingPause, NextDirection};
+use crate::party::{Controls, PlayerIndex};
+use crate::players::{award_points, Players};
+
+const GHOST_POINTS: u32 = 200;
+/// What the player driving a ghost scores for catching Pac-Man.
+const CATCH_POINTS: u32 = 1000;
+/// Pac-Man loses a frame eating a pellet, and three eating a power pellet.
+const PELLET_PAUSE_SECS: f32 = 1.0 / 60.0;
+const POWER_PELLET_PAUSE_SECS: f32 = 3.0 / 60.0;
 
 pub fn check_collision(
-    player_query: Query<(&Position, &Player)>,
-    ghost_query: Query<(&Position, &Ghost)>,
+    mut score: ResMut<Score>,
+    mut players: ResMut<Players>,
+    mut ghost_eaten_events: EventWriter<GhostEaten>,
+    mut died_events: EventWriter<PacmanDied>,
+    config: Res<GameConfig>,
+    player_query: Query<(&Position, Option<&PlayerIndex>), With<Player>>,
+    mut ghost_query: Query<(&Position, &mut GhostMode, Option<&PlayerIndex>), With<Ghost>>,
 ) {
-    for (player_pos, _) in player_query.iter() {
-        for (ghost_pos, _) in ghost_query.iter() {
-            if player_pos == ghost_pos {
-                println!("Collision at tile {player_pos:?}");
+    let current = players.current();
+    // The first player gets the ghost when two Pac-Men reach it together,
+    // whichever order they come in.
+    let mut pacmen = player_query
+        .iter()
+        .map(|(pos, index)| (pos, index.map_or(current, |index| index.0)))
+        .collect::<Vec<_>>();
+    pacmen.sort_by_key(|(_, player)| *player);
+
+    for (player_pos, pacman_player) in pacmen {
+        for (ghost_pos, mut mode, ghost_player) in &mut ghost_query {
+            if player_pos != ghost_pos {
+                continue;
+            }
+
+            match *mode {
+                GhostMode::Chase if config.god_mode => {}
+                GhostMode::Chase => {
+                    if let Some(PlayerIndex(player)) = ghost_player {
+                        award_points(&mut score, &mut players, *player, CATCH_POINTS);
+                    }
+                    died_events.send(PacmanDied);
+                }
+                GhostMode::Frightened => {
+                    *mode = GhostMode::Eaten;
+                    award_points(&mut score, &mut players, pacman_player, GHOST_POINTS);
+                    ghost_eaten_events.send(GhostEaten);
+                }
+                GhostMode::Eaten => {}
             }
         }
     }
 }
 
-pub fn steer(keyboard_input: Res<Input<KeyCode>>, mut query: Query<&mut Movable, &Player>) {
-    for mut movable in &mut query {
-        let new_dir = if keyboard_input.just_pressed(KeyCode::W) {
-            Direction::Up
-        } else if keyboard_input.just_pressed(KeyCode::A) {
-            Direction::Left
-        } else if keyboard_input.just_pressed(KeyCode::S) {
-            Direction::Down
-        } else if keyboard_input.just_pressed(KeyCode::D) {
-            Direction::Right
-        } else {
-            continue;
-        };
-
-        let new_target = movable.target_tile.translate(&new_dir);
-        if !MAP.is_wall(&new_target) && MAP.get_at(&new_target) != MapType::GhostOnlyBarrier {
-            movable.direction = new_dir;
+/// Turns every Pac-Man the way its player asks.
+pub fn steer(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut query: Query<(&Controls, &mut NextDirection), With<Player>>,
+) {
+    for (controls, mut next) in &mut query {
+        if let Some(dir) = controls.just_pressed(&keyboard_input) {
+            next.0 = Some(dir);
         }
     }
 }
 
-pub fn move_player(time: Res<Time>, mut query: Query<(&mut Position, &mut Movable, &Player)>) {
-    for (mut pos, mut movable, _) in &mut query {
-        let delta = time.delta().as_secs_f32();
-        let percent = movable.base_speed * delta;
-        movable.progress = movable.progress + percent;
-
-        if movable.progress >= 1.0 {
-            movable.progress = 0.0;
-            pos.0 = movable.target_tile.clone();
+pub fn eat_pellets(
+    mut commands: Commands,
+    mut score: ResMut<Score>,
+    mut players: ResMut<Players>,
+    mut pellet_events: EventWriter<PelletEaten>,
+    sprite_sheets: Res<SpriteSheets>,
+    mut player_query: Query<(&Position, &mut EatingPause, Option<&PlayerIndex>), With<Player>>,
+    mut pellet_query: Query<(Entity, &Position, &Pellet, &mut TextureAtlasSprite)>,
+) {
+    let current = players.current();
+    // Two Pac-Men reaching a pellet together only eat it once, the first
+    // player getting it.
+    let mut pacmen = player_query
+        .iter_mut()
+        .map(|(pos, pause, index)| (pos, pause, index.map_or(current, |index| index.0)))
+        .collect::<Vec<_>>();
+    pacmen.sort_by_key(|(_, _, player)| *player);
 
-            let new_tile = pos.translate(&movable.direction);
-            if !MAP.is_wall(&new_tile) {
-                movable.target_tile = new_tile;
+    let mut eaten = Vec::new();
+    for (player_pos, mut pause, player) in pacmen {
+        for (entity, pellet_pos, pellet, mut sprite) in &mut pellet_query {
+            if player_pos == pellet_pos && !eaten.contains(&entity) {
+                award_points(&mut score, &mut players, player, pellet.points);
+                pause.0 += if pellet.power {
+                    POWER_PELLET_PAUSE_SECS
+                } else {
+                    PELLET_PAUSE_SECS
+                };
+                pellet_events.send(PelletEaten {
+                    power: pellet.power,
+                });
+                sprite.index = sprite_sheets.manifest.tiles.empty;
+                commands.entity(entity).remove::<Pellet>();
+                eaten.push(entity);
             }
         }
     }
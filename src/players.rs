@@ -0,0 +1,356 @@
+use bevy::prelude::*;
+
+use crate::components::{Level, Pellet, Position, Score, SpriteSheets};
+use crate::config::GameConfig;
+use crate::editor::PlayTest;
+use crate::game_state::GameState;
+use crate::map::{MapType, OpenContent, TilePos};
+use crate::party::PartyMode;
+
+const GAME_OVER_SECS: f32 = 3.0;
+
+/// Where a player left off when the other one took over.
+#[derive(Clone, Debug, Reflect)]
+pub struct Turn {
+    pub score: u32,
+    pub lives: u32,
+    pub level: u32,
+    /// The pellets already eaten in the maze of [level].
+    pub eaten: Vec<TilePos>,
+}
+
+impl Turn {
+    /// A player yet to start, with the lives and level of the [GameConfig].
+    pub fn new(config: &GameConfig) -> Self {
+        Self {
+            score: 0,
+            lives: config.lives,
+            level: config.level,
+            eaten: Vec::new(),
+        }
+    }
+}
+
+/// The players of the game, taking turns at every death as in the arcade,
+/// or all playing at once in a [PartyMode] other than the arcade one.
+/// The score, lives and level of the player at the controls, the first one in
+/// a party, are those of [Score] and [Level]. [Turn]s only get updated as
+/// players swap, but for the scores of the other players of a party.
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct Players {
+    turns: Vec<Turn>,
+    current: usize,
+    party: PartyMode,
+}
+
+impl Default for Players {
+    fn default() -> Self {
+        Self::new(1, &GameConfig::default())
+    }
+}
+
+/// What happens after the player at the controls got caught.
+#[derive(Debug, PartialEq, Eq)]
+pub enum NextTurn {
+    /// The same player goes again.
+    Again,
+    /// The player at [0] takes over.
+    Switch(usize),
+    GameOver,
+}
+
+impl Players {
+    pub fn new(count: usize, config: &GameConfig) -> Self {
+        Self {
+            turns: vec![Turn::new(config); count],
+            current: 0,
+            party: PartyMode::Arcade,
+        }
+    }
+
+    /// Two players at once, sharing the lives of the first one.
+    pub fn party(party: PartyMode, config: &GameConfig) -> Self {
+        Self {
+            party,
+            ..Self::new(2, config)
+        }
+    }
+
+    pub fn party_mode(&self) -> PartyMode {
+        self.party
+    }
+
+    /// Whether the players play one after the other.
+    pub fn take_turns(&self) -> bool {
+        self.count() > 1 && self.party == PartyMode::Arcade
+    }
+
+    pub fn count(&self) -> usize {
+        self.turns.len()
+    }
+
+    /// The index of the player at the controls, from 0.
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn turn(&self, player: usize) -> Option<&Turn> {
+        self.turns.get(player)
+    }
+
+    pub fn lives(&self) -> u32 {
+        self.turns[self.current].lives
+    }
+
+    /// Takes a life from the player at the controls, the next player still
+    /// having some goes next.
+    pub fn lose_life(&mut self) -> NextTurn {
+        let turn = &mut self.turns[self.current];
+        turn.lives = turn.lives.saturating_sub(1);
+        if self.party != PartyMode::Arcade {
+            return match turn.lives {
+                0 => NextTurn::GameOver,
+                _ => NextTurn::Again,
+            };
+        }
+
+        let count = self.count();
+        let next = (1..=count)
+            .map(|offset| (self.current + offset) % count)
+            .find(|player| self.turns[*player].lives > 0);
+        match next {
+            Some(player) if player == self.current => NextTurn::Again,
+            Some(player) => NextTurn::Switch(player),
+            None => NextTurn::GameOver,
+        }
+    }
+}
+
+/// Gives [points] to [player], whose score is [Score::current] when at the
+/// controls. [Players] is only touched for the others, so that it isn't
+/// flagged as changed at every pellet.
+pub fn award_points(score: &mut Score, players: &mut ResMut<Players>, player: usize, points: u32) {
+    if player == players.current {
+        score.current += points;
+    } else if let Some(turn) = players.turns.get_mut(player) {
+        turn.score += points;
+    }
+}
+
+/// The best score of any player.
+pub fn best_score(score: &Score, players: &Players) -> u32 {
+    players
+        .turns
+        .iter()
+        .enumerate()
+        .filter(|(player, _)| *player != players.current)
+        .map(|(_, turn)| turn.score)
+        .fold(score.current, u32::max)
+}
+
+/// Hands the controls over to another player at the start of the next
+/// frame.
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct SwitchPlayer(pub usize);
+
+/// Every player is out of lives, "GAME  OVER" shows for a while.
+#[derive(Resource, Deref, DerefMut, Reflect)]
+#[reflect(Resource)]
+pub struct GameOver(Timer);
+
+impl Default for GameOver {
+    fn default() -> Self {
+        Self(Timer::from_seconds(GAME_OVER_SECS, TimerMode::Once))
+    }
+}
+
+/// True while nobody is at the controls, as players swap or once the game
+/// is over.
+pub fn between_turns(switch: Option<Res<SwitchPlayer>>, game_over: Option<Res<GameOver>>) -> bool {
+    switch.is_some() || game_over.is_some()
+}
+
+/// Leaving a game, back to a single player for the next one, including
+/// play-tests from the editor.
+pub fn reset_players(mut commands: Commands, config: Res<GameConfig>) {
+    commands.insert_resource(Players::new(1, &config));
+    commands.remove_resource::<GameOver>();
+    commands.remove_resource::<SwitchPlayer>();
+}
+
+/// Remembers where the player at the controls was before handing over to
+/// the next one.
+pub fn save_turn(
+    switch: Res<SwitchPlayer>,
+    mut players: ResMut<Players>,
+    score: Res<Score>,
+    level: Res<Level>,
+    tile_query: Query<(&Position, &MapType), Without<Pellet>>,
+) {
+    let current = players.current;
+    let turn = &mut players.turns[current];
+    turn.score = score.current;
+    turn.level = level.0;
+    turn.eaten = tile_query
+        .iter()
+        .filter(|(_, tile)| {
+            matches!(
+                tile,
+                MapType::Open(OpenContent::Food | OpenContent::GhostEater)
+            )
+        })
+        .map(|(pos, _)| pos.0.clone())
+        .collect();
+    players.current = switch.0;
+}
+
+/// Takes the pellets the player at the controls had eaten out of their
+/// freshly spawned maze.
+pub fn restore_pellets(
+    mut commands: Commands,
+    players: Res<Players>,
+    sprite_sheets: Res<SpriteSheets>,
+    mut pellet_query: Query<(Entity, &Position, &mut TextureAtlasSprite), With<Pellet>>,
+) {
+    let eaten = &players.turns[players.current].eaten;
+    for (entity, pos, mut sprite) in &mut pellet_query {
+        if eaten.contains(&pos.0) {
+            sprite.index = sprite_sheets.manifest.tiles.empty;
+            commands.entity(entity).remove::<Pellet>();
+        }
+    }
+    commands.remove_resource::<SwitchPlayer>();
+}
+
+/// Back to the attract loop once "GAME  OVER" has been up long enough, or
+/// to the editor after a play-test.
+pub fn end_game(
+    time: Res<Time>,
+    mut game_over: ResMut<GameOver>,
+    play_test: Option<Res<PlayTest>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    game_over.tick(time.delta());
+    if game_over.just_finished() {
+        next_state.set(if play_test.is_some() {
+            GameState::Editor
+        } else {
+            GameState::Attract
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PELLETS: [TilePos; 3] = [
+        TilePos { x: 1, y: 1 },
+        TilePos { x: 2, y: 1 },
+        TilePos { x: 3, y: 1 },
+    ];
+
+    fn run<M>(world: &mut World, systems: impl IntoSystemConfigs<M>) {
+        let mut schedule = Schedule::new();
+        schedule.add_systems(systems);
+        schedule.run(world);
+    }
+
+    /// Lays the maze's pellets out afresh, as when a level loads.
+    fn spawn_pellets(world: &mut World) {
+        let tiles = world
+            .query_filtered::<Entity, With<MapType>>()
+            .iter(world)
+            .collect::<Vec<_>>();
+        for entity in tiles {
+            world.despawn(entity);
+        }
+        for pos in PELLETS {
+            world.spawn((
+                Position(pos),
+                MapType::Open(OpenContent::Food),
+                Pellet::default(),
+                TextureAtlasSprite::default(),
+            ));
+        }
+    }
+
+    fn eat(world: &mut World, pellet: &TilePos, points: u32) {
+        let entity = world
+            .query::<(Entity, &Position)>()
+            .iter(world)
+            .find(|(_, pos)| pos.0 == *pellet)
+            .map(|(entity, _)| entity)
+            .unwrap();
+        world.entity_mut(entity).remove::<Pellet>();
+        world.resource_mut::<Score>().current += points;
+    }
+
+    /// The player at the controls gets caught, the next one taking over in
+    /// their own maze.
+    fn switch_players(world: &mut World) {
+        let NextTurn::Switch(player) = world.resource_mut::<Players>().lose_life() else {
+            panic!("the players don't swap");
+        };
+        world.insert_resource(SwitchPlayer(player));
+        run(world, save_turn);
+        spawn_pellets(world);
+        let score = world.resource::<Players>().turns[player].score;
+        world.resource_mut::<Score>().current = score;
+        run(world, restore_pellets);
+    }
+
+    fn pellets_left(world: &mut World) -> Vec<TilePos> {
+        let mut left = world
+            .query_filtered::<&Position, With<Pellet>>()
+            .iter(world)
+            .map(|pos| pos.0.clone())
+            .collect::<Vec<_>>();
+        left.sort_by_key(|pos| pos.x);
+        left
+    }
+
+    #[test]
+    fn players_take_turns_in_their_own_maze() {
+        let config = GameConfig {
+            lives: 2,
+            ..default()
+        };
+        let mut world = World::new();
+        world.insert_resource(Players::new(2, &config));
+        world.init_resource::<Score>();
+        world.insert_resource(Level(1));
+        world.init_resource::<SpriteSheets>();
+        spawn_pellets(&mut world);
+
+        eat(&mut world, &PELLETS[0], 10);
+        switch_players(&mut world);
+        assert_eq!(world.resource::<Players>().current(), 1);
+        assert!(!world.contains_resource::<SwitchPlayer>());
+        assert_eq!(world.resource::<Score>().current, 0);
+        assert_eq!(pellets_left(&mut world), PELLETS);
+
+        eat(&mut world, &PELLETS[1], 10);
+        eat(&mut world, &PELLETS[2], 50);
+        switch_players(&mut world);
+        assert_eq!(world.resource::<Players>().current(), 0);
+        assert_eq!(world.resource::<Score>().current, 10);
+        assert_eq!(pellets_left(&mut world), PELLETS[1..]);
+
+        let players = world.resource::<Players>();
+        let second = players.turn(1).unwrap();
+        assert_eq!(second.score, 60);
+        assert_eq!(second.eaten.len(), 2);
+        assert_eq!(players.lives(), 1);
+
+        // The first player loses their last life, then the second one does.
+        switch_players(&mut world);
+        assert_eq!(world.resource::<Score>().current, 60);
+        assert_eq!(pellets_left(&mut world), PELLETS[..1]);
+        let mut players = world.resource_mut::<Players>();
+        assert_eq!(players.lose_life(), NextTurn::GameOver);
+        assert_eq!(players.turn(0).unwrap().lives, 0);
+    }
+}
@@ -0,0 +1,108 @@
+//! Quick-saving: F6 writes the game being played to a file, as a scene of
+//! its [Snapshot], and F9 picks it up again from there, from the attract
+//! screens too. Starting with `--resume` does the same right away. The file
+//! is `pacman-save.ron` unless another is given with `--save-file FILE`, see
+//! [GameConfig].
+
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy::scene::serde::SceneDeserializer;
+use serde::de::DeserializeSeed;
+
+use crate::config::GameConfig;
+use crate::game_state::GameState;
+use crate::net::NetLink;
+use crate::snapshot::Snapshot;
+
+/// Where games get saved.
+#[derive(Resource)]
+pub struct SaveFile(pub PathBuf);
+
+impl SaveFile {
+    fn write(&self, world: &mut World) {
+        let snapshot = Snapshot::take(world);
+        let registry = world.resource::<AppTypeRegistry>();
+        let text = match snapshot.to_scene().serialize_ron(registry) {
+            Ok(text) => text,
+            Err(err) => {
+                eprintln!("Could not save the game: {err}");
+                return;
+            }
+        };
+        if let Err(err) = std::fs::write(&self.0, text) {
+            eprintln!("Could not write {}: {err}", self.0.display());
+        }
+    }
+
+    fn read(&self, world: &World) -> Option<Snapshot> {
+        let text = std::fs::read_to_string(&self.0)
+            .map_err(|err| eprintln!("Could not read {}: {err}", self.0.display()))
+            .ok()?;
+        let registry = world.resource::<AppTypeRegistry>().read();
+        let scene = ron::Deserializer::from_str(&text)
+            .map_err(ron::Error::from)
+            .and_then(|mut deserializer| {
+                SceneDeserializer {
+                    type_registry: &registry,
+                }
+                .deserialize(&mut deserializer)
+            })
+            .map_err(|err| eprintln!("Could not read {}: {err}", self.0.display()))
+            .ok()?;
+        let snapshot = Snapshot::from_scene(scene);
+        if snapshot.is_none() {
+            eprintln!("{} isn't a saved game", self.0.display());
+        }
+        snapshot
+    }
+}
+
+/// A saved game to resume as soon as a level is up for it to replace.
+#[derive(Resource)]
+pub struct ResumeGame(Snapshot);
+
+/// Starts a game and resumes it from the save file.
+fn resume(world: &mut World) {
+    let Some(snapshot) = world.resource::<SaveFile>().read(world) else {
+        return;
+    };
+    world.insert_resource(ResumeGame(snapshot));
+    world
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Playing);
+}
+
+/// Resumes the saved game when started with `--resume`.
+pub fn resume_from_config(world: &mut World) {
+    if world.resource::<GameConfig>().resume {
+        resume(world);
+    }
+}
+
+/// F6 saves the game being played, F9 resumes the saved one. Networked
+/// games can only be played on, the other machine knowing nothing of saves.
+pub fn quick_save_keys(world: &mut World) {
+    if world.contains_resource::<NetLink>() {
+        return;
+    }
+    let keys = world.resource::<Input<KeyCode>>();
+    let (save, load) = (
+        keys.just_pressed(KeyCode::F6),
+        keys.just_pressed(KeyCode::F9),
+    );
+    let state = *world.resource::<State<GameState>>().get();
+
+    if save && state == GameState::Playing {
+        world.resource_scope(|world, save_file: Mut<SaveFile>| save_file.write(world));
+    } else if load && state != GameState::Editor {
+        resume(world);
+    }
+}
+
+/// Puts the saved game in place of the level just started for it.
+pub fn resume_saved_game(world: &mut World) {
+    if let Some(ResumeGame(snapshot)) = world.remove_resource::<ResumeGame>() {
+        snapshot.restore(world);
+    }
+}
@@ -0,0 +1,323 @@
+//! A copy of a game in progress, taken and put back through reflection: the
+//! characters and other level entities, and the resources the gameplay
+//! systems keep their state in. The maze only needs its pellets noted, and
+//! whatever can be rebuilt, such as the HUD or the [NavGraph], is left out.
+
+use bevy::ecs::entity::EntityMap;
+use bevy::ecs::system::CommandQueue;
+use bevy::prelude::*;
+use bevy::scene::{DynamicEntity, SceneFilter};
+use bevy::sprite::Anchor;
+use bevy::time::Stopwatch;
+
+use crate::ai::blinky::Blinky;
+use crate::ai::profile::{AiDice, CasualReaction};
+use crate::ai::{FrightenedTimer, Ghost, GhostColor, GhostMode, GhostTarget};
+use crate::common::Direction;
+use crate::components::{
+    AnimationIndices, AnimationTimer, Level, LevelClear, LevelEntity, Movable, NormalAnimation,
+    Pellet, Player, Position, Score, SpriteSheets,
+};
+use crate::fruit::Fruit;
+use crate::hud::ReadyText;
+use crate::map::{Corner, Map, MapType, OpenContent, TilePos, WallType};
+use crate::mazes::MazePalette;
+use crate::movement::pixel::{PixelPosition, SpeedFrame};
+use crate::movement::{EatingPause, MoverKind, NextDirection};
+use crate::nav::use_nav_graph;
+use crate::party::{Controls, PartyMode, PlayerIndex, RequestedDirection};
+use crate::players::{GameOver, Players, SwitchPlayer, Turn};
+use crate::text::ArcadeText;
+
+/// Registers every component found on [LevelEntity]s and every resource a
+/// [Snapshot] holds. Components that aren't registered would be lost.
+/// Maze tiles are registered too, for a whole level to be reflected.
+pub fn register_snapshot_types(app: &mut App) {
+    app.register_type::<Player>()
+        .register_type::<Position>()
+        .register_type::<Movable>()
+        .register_type::<AnimationIndices>()
+        .register_type::<AnimationTimer>()
+        .register_type::<NormalAnimation>()
+        .register_type::<LevelEntity>()
+        .register_type::<Pellet>()
+        .register_type::<Fruit>()
+        .register_type::<MapType>()
+        .register_type::<Ghost>()
+        .register_type::<GhostTarget>()
+        .register_type::<GhostColor>()
+        .register_type::<GhostMode>()
+        .register_type::<Blinky>()
+        .register_type::<MoverKind>()
+        .register_type::<NextDirection>()
+        .register_type::<EatingPause>()
+        .register_type::<PixelPosition>()
+        .register_type::<PlayerIndex>()
+        .register_type::<Controls>()
+        .register_type::<RequestedDirection>()
+        .register_type::<ReadyText>()
+        .register_type::<ArcadeText>()
+        // Registered by the rendering plugins, which the terminal goes
+        // without.
+        .register_type::<Transform>()
+        .register_type::<GlobalTransform>()
+        .register_type::<Visibility>()
+        .register_type::<ComputedVisibility>()
+        .register_type::<TextureAtlasSprite>()
+        .register_type::<Handle<TextureAtlas>>();
+
+    // What the types above are made of, looked up when reading a saved
+    // game back.
+    app.register_type::<TilePos>()
+        .register_type::<Option<TilePos>>()
+        .register_type::<Vec<TilePos>>()
+        .register_type::<Direction>()
+        .register_type::<Option<Direction>>()
+        .register_type::<Vec<MapType>>()
+        .register_type::<WallType>()
+        .register_type::<OpenContent>()
+        .register_type::<Corner>()
+        .register_type::<Turn>()
+        .register_type::<Vec<Turn>>()
+        .register_type::<PartyMode>()
+        .register_type::<Vec<usize>>()
+        .register_type::<Timer>()
+        .register_type::<TimerMode>()
+        .register_type::<Stopwatch>()
+        .register_type::<Color>()
+        .register_type::<IVec2>()
+        .register_type::<Vec2>()
+        .register_type::<Option<Vec2>>()
+        .register_type::<Vec3>()
+        .register_type::<Quat>()
+        .register_type::<Anchor>();
+
+    app.register_type::<Score>()
+        .register_type::<Level>()
+        .register_type::<Players>()
+        .register_type::<FrightenedTimer>()
+        .register_type::<LevelClear>()
+        .register_type::<SwitchPlayer>()
+        .register_type::<GameOver>()
+        .register_type::<Map>()
+        .register_type::<EatenPellets>()
+        .register_type::<MazePalette>()
+        .register_type::<CasualReaction>()
+        .register_type::<AiDice>()
+        .register_type::<SpeedFrame>();
+}
+
+/// The resources a [Snapshot] holds, including those only around at times
+/// such as [LevelClear].
+fn snapshot_resources() -> SceneFilter {
+    let mut filter = SceneFilter::deny_all();
+    filter
+        .allow::<Score>()
+        .allow::<Level>()
+        .allow::<Players>()
+        .allow::<FrightenedTimer>()
+        .allow::<LevelClear>()
+        .allow::<SwitchPlayer>()
+        .allow::<GameOver>()
+        .allow::<MazePalette>()
+        .allow::<CasualReaction>()
+        .allow::<AiDice>()
+        .allow::<SpeedFrame>();
+    filter
+}
+
+/// The level's entities but for the maze tiles.
+fn level_entities(world: &World) -> Vec<Entity> {
+    world
+        .iter_entities()
+        .filter(|entity| entity.contains::<LevelEntity>() && !entity.contains::<MapType>())
+        .map(|entity| entity.id())
+        .collect()
+}
+
+/// The tiles whose pellet got eaten, only reflected as part of a
+/// [Snapshot::to_scene].
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+struct EatenPellets(Vec<TilePos>);
+
+/// The state of a game at some point, to go back to later.
+pub struct Snapshot {
+    scene: DynamicScene,
+    map: Map,
+    /// The tiles whose pellet got eaten.
+    eaten: Vec<TilePos>,
+}
+
+impl Snapshot {
+    pub fn take(world: &mut World) -> Self {
+        let mut builder = DynamicSceneBuilder::from_world(world);
+        // The glyphs of texts are drawn again from their [ArcadeText].
+        builder
+            .deny::<Parent>()
+            .deny::<Children>()
+            .with_resource_filter(snapshot_resources())
+            .extract_entities(level_entities(world).into_iter())
+            .extract_resources();
+        let scene = builder.build();
+
+        let eaten = world
+            .query_filtered::<(&Position, &MapType), Without<Pellet>>()
+            .iter(world)
+            .filter(|(_, tile)| Pellet::for_tile(tile).is_some())
+            .map(|(pos, _)| pos.0.clone())
+            .collect();
+        Self {
+            scene,
+            map: world.resource::<Map>().clone(),
+            eaten,
+        }
+    }
+
+    /// The whole snapshot as a scene, the maze being one of its resources,
+    /// for it to be saved. What only makes sense in the running game, its
+    /// sprite sheets and what the renderer works out, is left for
+    /// [Self::restore] to fill in.
+    pub fn to_scene(&self) -> DynamicScene {
+        let entities = self
+            .scene
+            .entities
+            .iter()
+            .map(|entity| DynamicEntity {
+                entity: entity.entity,
+                components: entity
+                    .components
+                    .iter()
+                    .filter(|component| {
+                        !component.represents::<Handle<TextureAtlas>>()
+                            && !component.represents::<ComputedVisibility>()
+                    })
+                    .map(|component| component.clone_value())
+                    .collect(),
+            })
+            .collect();
+        let mut resources = self
+            .scene
+            .resources
+            .iter()
+            .map(|resource| resource.clone_value())
+            .collect::<Vec<_>>();
+        resources.push(Box::new(self.map.clone()));
+        resources.push(Box::new(EatenPellets(self.eaten.clone())));
+        DynamicScene {
+            resources,
+            entities,
+        }
+    }
+
+    /// Reads back a scene made by [Self::to_scene], if it is one.
+    pub fn from_scene(mut scene: DynamicScene) -> Option<Self> {
+        let map = take_resource::<Map>(&mut scene)?;
+        let eaten = take_resource::<EatenPellets>(&mut scene)?;
+        Some(Self {
+            scene,
+            map,
+            eaten: eaten.0,
+        })
+    }
+
+    /// Puts the game back the way it was when the snapshot was taken. The
+    /// level's entities are all spawned again, the maze only if it changed.
+    pub fn restore(&self, world: &mut World) {
+        for entity in level_entities(world) {
+            world.entity_mut(entity).despawn_recursive();
+        }
+        // Resources are inserted afresh rather than updated, lists being
+        // only ever grown when reflected onto, and those the snapshot goes
+        // without must go.
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        for type_id in snapshot_resources().iter() {
+            if let Some(resource) = registry
+                .read()
+                .get(*type_id)
+                .and_then(|registration| registration.data::<ReflectResource>())
+            {
+                resource.remove(world);
+            }
+        }
+        self.scene
+            .write_to_world(world, &mut EntityMap::default())
+            .expect("Snapshot types are registered");
+        // Characters read back from a file get the sheet of this run.
+        let characters = world.resource::<SpriteSheets>().characters.clone();
+        let unsheeted = world
+            .query_filtered::<Entity, (With<TextureAtlasSprite>, Without<Handle<TextureAtlas>>)>()
+            .iter(world)
+            .collect::<Vec<_>>();
+        for entity in unsheeted {
+            world.entity_mut(entity).insert(characters.clone());
+        }
+        let unseen = world
+            .query_filtered::<Entity, (With<Visibility>, Without<ComputedVisibility>)>()
+            .iter(world)
+            .collect::<Vec<_>>();
+        for entity in unseen {
+            world
+                .entity_mut(entity)
+                .insert(ComputedVisibility::default());
+        }
+
+        if *world.resource::<Map>() != self.map {
+            let tiles = world
+                .query_filtered::<Entity, With<MapType>>()
+                .iter(world)
+                .collect::<Vec<_>>();
+            for entity in tiles {
+                world.entity_mut(entity).despawn_recursive();
+            }
+            let mut queue = CommandQueue::default();
+            let sprite_sheets = world.resource::<SpriteSheets>();
+            crate::spawn_map(
+                &mut Commands::new(&mut queue, world),
+                sprite_sheets,
+                &self.map,
+            );
+            queue.apply(world);
+            use_nav_graph(world, &self.map);
+            world.insert_resource(self.map.clone());
+        }
+
+        let mut commands = CommandQueue::default();
+        let manifest = world.resource::<SpriteSheets>().manifest.clone();
+        let mut tile_query = world.query::<(
+            Entity,
+            &Position,
+            &MapType,
+            &mut TextureAtlasSprite,
+            Option<&Pellet>,
+        )>();
+        for (entity, pos, tile, mut sprite, had_pellet) in tile_query.iter_mut(world) {
+            let Some(pellet) = Pellet::for_tile(tile) else {
+                continue;
+            };
+            let eaten = self.eaten.contains(&pos.0);
+            let has_pellet = had_pellet.is_some();
+            if eaten && has_pellet {
+                sprite.index = manifest.tiles.empty;
+                commands.push(move |world: &mut World| {
+                    world.entity_mut(entity).remove::<Pellet>();
+                });
+            } else if !eaten && !has_pellet {
+                sprite.index = manifest.tile_index(tile);
+                commands.push(move |world: &mut World| {
+                    world.entity_mut(entity).insert(pellet);
+                });
+            }
+        }
+        commands.apply(world);
+    }
+}
+
+/// Takes the resource [R] out of [scene].
+fn take_resource<R: Reflect + FromReflect>(scene: &mut DynamicScene) -> Option<R> {
+    let i = scene
+        .resources
+        .iter()
+        .position(|resource| resource.represents::<R>())?;
+    R::from_reflect(&*scene.resources.remove(i))
+}
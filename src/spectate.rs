@@ -0,0 +1,297 @@
+//! Lets other programs watch a live game, such as dashboards, stream
+//! overlays or bots, without linking to the game. Started with
+//! `--spectate [PORT]`, the game listens on that local TCP port and sends
+//! every client connecting to it the state of the game at every frame, a
+//! line of JSON each:
+//!
+//! ```json
+//! {"frame":812,"state":"playing","level":1,"current_player":0,"lives":2,
+//!  "scores":[1230],"high_score":4000,
+//!  "pacmen":[{"player":0,"x":13,"y":23,"direction":"left","progress":0.5}],
+//!  "ghosts":[{"name":"blinky","player":null,"x":12,"y":11,"direction":"up",
+//!             "progress":0.25,"mode":"chase"}],
+//!  "pellets":[{"x":1,"y":1,"power":false}]}
+//! ```
+//!
+//! Positions are in tiles, `progress` being how far, from 0 to 1, the
+//! character is on its way to the next tile in its direction. Pellets are
+//! only listed during a level.
+//!
+//! The first line sent also has the rows of the maze, drawn as in maze
+//! files (see [crate::map::layout]), and so does every line where the maze
+//! changes, such as at the start of a level:
+//!
+//! ```json
+//! {"frame":1,"state":"attract",...,"maze":["############################",
+//!  "#............##............#",...]}
+//! ```
+
+use std::io::{self, ErrorKind, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::Serialize;
+
+use crate::ai::blinky::Blinky;
+use crate::ai::{Ghost, GhostMode};
+use crate::common::Direction;
+use crate::components::{Level, Movable, Pellet, Player, Position, Score};
+use crate::game_state::GameState;
+use crate::map::{Map, MapType};
+use crate::party::PlayerIndex;
+use crate::players::Players;
+
+pub const DEFAULT_PORT: u16 = 7778;
+
+/// A client further behind than this is too slow to keep up and gets
+/// dropped.
+const MAX_BACKLOG_BYTES: usize = 1 << 20;
+
+/// A program watching the game, with the lines not yet sent to it.
+struct Spectator {
+    stream: TcpStream,
+    backlog: Vec<u8>,
+    /// Whether the spectator was sent the maze yet.
+    seen_maze: bool,
+}
+
+impl Spectator {
+    /// Sends as much of the backlog as the connection takes without
+    /// waiting, returning false once the spectator is gone.
+    fn flush(&mut self) -> bool {
+        while !self.backlog.is_empty() {
+            match self.stream.write(&self.backlog) {
+                Ok(0) => return false,
+                Ok(sent) => {
+                    self.backlog.drain(..sent);
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => return false,
+            }
+        }
+        self.backlog.len() <= MAX_BACKLOG_BYTES
+    }
+}
+
+/// The socket spectators connect to, on this machine only.
+#[derive(Resource)]
+pub struct SpectatorServer {
+    listener: TcpListener,
+    spectators: Vec<Spectator>,
+    frame: u64,
+}
+
+impl SpectatorServer {
+    pub fn open(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, port)))?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            spectators: Vec::new(),
+            frame: 0,
+        })
+    }
+
+    fn accept(&mut self) {
+        while let Ok((stream, _)) = self.listener.accept() {
+            if stream.set_nonblocking(true).is_ok() {
+                let _ = stream.set_nodelay(true);
+                self.spectators.push(Spectator {
+                    stream,
+                    backlog: Vec::new(),
+                    seen_maze: false,
+                });
+            }
+        }
+    }
+}
+
+/// What is going on in the game, as sent to the programs watching it.
+#[derive(Serialize)]
+pub struct GameView {
+    frame: u64,
+    state: &'static str,
+    level: u32,
+    current_player: usize,
+    lives: u32,
+    scores: Vec<u32>,
+    high_score: u32,
+    pacmen: Vec<PacmanView>,
+    ghosts: Vec<GhostView>,
+    pellets: Vec<PelletView>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    maze: Option<Vec<String>>,
+}
+
+#[derive(Serialize)]
+struct PacmanView {
+    player: usize,
+    x: i32,
+    y: i32,
+    direction: Direction,
+    progress: f32,
+}
+
+#[derive(Serialize)]
+struct GhostView {
+    name: &'static str,
+    /// The player driving the ghost, if not the computer.
+    player: Option<usize>,
+    x: i32,
+    y: i32,
+    direction: Direction,
+    progress: f32,
+    mode: GhostMode,
+}
+
+#[derive(Serialize)]
+struct PelletView {
+    x: i32,
+    y: i32,
+    power: bool,
+}
+
+fn state_name(state: &GameState) -> &'static str {
+    match state {
+        GameState::Attract => "attract",
+        GameState::Demo => "demo",
+        GameState::Playing => "playing",
+        GameState::Editor => "editor",
+    }
+}
+
+/// Looks at the game for the programs watching it, see [GameView].
+#[derive(SystemParam)]
+#[allow(clippy::type_complexity)]
+pub struct GameObserver<'w, 's> {
+    state: Res<'w, State<GameState>>,
+    level: Res<'w, Level>,
+    score: Res<'w, Score>,
+    players: Res<'w, Players>,
+    map: Res<'w, Map>,
+    pacman_query: Query<
+        'w,
+        's,
+        (
+            &'static Position,
+            &'static Movable,
+            Option<&'static PlayerIndex>,
+        ),
+        With<Player>,
+    >,
+    ghost_query: Query<
+        'w,
+        's,
+        (
+            &'static Position,
+            &'static Movable,
+            &'static GhostMode,
+            Option<&'static PlayerIndex>,
+            Option<&'static Blinky>,
+        ),
+        With<Ghost>,
+    >,
+    pellet_query: Query<'w, 's, (&'static Position, &'static Pellet), With<MapType>>,
+}
+
+impl GameObserver<'_, '_> {
+    /// The game at [frame], with the maze if [with_maze] or if it changed
+    /// since the last frame.
+    pub fn view(&self, frame: u64, with_maze: bool) -> GameView {
+        let players = &self.players;
+        let scores = (0..players.count())
+            .map(|player| {
+                if player == players.current() {
+                    self.score.current
+                } else {
+                    players.turn(player).map_or(0, |turn| turn.score)
+                }
+            })
+            .collect();
+        let pacmen = self
+            .pacman_query
+            .iter()
+            .map(|(position, movable, player)| PacmanView {
+                player: player.map_or(0, |player| player.0),
+                x: position.x,
+                y: position.y,
+                direction: movable.direction.clone(),
+                progress: movable.progress,
+            })
+            .collect();
+        let ghosts = self
+            .ghost_query
+            .iter()
+            .map(|(position, movable, mode, player, blinky)| GhostView {
+                name: if blinky.is_some() { "blinky" } else { "ghost" },
+                player: player.map(|player| player.0),
+                x: position.x,
+                y: position.y,
+                direction: movable.direction.clone(),
+                progress: movable.progress,
+                mode: mode.clone(),
+            })
+            .collect();
+        let mut pellets = self
+            .pellet_query
+            .iter()
+            .map(|(position, pellet)| PelletView {
+                x: position.x,
+                y: position.y,
+                power: pellet.power,
+            })
+            .collect::<Vec<_>>();
+        // Listed row by row, whatever the order of the entities.
+        pellets.sort_by_key(|pellet| (pellet.y, pellet.x));
+
+        GameView {
+            frame,
+            state: state_name(self.state.get()),
+            level: self.level.0,
+            current_player: players.current(),
+            lives: players.lives(),
+            scores,
+            high_score: self.score.high,
+            pacmen,
+            ghosts,
+            pellets,
+            maze: (with_maze || self.map.is_changed()).then(|| self.map.to_layout()),
+        }
+    }
+
+    /// The [GameView] as a line of JSON.
+    pub fn view_line(&self, frame: u64, with_maze: bool) -> Vec<u8> {
+        let mut line = serde_json::to_vec(&self.view(frame, with_maze)).unwrap_or_default();
+        line.push(b'\n');
+        line
+    }
+}
+
+/// Sends the state of the game to every spectator, taking in those who
+/// just connected.
+pub fn publish_game_state(mut server: ResMut<SpectatorServer>, observer: GameObserver) {
+    server.accept();
+    server.frame += 1;
+    if server.spectators.is_empty() {
+        return;
+    }
+
+    let line = observer.view_line(server.frame, false);
+    // Those who just connected get the maze along.
+    let maze_line = server
+        .spectators
+        .iter()
+        .any(|spectator| !spectator.seen_maze)
+        .then(|| observer.view_line(server.frame, true));
+    server.spectators.retain_mut(|spectator| {
+        let line = match &maze_line {
+            Some(maze_line) if !spectator.seen_maze => maze_line,
+            _ => &line,
+        };
+        spectator.backlog.extend_from_slice(line);
+        spectator.seen_maze = true;
+        spectator.flush()
+    });
+}
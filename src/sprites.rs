@@ -0,0 +1,404 @@
+//! What the game is drawn with: the sprite sheets, how they are cut into
+//! sprites, and which of those draw the characters, their animations and
+//! the maze. It is all read from `assets/pacman.sprites.ron`, which the
+//! window reloads as soon as it is saved, redrawing whatever is on screen.
+//! The copy built into the game is used until then, and in the terminal.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::utils::BoxedFuture;
+use serde::Deserialize;
+
+use crate::ai::{Ghost, GhostMode};
+use crate::components::{
+    AnimationIndices, AnimationTimer, LevelEntity, NormalAnimation, Pellet, Player, SpriteSheets,
+};
+use crate::fruit::Fruit;
+use crate::hud::LifeIcon;
+use crate::map::{MapType, OpenContent, WallType};
+use crate::text::ArcadeText;
+
+pub const SPRITE_MANIFEST_PATH: &str = "pacman.sprites.ron";
+const BUILT_IN_MANIFEST: &str = include_str!("../assets/pacman.sprites.ron");
+
+/// How a sheet is cut into sprites, see [TextureAtlas::from_grid].
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AtlasLayout {
+    /// The sheet, from the assets folder.
+    pub image: String,
+    pub tile_size: (f32, f32),
+    pub columns: usize,
+    pub rows: usize,
+    #[serde(default)]
+    pub padding: Option<(f32, f32)>,
+    #[serde(default)]
+    pub offset: Option<(f32, f32)>,
+}
+
+impl AtlasLayout {
+    pub fn atlas(&self, asset_server: &AssetServer) -> TextureAtlas {
+        TextureAtlas::from_grid(
+            asset_server.load(&self.image),
+            self.tile_size.into(),
+            self.columns,
+            self.rows,
+            self.padding.map(Vec2::from),
+            self.offset.map(Vec2::from),
+        )
+    }
+}
+
+/// The frames of an animation, for each way the character faces.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Animation {
+    pub right: Vec<usize>,
+    pub left: Vec<usize>,
+    pub up: Vec<usize>,
+    pub down: Vec<usize>,
+}
+
+impl Animation {
+    pub fn indices(&self) -> AnimationIndices {
+        AnimationIndices::new(
+            self.right.clone(),
+            self.left.clone(),
+            self.up.clone(),
+            self.down.clone(),
+        )
+    }
+
+    fn is_empty(&self) -> bool {
+        [&self.right, &self.left, &self.up, &self.down]
+            .iter()
+            .any(|frames| frames.is_empty())
+    }
+}
+
+/// The ghosts introduced by the attract mode, in the character sheet.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RollCall {
+    pub blinky: usize,
+    pub pinky: usize,
+    pub inky: usize,
+    pub clyde: usize,
+}
+
+impl RollCall {
+    /// The ghosts in the order they are introduced.
+    pub fn sprites(&self) -> [usize; 4] {
+        [self.blinky, self.pinky, self.inky, self.clyde]
+    }
+}
+
+/// The sprites of the maze tiles other than walls.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TileSprites {
+    pub empty: usize,
+    pub food: usize,
+    pub ghost_eater: usize,
+    pub door: usize,
+    /// How far down the sheet the white copy of the walls starts.
+    pub white_walls: usize,
+}
+
+#[derive(Debug)]
+pub enum ManifestError {
+    Syntax(ron::error::SpannedError),
+    /// An animation has a direction without frames.
+    EmptyAnimation(&'static str),
+    NoFruits,
+    /// There are more glyphs than the font sheet has sprites.
+    GlyphsOffSheet,
+    /// A wall has no sprite to draw it with.
+    MissingWall(WallType),
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ManifestError::Syntax(err) => write!(f, "{err}"),
+            ManifestError::EmptyAnimation(name) => {
+                write!(f, "the {name} animation is missing frames")
+            }
+            ManifestError::NoFruits => write!(f, "there are no fruits"),
+            ManifestError::GlyphsOffSheet => write!(f, "the glyphs don't fit the font sheet"),
+            ManifestError::MissingWall(wall) => write!(f, "there is no sprite for {wall:?} walls"),
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+/// The sprite sheets and which of their sprites draw what.
+#[derive(Clone, Debug, Deserialize, TypeUuid, TypePath)]
+#[uuid = "5b0d7f52-3a0e-4c51-9a53-6f1d2c8e94a7"]
+#[serde(deny_unknown_fields)]
+pub struct SpriteManifest {
+    pub characters: AtlasLayout,
+    pub maze: AtlasLayout,
+    pub font: AtlasLayout,
+    /// The characters drawn by the font sheet, a row of it each.
+    pub glyphs: Vec<String>,
+    pub pacman: Animation,
+    pub blinky: Animation,
+    pub frightened: Animation,
+    pub eaten: Animation,
+    pub pacman_frame_secs: f32,
+    pub ghost_frame_secs: f32,
+    pub life_icon: usize,
+    /// The bonus fruits in the order the levels bring them, see [Fruit].
+    pub fruits: Vec<usize>,
+    pub roll_call: RollCall,
+    pub tiles: TileSprites,
+    /// Every wall, see [WallType::all].
+    pub walls: HashMap<WallType, usize>,
+    /// The walls drawn with their sprite upside down.
+    pub upside_down_walls: Vec<WallType>,
+}
+
+impl Default for SpriteManifest {
+    fn default() -> Self {
+        Self::parse(BUILT_IN_MANIFEST.as_bytes()).expect("the built-in sprite manifest is valid")
+    }
+}
+
+impl SpriteManifest {
+    pub fn parse(bytes: &[u8]) -> Result<Self, ManifestError> {
+        let manifest: Self = ron::de::from_bytes(bytes).map_err(ManifestError::Syntax)?;
+        let animations = [
+            ("pacman", &manifest.pacman),
+            ("blinky", &manifest.blinky),
+            ("frightened", &manifest.frightened),
+            ("eaten", &manifest.eaten),
+        ];
+        if let Some((name, _)) = animations
+            .iter()
+            .find(|(_, animation)| animation.is_empty())
+        {
+            return Err(ManifestError::EmptyAnimation(name));
+        }
+        if manifest.fruits.is_empty() {
+            return Err(ManifestError::NoFruits);
+        }
+        let font = &manifest.font;
+        if manifest.glyphs.len() > font.rows
+            || manifest
+                .glyphs
+                .iter()
+                .any(|row| row.chars().count() > font.columns)
+        {
+            return Err(ManifestError::GlyphsOffSheet);
+        }
+        if let Some(wall) = WallType::all()
+            .into_iter()
+            .find(|wall| !manifest.walls.contains_key(wall))
+        {
+            return Err(ManifestError::MissingWall(wall));
+        }
+        Ok(manifest)
+    }
+
+    /// The animation of a ghost in [mode], [normal] being its own.
+    pub fn ghost_animation(&self, mode: &GhostMode, normal: &AnimationIndices) -> AnimationIndices {
+        match mode {
+            GhostMode::Chase => normal.clone(),
+            GhostMode::Frightened => self.frightened.indices(),
+            GhostMode::Eaten => self.eaten.indices(),
+        }
+    }
+
+    /// The sprite of the font sheet drawing [c], if there is one.
+    pub fn glyph_index(&self, c: char) -> Option<usize> {
+        let c = c.to_ascii_uppercase();
+        if c == ' ' {
+            return None;
+        }
+        self.glyphs.iter().enumerate().find_map(|(row, glyphs)| {
+            let column = glyphs.chars().position(|glyph| glyph == c)?;
+            Some(row * self.font.columns + column)
+        })
+    }
+
+    /// The sprite of the [Fruit] of that kind, the last one standing in for
+    /// the kinds past it.
+    pub fn fruit_sprite(&self, kind: usize) -> usize {
+        self.fruits[kind.min(self.fruits.len() - 1)]
+    }
+
+    pub fn tile_sprite(&self, map_type: &MapType) -> TextureAtlasSprite {
+        let wall_type = match map_type {
+            MapType::Wall(wall_type) => wall_type,
+            MapType::GhostOnlyBarrier => return TextureAtlasSprite::new(self.tiles.door),
+            MapType::Open(OpenContent::Food) => return TextureAtlasSprite::new(self.tiles.food),
+            MapType::Open(OpenContent::GhostEater) => {
+                return TextureAtlasSprite::new(self.tiles.ghost_eater)
+            }
+            MapType::Open(OpenContent::None) => return TextureAtlasSprite::new(self.tiles.empty),
+        };
+
+        TextureAtlasSprite {
+            index: self.walls[wall_type] + self.tiles.white_walls,
+            flip_y: self.upside_down_walls.contains(wall_type),
+            ..default()
+        }
+    }
+
+    pub fn tile_index(&self, map_type: &MapType) -> usize {
+        self.tile_sprite(map_type).index
+    }
+}
+
+#[derive(Default)]
+pub struct SpriteManifestLoader;
+
+impl AssetLoader for SpriteManifestLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let manifest = SpriteManifest::parse(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(manifest));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["sprites.ron"]
+    }
+}
+
+/// The manifest file, kept loaded for its changes to be picked up.
+#[derive(Resource)]
+pub struct SpriteManifestHandle(pub Handle<SpriteManifest>);
+
+/// Cuts the sheets again whenever the manifest file is loaded or saved.
+pub fn reload_sprite_manifest(
+    mut events: EventReader<AssetEvent<SpriteManifest>>,
+    handle: Res<SpriteManifestHandle>,
+    manifests: Res<Assets<SpriteManifest>>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut sprite_sheets: ResMut<SpriteSheets>,
+) {
+    let reloaded = events.iter().any(|event| match event {
+        AssetEvent::Created { handle: loaded } | AssetEvent::Modified { handle: loaded } => {
+            *loaded == handle.0
+        }
+        AssetEvent::Removed { .. } => false,
+    });
+    let Some(manifest) = manifests.get(&handle.0).filter(|_| reloaded) else {
+        return;
+    };
+
+    let sheets = [
+        (sprite_sheets.characters.id(), &manifest.characters),
+        (sprite_sheets.maze.id(), &manifest.maze),
+        (sprite_sheets.font.id(), &manifest.font),
+    ];
+    for (id, layout) in sheets {
+        texture_atlases.set_untracked(id, layout.atlas(&asset_server));
+    }
+    sprite_sheets.manifest = manifest.clone();
+}
+
+/// Redraws what is on screen with the sprites of a manifest just loaded.
+#[allow(clippy::type_complexity)]
+pub fn apply_sprite_manifest(
+    sprite_sheets: Res<SpriteSheets>,
+    mut tile_query: Query<(
+        &MapType,
+        Option<&Pellet>,
+        Option<&LevelEntity>,
+        &mut TextureAtlasSprite,
+    )>,
+    mut pacman_query: Query<(&mut AnimationIndices, &mut AnimationTimer), With<Player>>,
+    mut ghost_query: Query<
+        (
+            &GhostMode,
+            &mut NormalAnimation,
+            &mut AnimationIndices,
+            &mut AnimationTimer,
+        ),
+        (With<Ghost>, Without<Player>),
+    >,
+    mut icon_query: Query<&mut TextureAtlasSprite, (With<LifeIcon>, Without<MapType>)>,
+    mut fruit_query: Query<
+        (&Fruit, &mut TextureAtlasSprite),
+        (Without<LifeIcon>, Without<MapType>),
+    >,
+    mut text_query: Query<&mut ArcadeText>,
+) {
+    let manifest = &sprite_sheets.manifest;
+    for (tile, pellet, in_level, mut sprite) in &mut tile_query {
+        // Tiles keep the colours of their maze.
+        *sprite = TextureAtlasSprite {
+            color: sprite.color,
+            ..manifest.tile_sprite(tile)
+        };
+        let eaten = matches!(tile, MapType::Open(_)) && pellet.is_none();
+        if eaten && in_level.is_some() {
+            sprite.index = manifest.tiles.empty;
+        }
+    }
+    for (mut indices, mut timer) in &mut pacman_query {
+        *indices = manifest.pacman.indices();
+        timer.set_duration(Duration::from_secs_f32(manifest.pacman_frame_secs));
+    }
+    for (mode, mut normal, mut indices, mut timer) in &mut ghost_query {
+        normal.0 = manifest.blinky.indices();
+        *indices = manifest.ghost_animation(mode, &normal.0);
+        timer.set_duration(Duration::from_secs_f32(manifest.ghost_frame_secs));
+    }
+    for mut sprite in &mut icon_query {
+        sprite.index = manifest.life_icon;
+    }
+    for (fruit, mut sprite) in &mut fruit_query {
+        sprite.index = manifest.fruit_sprite(fruit.kind);
+    }
+    for mut text in &mut text_query {
+        text.set_changed();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_built_in_manifest_draws_every_wall() {
+        let manifest = SpriteManifest::parse(BUILT_IN_MANIFEST.as_bytes()).unwrap();
+        assert_eq!(manifest.walls.len(), WallType::all().len());
+    }
+
+    #[test]
+    fn the_built_in_glyphs_match_the_font_sheet() {
+        let manifest = SpriteManifest::parse(BUILT_IN_MANIFEST.as_bytes()).unwrap();
+        let glyphs = "09/-.\"©!AMNZz ?"
+            .chars()
+            .map(|c| manifest.glyph_index(c))
+            .collect::<Vec<_>>();
+        let expected = [0, 9, 26, 27, 31, 42, 43, 44, 48, 60, 64, 76, 76];
+        assert_eq!(glyphs[..13], expected.map(Some));
+        assert_eq!(glyphs[13..], [None, None]);
+    }
+
+    #[test]
+    fn refuses_a_manifest_missing_a_wall() {
+        let manifest = BUILT_IN_MANIFEST.replace("        Inner: 44,\n", "");
+        assert!(matches!(
+            SpriteManifest::parse(manifest.as_bytes()),
+            Err(ManifestError::MissingWall(WallType::Inner))
+        ));
+    }
+}
@@ -0,0 +1,353 @@
+//! Plays the game in a terminal, for when there is no window to open such as
+//! over SSH: the maze, pellets and characters are drawn with ANSI colours and
+//! the keys are read from the tty, while the gameplay systems run unchanged.
+
+use std::io::{self, Write};
+use std::time::Duration;
+
+use bevy::{app::AppExit, prelude::*, window::ReceivedCharacter};
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyEventKind, KeyModifiers},
+    execute, queue,
+    terminal::{self, ClearType},
+};
+
+use crate::ai::{Ghost, GhostColor, GhostMode};
+use crate::components::{Level, Pellet, Player, Position};
+use crate::console::Console;
+use crate::hud::LifeIcon;
+use crate::map::{Map, MapType, TilePos, TILE_SIZE};
+use crate::mazes::MazePalette;
+use crate::party::PlayerIndex;
+use crate::text::ArcadeText;
+
+/// Rows above the maze, where the arcade shows the scores.
+const HUD_ROWS: i32 = 3;
+/// Rows below the maze, for the lives left and a line of help.
+const FOOTER_ROWS: i32 = 3;
+/// Terminal characters are about twice as tall as they are wide, so every
+/// tile is drawn two columns wide.
+const COLUMNS_PER_TILE: usize = 2;
+
+const PACMAN_COLOR: Color = Color::YELLOW;
+const SECOND_PACMAN_COLOR: Color = Color::rgb(0.6, 1.0, 1.0);
+const FRIGHTENED_COLOR: Color = Color::rgb(0.13, 0.13, 1.0);
+const HINT_COLOR: Color = Color::GRAY;
+
+/// Puts the terminal back the way it was found when the game exits.
+#[derive(Resource)]
+pub struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(
+            io::stdout(),
+            terminal::EnterAlternateScreen,
+            cursor::Hide,
+            terminal::Clear(ClearType::All)
+        )?;
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// The lines currently on the terminal, so that only those that change get
+/// drawn again.
+#[derive(Resource, Default)]
+pub struct TerminalScreen {
+    lines: Vec<String>,
+}
+
+pub fn enter_terminal(mut commands: Commands, mut exit: EventWriter<AppExit>) {
+    match TerminalGuard::enter() {
+        Ok(guard) => commands.insert_resource(guard),
+        Err(err) => {
+            eprintln!("Can't use the terminal: {err}");
+            exit.send(AppExit);
+        }
+    }
+}
+
+fn key_code(code: event::KeyCode) -> Option<KeyCode> {
+    match code {
+        event::KeyCode::Up => Some(KeyCode::Up),
+        event::KeyCode::Left => Some(KeyCode::Left),
+        event::KeyCode::Down => Some(KeyCode::Down),
+        event::KeyCode::Right => Some(KeyCode::Right),
+        event::KeyCode::Enter => Some(KeyCode::Return),
+        event::KeyCode::Esc => Some(KeyCode::Escape),
+        event::KeyCode::Backspace => Some(KeyCode::Back),
+        event::KeyCode::F(6) => Some(KeyCode::F6),
+        event::KeyCode::F(9) => Some(KeyCode::F9),
+        event::KeyCode::Char(c) => match c.to_ascii_lowercase() {
+            'w' => Some(KeyCode::W),
+            'a' => Some(KeyCode::A),
+            's' => Some(KeyCode::S),
+            'd' => Some(KeyCode::D),
+            ' ' => Some(KeyCode::Space),
+            '`' => Some(KeyCode::Grave),
+            '1' => Some(KeyCode::Key1),
+            '2' => Some(KeyCode::Key2),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Feeds the keys typed in the terminal to [Input<KeyCode>], and the
+/// characters to the [Console]. Q or Ctrl+C quits, but for Q in the console.
+pub fn read_terminal_input(
+    mut keys: ResMut<Input<KeyCode>>,
+    mut screen: ResMut<TerminalScreen>,
+    console: Res<Console>,
+    mut characters: EventWriter<ReceivedCharacter>,
+    mut exit: EventWriter<AppExit>,
+    mut held: Local<Vec<KeyCode>>,
+) {
+    // Terminals only report key presses, each one is held for a frame.
+    for key in held.drain(..) {
+        keys.release(key);
+    }
+
+    while event::poll(Duration::ZERO).unwrap_or(false) {
+        let key = match event::read() {
+            Ok(Event::Key(key)) if key.kind != KeyEventKind::Release => key,
+            Ok(Event::Resize(..)) => {
+                screen.lines.clear();
+                let _ = execute!(io::stdout(), terminal::Clear(ClearType::All));
+                continue;
+            }
+            Ok(_) => continue,
+            Err(_) => break,
+        };
+
+        let ctrl_c =
+            key.modifiers.contains(KeyModifiers::CONTROL) && key.code == event::KeyCode::Char('c');
+        let quit = key.code == event::KeyCode::Char('q') && !console.is_open();
+        if ctrl_c || quit {
+            exit.send(AppExit);
+            continue;
+        }
+        let typed = match key.code {
+            event::KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => Some(c),
+            _ => None,
+        };
+        if let Some(c) = typed {
+            characters.send(ReceivedCharacter {
+                window: Entity::PLACEHOLDER,
+                char: c,
+            });
+        }
+        if let Some(code) = key_code(key.code) {
+            keys.press(code);
+            held.push(code);
+        }
+    }
+}
+
+/// What the terminal shows, a character and its colour per column.
+struct Frame {
+    cells: Vec<Vec<(char, Color)>>,
+}
+
+impl Frame {
+    fn new(map: &Map) -> Self {
+        let width = map.width() * COLUMNS_PER_TILE;
+        let height = (HUD_ROWS + map.height() as i32 + FOOTER_ROWS) as usize;
+        Self {
+            cells: vec![vec![(' ', Color::BLACK); width]; height],
+        }
+    }
+
+    /// Puts [c] at [column] of the tile row [y], rows above the maze being
+    /// negative.
+    fn put(&mut self, column: i32, y: i32, c: char, color: Color) {
+        let row = y + HUD_ROWS;
+        if row < 0 || column < 0 {
+            return;
+        }
+        if let Some(cell) = self
+            .cells
+            .get_mut(row as usize)
+            .and_then(|line| line.get_mut(column as usize))
+        {
+            *cell = (c, color);
+        }
+    }
+
+    fn put_tile(&mut self, pos: &TilePos, c: char, color: Color) {
+        self.put(pos.x * COLUMNS_PER_TILE as i32, pos.y, c, color);
+    }
+
+    /// Writes [text] centred over the tiles it covers in the arcade.
+    fn put_text(&mut self, pos: &TilePos, text: &str, color: Color) {
+        let length = text.chars().count() as i32;
+        let start = pos.x * COLUMNS_PER_TILE as i32 + length / 2;
+        for (i, c) in text.chars().enumerate() {
+            self.put(start + i as i32, pos.y, c, color);
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        self.cells
+            .iter()
+            .map(|cells| {
+                let mut line = String::new();
+                let mut current = None;
+                for (c, color) in cells {
+                    if *c != ' ' && current != Some(*color) {
+                        let [r, g, b, _] = color.as_rgba_u8();
+                        line.push_str(&format!("\x1b[38;2;{r};{g};{b}m"));
+                        current = Some(*color);
+                    }
+                    line.push(*c);
+                }
+                line.push_str("\x1b[0m");
+                line
+            })
+            .collect()
+    }
+}
+
+/// Walls are outlined, as in the arcade: a line runs along every wall tile
+/// next to a corridor, joining the neighbouring ones along the same
+/// corridor.
+fn wall_glyphs(map: &Map, pos: &TilePos) -> [char; 2] {
+    let solid = |x: i32, y: i32| {
+        let in_bounds = x >= 0 && y >= 0 && x < map.width() as i32 && y < map.height() as i32;
+        !in_bounds || !matches!(map.get_at(&TilePos { x, y }), MapType::Open(_))
+    };
+    let open = |x: i32, y: i32| !solid(x, y);
+    let outlined =
+        |x: i32, y: i32| solid(x, y) && (-1..=1).any(|dy| (-1..=1).any(|dx| open(x + dx, y + dy)));
+    let (x, y) = (pos.x, pos.y);
+    if !outlined(x, y) {
+        return [' ', ' '];
+    }
+
+    let across = |x: i32| {
+        outlined(x, y)
+            && outlined(x + 1, y)
+            && (open(x, y - 1) || open(x + 1, y - 1) || open(x, y + 1) || open(x + 1, y + 1))
+    };
+    let down = |y: i32| {
+        outlined(x, y)
+            && outlined(x, y + 1)
+            && (open(x - 1, y) || open(x - 1, y + 1) || open(x + 1, y) || open(x + 1, y + 1))
+    };
+    let (up, right, down, left) = (down(y - 1), across(x), down(y), across(x - 1));
+
+    let glyph = match (up, right, down, left) {
+        (true, true, true, true) => '┼',
+        (false, true, true, true) => '┬',
+        (true, false, true, true) => '┤',
+        (true, true, false, true) => '┴',
+        (true, true, true, false) => '├',
+        (false, true, true, false) => '╭',
+        (false, false, true, true) => '╮',
+        (true, true, false, false) => '╰',
+        (true, false, false, true) => '╯',
+        (true, false, true, false) | (true, false, false, false) | (false, false, true, false) => {
+            '│'
+        }
+        _ => '─',
+    };
+    [glyph, if right { '─' } else { ' ' }]
+}
+
+/// Draws the maze, the characters and the texts, rewriting the lines that
+/// changed since the last frame.
+#[allow(clippy::type_complexity)]
+pub fn draw_terminal(
+    map: Res<Map>,
+    palette: Res<MazePalette>,
+    level: Res<Level>,
+    tile_query: Query<(&Position, Option<&Pellet>), With<MapType>>,
+    text_query: Query<(&ArcadeText, &Transform)>,
+    character_query: Query<
+        (
+            &Transform,
+            Option<&GhostMode>,
+            Option<&GhostColor>,
+            Option<&PlayerIndex>,
+        ),
+        Or<(With<Player>, With<Ghost>, With<LifeIcon>)>,
+    >,
+    mut screen: ResMut<TerminalScreen>,
+) {
+    let mut frame = Frame::new(&map);
+    let tile_at = |transform: &Transform| TilePos {
+        x: (transform.translation.x / TILE_SIZE as f32).round() as i32,
+        y: (-transform.translation.y / TILE_SIZE as f32).round() as i32,
+    };
+
+    // The maze is only up during a level, as in the window.
+    if !tile_query.is_empty() {
+        for y in 0..map.height() as i32 {
+            for x in 0..map.width() as i32 {
+                let pos = TilePos { x, y };
+                let color = match map.get_at(&pos) {
+                    MapType::Open(_) => continue,
+                    MapType::GhostOnlyBarrier => palette.door,
+                    MapType::Wall(_) => palette.walls,
+                };
+                let [first, second] = wall_glyphs(&map, &pos);
+                frame.put_tile(&pos, first, color);
+                frame.put(x * COLUMNS_PER_TILE as i32 + 1, y, second, color);
+            }
+        }
+    }
+
+    for (pos, pellet) in tile_query.iter() {
+        let Some(pellet) = pellet else {
+            continue;
+        };
+        let glyph = if pellet.power { '●' } else { '·' };
+        frame.put_tile(pos, glyph, palette.pellets);
+    }
+
+    // Pac-Man first, so that the ghosts show on top of him.
+    let mut characters = character_query.iter().collect::<Vec<_>>();
+    characters.sort_by_key(|(_, mode, _, _)| mode.is_some());
+    for (transform, mode, color, player) in characters {
+        let (glyph, color) = match (mode, color) {
+            (Some(GhostMode::Eaten), _) => ('"', Color::WHITE),
+            (Some(GhostMode::Frightened), _) => ('M', FRIGHTENED_COLOR),
+            (Some(_), Some(GhostColor(color))) => ('M', *color),
+            (Some(_), None) => ('M', Color::WHITE),
+            (None, _) if player.is_some_and(|player| player.0 > 0) => ('@', SECOND_PACMAN_COLOR),
+            (None, _) => ('@', PACMAN_COLOR),
+        };
+        frame.put_tile(&tile_at(transform), glyph, color);
+    }
+
+    // Texts go over the characters, as in the window.
+    for (text, transform) in text_query.iter() {
+        frame.put_text(&tile_at(transform), &text.text, text.color);
+    }
+
+    let footer = map.height() as i32 + FOOTER_ROWS - 1;
+    let status = format!("LEVEL {}  ARROWS/WASD MOVE  1/2 PLAYERS  Q QUIT", level.0);
+    for (i, c) in status.chars().enumerate() {
+        frame.put(2 + i as i32, footer, c, HINT_COLOR);
+    }
+
+    let lines = frame.lines();
+    let mut stdout = io::stdout().lock();
+    for (row, line) in lines.iter().enumerate() {
+        if screen.lines.get(row) != Some(line) {
+            let _ = queue!(stdout, cursor::MoveTo(0, row as u16));
+            let _ = stdout.write_all(line.as_bytes());
+        }
+    }
+    let _ = stdout.flush();
+    screen.lines = lines;
+}
@@ -0,0 +1,66 @@
+use bevy::prelude::*;
+
+use crate::components::SpriteSheets;
+use crate::map::{TilePos, TILE_SIZE};
+
+/// A line of text drawn with the arcade font, anchored at the tile of its
+/// first character. Changing the text or colour redraws the glyphs.
+#[derive(Component, Clone, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct ArcadeText {
+    pub text: String,
+    pub color: Color,
+}
+
+#[derive(Bundle)]
+pub struct ArcadeTextBundle {
+    pub text: ArcadeText,
+    pub spatial: SpatialBundle,
+}
+
+impl ArcadeTextBundle {
+    pub fn new(text: impl Into<String>, tile_pos: TilePos, color: Color) -> Self {
+        let pos = tile_pos.to_display_pos();
+        Self {
+            text: ArcadeText {
+                text: text.into(),
+                color,
+            },
+            spatial: SpatialBundle::from_transform(Transform::from_translation(Vec3::new(
+                pos.x, -pos.y, 1.0,
+            ))),
+        }
+    }
+}
+
+pub fn render_text(
+    mut commands: Commands,
+    sprite_sheets: Res<SpriteSheets>,
+    query: Query<(Entity, &ArcadeText), Changed<ArcadeText>>,
+) {
+    for (entity, text) in query.iter() {
+        commands
+            .entity(entity)
+            .despawn_descendants()
+            .with_children(|parent| {
+                for (i, c) in text.text.chars().enumerate() {
+                    let Some(index) = sprite_sheets.manifest.glyph_index(c) else {
+                        continue;
+                    };
+
+                    let mut sprite = TextureAtlasSprite::new(index);
+                    sprite.color = text.color;
+                    parent.spawn(SpriteSheetBundle {
+                        texture_atlas: sprite_sheets.font.clone(),
+                        sprite,
+                        transform: Transform::from_translation(Vec3::new(
+                            (i as i32 * TILE_SIZE) as f32,
+                            0.0,
+                            0.0,
+                        )),
+                        ..default()
+                    });
+                }
+            });
+    }
+}
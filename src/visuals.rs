@@ -1,9 +1,15 @@
 use bevy::prelude::*;
 
+use crate::ai::GhostMode;
 use crate::common::Direction;
-use crate::map::{Corner, WallType};
+use crate::map::{Map, MapType, TILE_SIZE};
+use crate::mazes::MazePalette;
+use crate::movement::{pixel::PixelPosition, MovementModel};
 use crate::{
-    components::{AnimationIndices, AnimationTimer, Movable, Position},
+    components::{
+        AnimationIndices, AnimationTimer, LevelClear, Movable, NormalAnimation, Position,
+        SpriteSheets,
+    },
     math,
 };
 
@@ -37,9 +43,33 @@ pub fn animate_sprite(
     }
 }
 
-pub fn get_display_pos(pos: &Position, movable: &Movable) -> Vec2 {
+pub fn ghost_mode_visuals(
+    sprite_sheets: Res<SpriteSheets>,
+    mut query: Query<(&GhostMode, &NormalAnimation, &mut AnimationIndices), Changed<GhostMode>>,
+) {
+    for (mode, normal, mut indices) in &mut query {
+        *indices = sprite_sheets.manifest.ghost_animation(mode, &normal.0);
+    }
+}
+
+pub fn get_display_pos(map: &Map, pos: &Position, movable: &Movable) -> Vec2 {
     let pos_a = pos.to_display_pos();
-    let pos_b = movable.target_tile.to_display_pos();
+    let mut pos_b = movable.target_tile.to_display_pos();
+
+    // Going through a tunnel, slide off the edge of the maze rather than
+    // across it.
+    let maze_size = map.pixel_size().as_vec2();
+    let tile = TILE_SIZE as f32;
+    if pos_b.x - pos_a.x > tile {
+        pos_b.x -= maze_size.x;
+    } else if pos_a.x - pos_b.x > tile {
+        pos_b.x += maze_size.x;
+    }
+    if pos_b.y - pos_a.y > tile {
+        pos_b.y -= maze_size.y;
+    } else if pos_a.y - pos_b.y > tile {
+        pos_b.y += maze_size.y;
+    }
 
     Vec2::new(
         math::lerp(pos_a.x, pos_b.x, movable.progress),
@@ -47,45 +77,64 @@ pub fn get_display_pos(pos: &Position, movable: &Movable) -> Vec2 {
     )
 }
 
-pub fn draw_movable(mut query: Query<(&mut Transform, &Position, &Movable)>) {
-    for (mut sprite, pos, movable) in &mut query {
-        let p = get_display_pos(pos, movable);
+pub fn draw_movable(
+    model: Res<MovementModel>,
+    map: Res<Map>,
+    mut query: Query<(&mut Transform, &Position, &Movable, Option<&PixelPosition>)>,
+) {
+    for (mut sprite, pos, movable, pixel) in &mut query {
+        let p = match (*model, pixel) {
+            (MovementModel::Pixels, Some(pixel)) => pixel.0.as_vec2(),
+            _ => get_display_pos(&map, pos, movable),
+        };
         sprite.translation = Vec3::new(p.x, -p.y, sprite.translation.z);
     }
 }
 
-pub fn sprite_index_for_wall_type(wall_type: &WallType) -> usize {
-    match wall_type {
-        WallType::Straight(Direction::Up) => 20,
-        WallType::Straight(Direction::Right) => 25,
-        WallType::Straight(Direction::Down) => 14,
-        WallType::Straight(Direction::Left) => 24,
-        WallType::DoubleStraight(Direction::Up) => 10,
-        WallType::DoubleStraight(Direction::Right) => 2,
-        WallType::DoubleStraight(Direction::Down) => 12,
-        WallType::DoubleStraight(Direction::Left) => 3,
-        WallType::DoubleCorner(Corner::TopRight) => 0,
-        WallType::DoubleCorner(Corner::BottomRight) => 4,
-        WallType::DoubleCorner(Corner::BottomLeft) => 5,
-        WallType::DoubleCorner(Corner::TopLeft) => 1,
-        WallType::VerticalLineInnerCorner(Corner::TopRight) => 8,
-        WallType::VerticalLineInnerCorner(Corner::BottomRight) => 6,
-        WallType::VerticalLineInnerCorner(Corner::BottomLeft) => 7,
-        WallType::VerticalLineInnerCorner(Corner::TopLeft) => 9,
-        WallType::HorizontalLineInnerCornerTopRight => 43,
-        WallType::HorizontalLineInnerCornerTopLeft => 42,
-        WallType::OuterCorner(Corner::TopRight) => 27,
-        WallType::OuterCorner(Corner::BottomRight) => 23,
-        WallType::OuterCorner(Corner::BottomLeft) => 22,
-        WallType::OuterCorner(Corner::TopLeft) => 26,
-        WallType::InnerCorner(Corner::TopRight) => 35,
-        WallType::InnerCorner(Corner::BottomRight) => 37,
-        WallType::InnerCorner(Corner::BottomLeft) => 36,
-        WallType::InnerCorner(Corner::TopLeft) => 34,
-        WallType::NestCorner(Corner::TopRight) => 31,
-        WallType::NestCorner(Corner::BottomRight) => 29,
-        WallType::NestCorner(Corner::BottomLeft) => 28,
-        WallType::NestCorner(Corner::TopLeft) => 30,
-        WallType::Inner => 44,
+/// The colour of the wall tiles in the sheet once whitened.
+const WHITE_WALL_COLOR: Color = Color::rgb(0.87, 0.87, 1.0);
+/// The colour of the pellets in the sheet.
+const PELLET_COLOR: Color = Color::rgb(0.98, 0.73, 0.69);
+
+/// The tint turning the pixels of a sprite drawn in [base] into [target].
+/// Sprites are tinted by multiplying their colour, in linear space.
+fn tint(base: Color, target: Color) -> Color {
+    let [base_r, base_g, base_b, _] = base.as_linear_rgba_f32();
+    let [r, g, b, a] = target.as_linear_rgba_f32();
+    Color::rgba_linear(r / base_r, g / base_g, b / base_b, a)
+}
+
+/// Draws the maze tiles in the colours of the current [MazePalette].
+pub fn tint_maze(
+    palette: Res<MazePalette>,
+    mut query: Query<(Ref<MapType>, &mut TextureAtlasSprite)>,
+) {
+    for (tile, mut sprite) in &mut query {
+        if !palette.is_changed() && !tile.is_changed() {
+            continue;
+        }
+
+        sprite.color = match *tile {
+            MapType::Wall(_) => tint(WHITE_WALL_COLOR, palette.walls),
+            MapType::Open(_) => tint(PELLET_COLOR, palette.pellets),
+            MapType::GhostOnlyBarrier => tint(WHITE_WALL_COLOR, palette.door),
+        };
+    }
+}
+
+/// Flashes the walls white while the level is being cleared.
+pub fn flash_maze(
+    time: Res<Time>,
+    mut clear: ResMut<LevelClear>,
+    mut palette: ResMut<MazePalette>,
+) {
+    clear.timer.tick(time.delta());
+    let walls = if clear.flash_on() {
+        Color::WHITE
+    } else {
+        clear.palette.walls
+    };
+    if palette.walls != walls {
+        palette.walls = walls;
     }
 }